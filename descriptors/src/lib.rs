@@ -115,6 +115,14 @@ bitfield! {
     pub volatile, set_volatile: 7;
 }
 
+impl MainItemSetting {
+    /// Returns the item data in its 2-byte form, with bit 8 (Buffered Bytes when set,
+    /// Bit Field otherwise) set as given. The bit doesn't fit in the 1-byte setting.
+    pub fn data_with_buffered_bytes(&self, buffered_bytes: bool) -> u16 {
+        self.0 as u16 | (buffered_bytes as u16) << 8
+    }
+}
+
 bitfield! {
     /// ItemPrefix describes the 1 byte prefix describing an item in a descriptor.
     pub struct ItemPrefix(u8);
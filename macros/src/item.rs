@@ -24,10 +24,19 @@ pub struct ReportUnaryField {
     pub bit_width: usize,
     pub descriptor_item: MainItem,
     pub ident: Ident,
+    // The report ID in effect when the field was emitted, if any.
+    pub report_id: Option<u8>,
+    // Number of booleans packed into the field, if declared with packed_bits.
+    pub packed_bits: Option<u16>,
+    // Length of the field, if it is an array.
+    pub array_len: Option<usize>,
+    // Element of the array field this item describes, for fields of a repeated group.
+    pub element: Option<usize>,
 }
 
 /// analyze_field constructs a main item from an item spec & field.
 pub fn analyze_field(field: Field, ft: Type, item: &ItemSpec) -> Result<ReportUnaryField> {
+    let is_array = matches!(ft, Type::Array(_));
     let (p, size) = parse_type(&field, ft)?;
 
     if p.path.segments.len() != 1 {
@@ -63,9 +72,13 @@ pub fn analyze_field(field: Field, ft: Type, item: &ItemSpec) -> Result<ReportUn
     }
 
     let mut output = unary_item(field.ident.clone().unwrap(), item.kind, bit_width);
+    if is_array {
+        output.array_len = Some(size);
+    }
 
     if let Some(want_bits) = item.want_bits {
         // bitpack
+        output.packed_bits = Some(want_bits);
         output.descriptor_item.logical_minimum = 0;
         output.descriptor_item.logical_maximum = 1;
         output.descriptor_item.report_count = want_bits;
@@ -84,12 +97,96 @@ pub fn analyze_field(field: Field, ft: Type, item: &ItemSpec) -> Result<ReportUn
         if remaining_bits > 0 {
             output.descriptor_item.padding_bits = Some(remaining_bits);
         }
+        if let Some(pad_bytes) = item.pad_bytes {
+            if pad_bytes * 8 != remaining_bits {
+                return Err(parse::Error::new(
+                    field.ident.unwrap().span(),
+                    format!(
+                        "`#[gen_hid_descriptor]` pad_bytes {} does not match the {} remaining bit(s)",
+                        pad_bytes, remaining_bits
+                    ),
+                ));
+            }
+        }
+    } else if item.pad_bytes.is_some() {
+        return Err(parse::Error::new(
+            field.ident.unwrap().span(),
+            "`#[gen_hid_descriptor]` pad_bytes can only be used with packed_bits",
+        ));
     } else {
         // array of reports
         type_setter.unwrap()(&mut output, bit_width);
         output.descriptor_item.report_count *= size as u16;
     }
 
+    if item.full_range {
+        if item.want_bits.is_some() {
+            return Err(parse::Error::new(
+                field.ident.unwrap().span(),
+                "`#[gen_hid_descriptor]` full_range can't be used with packed_bits",
+            ));
+        }
+        set_full_range(&mut output, sign == "i", bit_width);
+    }
+    if let Some(logical_minimum) = item.logical_min {
+        output.descriptor_item.logical_minimum = logical_minimum as isize;
+    }
+    if let Some(logical_maximum) = item.logical_max {
+        output.descriptor_item.logical_maximum = logical_maximum as isize;
+    }
+    // Each element of a variable item takes one designator, but the elements of an array
+    // item select theirs from the range, so it can be of any length.
+    let is_variable = match &item.settings {
+        Some(s) => s.is_variable(),
+        None => true, // Data,Var,Abs
+    };
+    match (item.designator_min, item.designator_max) {
+        (None, None) => {}
+        (Some(min), Some(max)) if max >= min && !is_variable => {}
+        (Some(min), Some(max))
+            if max >= min && max - min + 1 == output.descriptor_item.report_count as u32 => {}
+        (Some(_), Some(_)) => {
+            return Err(parse::Error::new(
+                field.ident.unwrap().span(),
+                format!(
+                "`#[gen_hid_descriptor]` designator range must span the {} element(s) of the field",
+                output.descriptor_item.report_count
+            ),
+            ))
+        }
+        _ => {
+            return Err(parse::Error::new(
+                field.ident.unwrap().span(),
+                "`#[gen_hid_descriptor]` designator_min and designator_max must be used together",
+            ))
+        }
+    }
+
+    Ok(output)
+}
+
+/// analyze_element constructs the main item of one element of an array field, for a group
+/// which is emitted `repeat` times.
+pub fn analyze_element(
+    field: Field,
+    item: &ItemSpec,
+    element: usize,
+    repeat: usize,
+) -> Result<ReportUnaryField> {
+    let elem = match &field.ty {
+        Type::Array(arr) if parse_type(&field, field.ty.clone())?.1 == repeat => (*arr.elem).clone(),
+        _ => {
+            return Err(parse::Error::new(
+                field.ident.unwrap().span(),
+                format!(
+                    "`#[gen_hid_descriptor]` fields of a group repeated {} times must be arrays of length {}",
+                    repeat, repeat
+                ),
+            ))
+        }
+    };
+    let mut output = analyze_field(field, elem, item)?;
+    output.element = Some(element);
     Ok(output)
 }
 
@@ -129,15 +226,33 @@ fn set_signed_unary_item(out: &mut ReportUnaryField, bit_width: usize) {
     out.descriptor_item.logical_maximum = bound;
 }
 
+/// set_full_range sets the logical range to every value of the backing type, ie: -128..=127
+/// for an i8, where set_signed_unary_item leaves out the most negative value.
+fn set_full_range(out: &mut ReportUnaryField, signed: bool, bit_width: usize) {
+    if signed {
+        let min = -(1i64 << (bit_width - 1));
+        out.descriptor_item.logical_minimum = min as isize;
+        out.descriptor_item.logical_maximum = (-min - 1) as isize;
+    } else {
+        set_unsigned_unary_item(out, bit_width);
+    }
+}
+
 fn set_unsigned_unary_item(out: &mut ReportUnaryField, bit_width: usize) {
     out.descriptor_item.logical_minimum = 0;
-    out.descriptor_item.logical_maximum = 2u32.pow(bit_width as u32) as isize - 1;
+    // Logical values are signed 32-bit integers, so a u32 field is limited to the
+    // largest positive one.
+    out.descriptor_item.logical_maximum = ((1u64 << bit_width) - 1).min(i32::MAX as u64) as isize;
 }
 
 fn unary_item(id: Ident, kind: MainItemKind, bit_width: usize) -> ReportUnaryField {
     ReportUnaryField {
         ident: id,
         bit_width,
+        report_id: None,
+        packed_bits: None,
+        array_len: None,
+        element: None,
         descriptor_item: MainItem {
             kind,
             logical_minimum: 0,
@@ -5,10 +5,10 @@ extern crate alloc;
 extern crate proc_macro;
 extern crate usbd_hid_descriptors;
 
-use alloc::{boxed::Box, vec, vec::Vec};
+use alloc::{boxed::Box, format, vec, vec::Vec};
 use proc_macro::TokenStream;
 use proc_macro2::Span;
-use quote::quote;
+use quote::{format_ident, quote};
 use syn::punctuated::Punctuated;
 use syn::token::Bracket;
 use syn::{parse, parse_macro_input, Expr, Fields, ItemStruct};
@@ -22,7 +22,10 @@ use spec::*;
 mod item;
 use item::*;
 mod packer;
-use packer::{gen_serializer, uses_report_ids};
+use packer::{
+    fields_len, gen_bit_accessors, gen_serializer, gen_unpacker, report_len, uses_report_ids,
+    wire_len,
+};
 
 /// Attribute to generate a HID descriptor & serialization code
 ///
@@ -34,19 +37,50 @@ use packer::{gen_serializer, uses_report_ids};
 ///
 /// The generated HID descriptor will be available as a `&[u8]` by calling
 /// `YourStructType::desc()`. `YourStructType` also now implements `SerializedDescriptor`.
+/// For use in const contexts, the descriptor is also available as
+/// `YourStructType::DESC`, a `[u8; YourStructType::DESC_LEN]` constant.
+/// `SerializedDescriptor::TOP_COLLECTION` holds the type of the outermost collection, ie:
+/// `Some(0x01)` for Application, or `None` where several top-level collections are declared.
 ///
 /// As long as a descriptor describes only input or output types, and a report ID is
 /// not used, the wire format for transmitting and recieving the data described by the
 /// descriptor is simply the packed representation of the struct itself.
 /// Where report ID's are used anywhere in the descriptor, you must prepend the relevant
 /// report ID to the packed representation of the struct prior to transmission.
+/// To do that, a wrapper type named `YourStructTypeId<report ID>` (ie: `YourStructTypeId1`) is
+/// generated for every report ID which contains input fields. Wrapping a reference to your struct
+/// in it serializes only the input fields of that report, prefixed by the report ID.
+/// When every field belongs to the same report, as in a vendor-defined collection with a
+/// single `report_id`, `YourStructType` itself also implements `AsInputReport`: its `Serialize`
+/// implementation covers the fields, and `serialize_into()` (and so `push_input()`) writes the
+/// report ID ahead of them.
+/// Several top-level collections may be declared as siblings, ie: a keyboard and a system
+/// control collection, in which case each must declare a `report_id`.
+/// `YourStructType::REPORT_IDS` lists the report IDs declared by the descriptor, and when there
+/// is only one, `YourStructType::REPORT_ID` holds it.
+///
+/// `YourStructType::expected_set_report_len(report_id, report_type)` returns the length of
+/// the given report including its report ID prefix, ie: to validate the length of a received
+/// SET_REPORT. It returns None for reports the descriptor doesn't declare. Descriptors without
+/// report IDs use report ID 0.
 ///
 /// If inputs and outputs are mixed within the same HID descriptor, then only the struct
 /// fields used in that direction can be present in a payload being transmitted in that
-/// direction.
+/// direction. Input and output fields may be interleaved in any order: each report holds the
+/// fields of its direction in declaration order, which is also the order of their items in the
+/// generated descriptor. The packed representation of such a struct does not match either
+/// report, so use the generated serialization and `TryFrom` implementations rather than
+/// transmitting the struct's bytes directly.
 ///
 /// If report ID's are not used, input (device-to-host) serialization code is generated
 /// automatically, and is represented by the implementation of the `AsInputReport` trait.
+/// In that case `YourStructType::MAX_INPUT_LEN` holds the length of the serialized input
+/// report, and `to_array()` serializes the report into a `[u8; MAX_INPUT_LEN]`. If the descriptor
+/// contains output fields, `TryFrom<&[u8]>` and the `AsOutputReport` trait are also implemented
+/// to unpack a received output report into the struct, ie: with `HIDClass::pull_output`,
+/// returning a `ParseError` if the length does not match. A compile-time
+/// assertion also checks that the size of the struct matches the size of the report fields
+/// described by the descriptor, catching fields which are missing from the descriptor.
 ///
 /// # Examples
 ///
@@ -126,6 +160,30 @@ use packer::{gen_serializer, uses_report_ids};
 ///  - Variable (`variable`) - as opposed to an array
 ///  - Relative (`relative`) - as opposed to absolute
 ///
+/// - Variable number of elements
+///
+/// ```ignore
+/// #[gen_hid_descriptor(
+///     (collection = APPLICATION, usage_page = VENDOR_DEFINED_START, usage = 0x01) = {
+///         (usage = 0x01,) = {
+///             #[logical_max 8] count=input;
+///         };
+///         (usage = 0x02,) = {
+///             samples=input;
+///         };
+///     }
+/// )]
+/// struct CustomSamples {
+///     count: u8,
+///     samples: [u8; 8],
+/// }
+/// ```
+///
+/// The report count of an item is fixed by the descriptor, so a variable number of elements is
+/// sent as an array sized for the maximum, prefixed by a field holding the number of valid
+/// elements. The host must read `count` to know how many `samples` are valid; the remaining
+/// elements are still transmitted, and should be zeroed.
+///
 /// # Supported struct types
 ///
 /// The struct following the attribute must consist entirely of named fields, using
@@ -136,7 +194,16 @@ use packer::{gen_serializer, uses_report_ids};
 ///  - u32 / i32
 ///
 /// `LOGICAL_MINIMUM` & `LOGICAL_MAXIMUM` are automatically set in the descriptor, based
-/// on the type & whether `#[packed_bits]` was set on the field or not.
+/// on the type & whether `#[packed_bits]` was set on the field or not. Signed fields leave out
+/// the most negative value, ie: -127..=127 for an i8, and the `#[full_range]` sub-attribute
+/// extends them to the full range of the type, ie: -128..=127. It can't be used together with
+/// `#[packed_bits]`. The
+/// `#[logical_min <value>]` and `#[logical_max <value>]` sub-attributes set them explicitly
+/// for a single field, ie: `#[logical_min -64] #[logical_max 191] throttle=input;`.
+/// `#[percent]` is shorthand for `#[logical_min 0] #[logical_max 100]`, for fields such as
+/// a battery level or volume. The `logical_min` and `logical_max` group parameters set them
+/// for every field within the group which doesn't set its own, ie:
+/// `(usage_min = 0x81, usage_max = 0xB7, logical_min = 0x81, logical_max = 0xB7) = { keys=input; }`.
 ///
 /// # Descriptor format
 ///
@@ -163,50 +230,166 @@ use packer::{gen_serializer, uses_report_ids};
 ///
 /// Note: All collection specs must end in a semicolon, except the top-level one.
 ///
-/// Note: Parameters are a tuple, so make sure you have a trailing comma if you only have one
-/// parameter.
+/// Note: Parameters are a tuple, a trailing comma is optional if you only have one parameter.
 ///
 /// The valid parameters are `collection`, `usage_page`, `usage`, `usage_min`, `usage_max`,
-/// `unit_exponent`, and `report_id`.
+/// `unit`, `unit_exponent`, `logical_min`, `logical_max`, `physical_min`, `physical_max`,
+/// `repeat`, and `report_id`.
 /// These simply configure parameters that apply to contained items in the report.
+/// Values are a numeric literal or a named constant such as `GENERIC_DESKTOP`. Pages and usages
+/// without a named constant can always be given numerically, ie: `usage_page = 0x41` for the
+/// Braille Display page; the error for an unknown constant lists the known ones.
+/// `usage` may be repeated to assign one usage to each bit of a following `packed_bits` item,
+/// ie: `(usage = PLAY_PAUSE, usage = SCAN_NEXT_TRACK) = { #[packed_bits 2] buttons=input; }`
+/// or `(usage = NUM_LOCK, usage = CAPS_LOCK) = { #[packed_bits 2] leds=output; }`,
+/// or to each element of a following array, ie: `(usage = X, usage = Y, usage = Z) = { accel=input; }`
+/// for an `accel: [i16; 3]` field.
+/// `usage`, `usage_min` and `usage_max` also accept 32-bit extended usages, which hold the
+/// usage page in the high 16 bits (ie: `0x000C0514`), and are emitted in the 4-byte form.
 /// Use of the `collection` parameter automatically creates a collection feature for all items
 /// which are contained within it, and other parameters specified in the same collection-spec
 /// apply to the collection, not directly to the elements of the collection (ie: defining a
 /// collection + a usage generates a descriptor where the usage is set on the collection, not the
-/// items contained within the collection).
+/// items contained within the collection). This includes `usage_min` & `usage_max`, which are
+/// emitted before the collection item like the usage: to declare the usages of the items
+/// within a collection, use a nested group, ie:
+/// `(collection = PHYSICAL, usage = 0x02) = { (usage_min = 0x10, usage_max = 0x12) = { ... }; }`.
+/// `physical_min` and `physical_max` set the Physical Minimum / Maximum of the items within the
+/// group, ie: `(physical_min = -90, physical_max = 90) = { x=input; y=input; }` for axes
+/// which span 180 degrees. `physical_max` may be given alone for a range starting at 0, but
+/// `physical_min` requires it. They are reset to 0 (the logical extents) for the items
+/// following the group. Only these, `logical_min`, `logical_max` and `unit_exponent` may be
+/// negative.
+/// `unit` and `unit_exponent` likewise set the Unit and Unit Exponent (-8 to 7) of the items
+/// within the group, ie: `(unit = DEGREES, physical_max = 359) = { heading=input; }`. The unit
+/// is either a nibble-packed unit code or one of the named units `SI_LINEAR`, `SI_ROTATION`,
+/// `ENGLISH_LINEAR`, `ENGLISH_ROTATION`, `CENTIMETERS`, `RADIANS`, `INCHES`, `DEGREES`, `GRAMS`,
+/// `SECONDS`, `KELVIN`, `VOLTS`, `CENTIMETERS_PER_SECOND` and `DEGREES_PER_SECOND`. The SI
+/// system measures length in centimeters and mass in grams, so `VOLTS` takes a unit exponent
+/// of 7 for volts, or 4 for millivolts. The exponent may also be given as its 4-bit two's
+/// complement encoding, ie: `unit_exponent = 0x0E` for -2.
+/// Both are reset to 0 (no unit) for the items following the group.
+/// `repeat = <n>` emits the group `n` times, ie: once per contact of a touch screen, and must
+/// be at least 1. Each field within it must be an array of length `n`, and instance `i`
+/// describes element `i` of every field, ie:
+/// `(collection = LOGICAL, usage = FINGER, repeat = 2) = { contact_id=input; }` for a
+/// `contact_id: [u8; 2]` field. Repeated groups can't be nested.
 ///
 /// ## `item-spec`:
 ///
 /// ```ignore
-///     #[packed_bits <num_items>] #[item_settings <setting>,...] <fieldname>=input OR output;
+///     #[packed_bits <num_items>] #[item_settings <setting>,...] <fieldname>=input OR output OR feature;
 /// ```
 ///
-/// The two sub-attributes are both optional.
+/// The sub-attributes are all optional.
 ///
 ///   - `fieldname` refers to the name of a field within the struct. All fields must be specified.
 ///   - `input` fields are sent in reports from device to host. `output` fields are sent in reports
 ///     from host to device. This matches the terminology used in the USB & HID specifications.
+///   - `feature` fields are configuration the host both reads (GET_REPORT) and writes
+///     (SET_REPORT). Where report IDs are not used, `AsFeatureReport` is implemented to
+///     serialize and unpack them, ie: with `HIDClass::push_feature` and `pull_feature`.
 ///   - `packed_bits` configures the field as a set of `num_items` booleans rather than a number.
 ///     If the number of packed bits is less than the natural bit width of the field, the
 ///     remaining most-significant bits are set as constants within the report and are not used.
-///     `packed_bits` is typically used to implement buttons.
+///     `packed_bits` is typically used to implement buttons. `<fieldname>_bit(n)` and
+///     `set_<fieldname>_bit(n, value)` methods are generated to access the individual bits.
+///   - `pad_bytes` emits the unused bits of a `packed_bits` field as `pad_bytes` constant bytes
+///     (Report Size 8) rather than as single bits, ie: `#[packed_bits 8] #[pad_bytes 3] buttons`
+///     for a `u32` field. Either way the padding is a single constant item, this only changes
+///     its report size & count, ie: to match a reference descriptor declaring reserved bytes.
 ///   - `item_settings` describes settings on the input/output item, as enumerated in section
 ///     6.2.2.5 of the [HID specification, version 1.11](https://www.usb.org/sites/default/files/documents/hid1_11.pdf).
 ///     By default, all items are configured as `(Data,Var,Abs,No Wrap,Linear,Preferred State,No Null Position)`.
+///     Setting `buffered_bytes` emits the item data in its 2-byte form, as the flag is bit 8.
+///   - `usage` and `designator` emit a Usage / Designator Index local item (a numeric literal,
+///     or for `usage` a recognized constant) just before the field's input/output item, ie:
+///     `#[usage 0x30] #[designator 1] x=input;`.
+///   - `usage_min` and `usage_max` emit a Usage Minimum / Usage Maximum local item just before
+///     the field's input/output item, so an array can declare its usage range without a
+///     wrapping group, ie: `#[usage_min 0x00] #[usage_max 0xDD] keycodes=input;`.
+///   - `designator_min` and `designator_max` likewise emit a Designator Minimum / Maximum local
+///     item, assigning one designator of a physical descriptor to each element of the field,
+///     ie: `#[designator_min 1] #[designator_max 5] finger_bend=input;` for a `[u8; 5]` field.
+///     For variable items the range must span the elements of the field; the elements of an
+///     array item select their designator from the range, so it can be of any length.
+///   - `unit_exponent` sets the Unit Exponent global (-8 to 7) from this field to the end of
+///     its group, ie: `#[unit_exponent -1] temperature=input; #[unit_exponent 2] pressure=input;`.
+///     It is only emitted when it differs from the exponent already in effect.
+///   - `usage_page` switches the usage page for just this field, ie:
+///     `#[usage_page CONSUMER] #[usage AC_PAN] pan=input;`. The previous usage page is
+///     restored after the field's input/output item.
+///
+/// ## Byte order
+///
+/// Multi-byte fields are serialized in little-endian order, as required by the HID
+/// specification. For vendor-defined reports which are interpreted by a custom host
+/// application, `byte_order(big)` can be given before the top-level collection to serialize
+/// (and unpack) every multi-byte field in big-endian order instead, ie:
+/// `#[gen_hid_descriptor(byte_order(big), (collection = APPLICATION, ...) = { ... })]`.
+/// `packed_bits` fields are unaffected, as their bits are numbered from the start of the report.
+///
+/// ## Bit packing
+///
+/// By default, the bits of each `packed_bits` field which are not in use are padded out
+/// to the width of its type. `bit_packing(report)` given before the top-level collection
+/// instead packs the bits of consecutive `packed_bits` fields next to each other, and pads
+/// the combined bits with a single constant item, ie: a `#[packed_bits 3]` field followed
+/// by a `#[packed_bits 8]` field makes 11 bits and 5 bits of padding, rather than 3 + 5 bits
+/// and 8 bits. The serialized report is then shorter than the struct. Bits which share
+/// bytes must total at most 64 bits.
+///
+/// ## Struct layout
+///
+/// The struct is given `#[repr(C, packed)]`, so that its layout matches the report. Taking a
+/// reference to a field of a packed struct may be unaligned, which faults on some
+/// architectures. `aligned` given before the top-level collection keeps the struct
+/// `#[repr(C)]` instead, ie: `#[gen_hid_descriptor(aligned, (collection = APPLICATION, ...) = { ... })]`.
+/// The generated serialization copies each field by value, so the wire format is the same,
+/// but the struct may then be larger than the report and its bytes must not be transmitted
+/// directly.
+///
+/// ## Report padding
+///
+/// Some host drivers expect reports of a fixed length. `pad_to(<bytes>)` given before the
+/// top-level collection appends a constant item to each report which is shorter than that,
+/// ie: `#[gen_hid_descriptor(pad_to(8), (collection = APPLICATION, ...) = { ... })]`. The
+/// padding is not part of the struct: `serialize_into()` and `to_array()` write it as zero
+/// bytes, and received output reports must have the padded length. `pad_to` can't be used
+/// with report IDs.
 ///
 /// ## Quirks
 ///
 /// By default generated descriptors are such to maximize compatibility. To change this
 /// behaviour, you can use a `#[quirks <settings>]` attribute on the relevant input/output
-/// item.
-/// For now, the only quirk is `#[quirks allow_short]`, which allows global features to be
-/// serialized in a 1 byte form. This is disabled by default as the Windows HID parser
-/// considers it invalid.
+/// item. The quirks are `allow_short`, `implicit_logical_min` and `wide_usage_page`, described
+/// below; `long_form` turns `allow_short` back off.
+///
+/// `#[quirks allow_short]` allows global features to be serialized in a 1 byte form. This is
+/// disabled by default as the Windows HID parser considers it invalid.
+///
+/// A `#[quirks(<settings>)]` attribute placed on the struct (after `#[gen_hid_descriptor]`)
+/// applies to every item of the descriptor, ie: `#[quirks(allow_short)]` for the smallest
+/// descriptor. Items with their own `#[quirks]` attribute don't use the struct's quirks, and
+/// can opt back into the default encoding with `#[quirks long_form]`.
+///
+/// `#[quirks(wide_usage_page)]` on the struct emits every Usage Page item in its 2-byte form,
+/// ie: `0x06, 0x01, 0x00` rather than `0x05, 0x01` for Generic Desktop, for hosts which
+/// expect it.
+///
+/// `#[quirks(implicit_logical_min)]` omits the Logical Minimum item of the first item when
+/// it is 0, saving two bytes. This relies on the host's parser starting with a zeroed global
+/// state, which the HID specification does not state explicitly, so verify the descriptor
+/// with the hosts you target before using it.
 #[proc_macro_attribute]
 pub fn gen_hid_descriptor(args: TokenStream, input: TokenStream) -> TokenStream {
-    let decl = parse_macro_input!(input as ItemStruct);
+    let mut decl = parse_macro_input!(input as ItemStruct);
     let spec = parse_macro_input!(args as GroupSpec);
     let ident = decl.ident.clone();
+    let quirks = match take_descriptor_quirks(&mut decl.attrs) {
+        Ok(quirks) => quirks,
+        Err(e) => return e.to_compile_error().into(),
+    };
 
     // Error if the struct doesn't name its fields.
     match decl.fields {
@@ -223,29 +406,205 @@ pub fn gen_hid_descriptor(args: TokenStream, input: TokenStream) -> TokenStream
 
     let do_serialize = !uses_report_ids(&Spec::Collection(spec.clone()));
 
-    let output = match compile_descriptor(spec, &decl.fields) {
+    // Hosts tell the reports of several top-level collections apart by their report IDs.
+    let top_level_collections: Vec<u32> = spec
+        .fields
+        .values()
+        .filter_map(|f| match f {
+            Spec::Collection(g) => g.collection,
+            _ => None,
+        })
+        .collect();
+    if top_level_collections.len() > 1
+        && spec.fields.values().any(|f| match f {
+            Spec::Collection(g) => g.collection.is_some() && !uses_report_ids(f),
+            _ => false,
+        })
+    {
+        return parse::Error::new(
+            ident.span(),
+            "`#[gen_hid_descriptor]` each of multiple top-level collections requires a report ID",
+        )
+        .to_compile_error()
+        .into();
+    }
+    let byte_order = spec.byte_order;
+    let pad_to = spec.pad_to;
+    let aligned = spec.aligned;
+    let repr = match aligned {
+        true => quote!(#[repr(C)]),
+        false => quote!(#[repr(C, packed)]),
+    };
+    if pad_to.is_some() && !do_serialize {
+        return parse::Error::new(
+            ident.span(),
+            "`#[gen_hid_descriptor]` pad_to is not supported with report IDs",
+        )
+        .to_compile_error()
+        .into();
+    }
+
+    let output = match compile_descriptor(spec, &decl.fields, quirks) {
         Ok(d) => d,
         Err(e) => return e.to_compile_error().into(),
     };
     let (descriptor, fields) = output;
+    let desc_len = descriptor.elems.len();
+    let top_collection = match top_level_collections.as_slice() {
+        [collection] => {
+            let collection = *collection as u8;
+            quote!(Some(#collection))
+        }
+        _ => quote!(None),
+    };
+    let bit_accessors = gen_bit_accessors(&fields, &decl.fields);
+    let set_report_lens = gen_set_report_lens(&fields, pad_to);
 
     let mut out = quote! {
         #[derive(Debug, Clone, Copy, Eq, PartialEq)]
-        #[repr(C, packed)]
+        #repr
         #decl
 
+        impl #ident {
+            /// Length in bytes of the report descriptor.
+            pub const DESC_LEN: usize = #desc_len;
+            /// The report descriptor, usable in const contexts.
+            pub const DESC: [u8; #desc_len] = #descriptor;
+        }
+
+        #[allow(dead_code)]
+        impl #ident {
+            #bit_accessors
+
+            /// Returns the length in bytes of a SET_REPORT request for the given report, as
+            /// described by the descriptor, or None if the descriptor doesn't declare it.
+            /// The length includes the report ID prefix if the descriptor uses report IDs.
+            pub fn expected_set_report_len(report_id: u8, report_type: ReportType) -> Option<usize> {
+                #set_report_lens
+            }
+        }
+
         impl SerializedDescriptor for #ident {
+            const TOP_COLLECTION: Option<u8> = #top_collection;
+
             fn desc() -> &'static[u8] {
-                &#descriptor
+                &Self::DESC
             }
         }
     };
 
-    if do_serialize {
-        let input_serializer = match gen_serializer(fields, MainItemKind::Input) {
+    if do_serialize
+        && fields
+            .iter()
+            .any(|f| f.descriptor_item.kind == MainItemKind::Output)
+    {
+        let output_unpacker = gen_unpacker(
+            &fields,
+            MainItemKind::Output,
+            &decl.fields,
+            byte_order,
+            pad_to,
+        );
+        out = quote! {
+            #out
+
+            impl ::core::convert::TryFrom<&[u8]> for #ident {
+                type Error = UsbError;
+
+                /// Unpacks an output report. Input fields are set to zero.
+                fn try_from(buf: &[u8]) -> ::core::result::Result<Self, Self::Error> {
+                    #output_unpacker
+                }
+            }
+
+            impl AsOutputReport for #ident {
+                fn from_output_report(buf: &[u8]) -> ::core::result::Result<Self, UsbError> {
+                    Self::try_from(buf)
+                }
+            }
+        };
+    }
+
+    if do_serialize
+        && fields
+            .iter()
+            .any(|f| f.descriptor_item.kind == MainItemKind::Feature)
+    {
+        let mut feature_len = report_len(&fields, MainItemKind::Feature);
+        let mut padded_len = quote!();
+        if let Some(pad_to) = pad_to {
+            feature_len = feature_len.max(pad_to as usize);
+            padded_len = quote!(const PADDED_LEN: usize = #feature_len;);
+        }
+        let feature_serializer = match gen_serializer(
+            fields.clone(),
+            MainItemKind::Feature,
+            quote!(self),
+            None,
+            byte_order,
+        ) {
             Ok(s) => s,
             Err(e) => return e.to_compile_error().into(),
         };
+        let feature_unpacker = gen_unpacker(
+            &fields,
+            MainItemKind::Feature,
+            &decl.fields,
+            byte_order,
+            pad_to,
+        );
+        out = quote! {
+            #out
+
+            impl AsFeatureReport for #ident {
+                #padded_len
+
+                fn serialize_feature<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+                where
+                    S: Serializer,
+                {
+                    #feature_serializer
+                }
+
+                fn try_from_feature(buf: &[u8]) -> ::core::result::Result<Self, UsbError> {
+                    #feature_unpacker
+                }
+            }
+
+            impl #ident {
+                /// Length in bytes of the serialized feature report.
+                pub const MAX_FEATURE_LEN: usize = #feature_len;
+            }
+        };
+    }
+
+    if do_serialize {
+        let struct_len = fields_len(&fields);
+        let mut input_len = report_len(&fields, MainItemKind::Input);
+        // Reports padded with pad_to are zero-filled by serialize_into.
+        let mut padded_len = quote!();
+        if let Some(pad_to) = pad_to {
+            if input_len > 0 {
+                input_len = input_len.max(pad_to as usize);
+                padded_len = quote!(const PADDED_LEN: usize = #input_len;);
+            }
+        }
+        // Every byte of a packed struct must be described by the descriptor, otherwise the
+        // wire format differs from the struct layout. Aligned structs may hold padding.
+        let size_check = match aligned {
+            true => quote!(),
+            false => quote! {
+                const _: () = assert!(
+                    core::mem::size_of::<#ident>() == #struct_len,
+                    "struct size does not match the size of the report fields in the descriptor",
+                );
+            },
+        };
+        let input_serializer =
+            match gen_serializer(fields, MainItemKind::Input, quote!(self), None, byte_order) {
+                Ok(s) => s,
+                Err(e) => return e.to_compile_error().into(),
+            };
 
         out = quote! {
             #out
@@ -258,18 +617,207 @@ pub fn gen_hid_descriptor(args: TokenStream, input: TokenStream) -> TokenStream
                     #input_serializer
                 }
             }
-            impl AsInputReport for #ident {}
+            impl AsInputReport for #ident {
+                #padded_len
+            }
+
+            #size_check
+
+
+            impl #ident {
+                /// Length in bytes of the serialized input report.
+                pub const MAX_INPUT_LEN: usize = #input_len;
+
+                /// Serializes the input report into an array of exactly `MAX_INPUT_LEN` bytes.
+                pub fn to_array(&self) -> [u8; Self::MAX_INPUT_LEN] {
+                    let mut buf = [0u8; Self::MAX_INPUT_LEN];
+                    // The buffer is sized to fit the report, so this can never overflow.
+                    let _ = AsInputReport::serialize_into(self, &mut buf);
+                    buf
+                }
+            }
         };
+    } else {
+        // Generate a wrapper type per report ID, which serializes the input fields of
+        // that report prefixed by the report ID.
+        let mut report_ids: Vec<u8> = Vec::new();
+        for f in fields.iter() {
+            if let (MainItemKind::Input, Some(id)) = (f.descriptor_item.kind, f.report_id) {
+                if !report_ids.contains(&id) {
+                    report_ids.push(id);
+                }
+            }
+        }
+
+        // Every report ID the descriptor declares, in declaration order.
+        let mut all_report_ids: Vec<u8> = Vec::new();
+        for id in fields.iter().filter_map(|f| f.report_id) {
+            if !all_report_ids.contains(&id) {
+                all_report_ids.push(id);
+            }
+        }
+        out = quote! {
+            #out
+
+            impl #ident {
+                /// The report IDs declared by the descriptor, in declaration order.
+                pub const REPORT_IDS: &'static [u8] = &[#(#all_report_ids),*];
+            }
+        };
+        if let [report_id] = all_report_ids.as_slice() {
+            out = quote! {
+                #out
+
+                impl #ident {
+                    /// The report ID of the descriptor's only report.
+                    pub const REPORT_ID: u8 = #report_id;
+                }
+            };
+        }
+
+        // If every field belongs to the same report, the struct itself is that report.
+        let single_report_id = match report_ids.as_slice() {
+            [id] if fields.iter().all(|f| f.report_id == Some(*id)) => Some(*id),
+            _ => None,
+        };
+
+        let vis = &decl.vis;
+        for report_id in report_ids {
+            let report_fields: Vec<ReportUnaryField> = fields
+                .iter()
+                .filter(|f| f.report_id == Some(report_id))
+                .cloned()
+                .collect();
+            let input_len = 1 + report_len(&report_fields, MainItemKind::Input);
+            let input_serializer = match gen_serializer(
+                report_fields.clone(),
+                MainItemKind::Input,
+                quote!(self.0),
+                Some(report_id),
+                byte_order,
+            ) {
+                Ok(s) => s,
+                Err(e) => return e.to_compile_error().into(),
+            };
+
+            if single_report_id == Some(report_id) {
+                let struct_serializer = match gen_serializer(
+                    report_fields,
+                    MainItemKind::Input,
+                    quote!(self),
+                    None,
+                    byte_order,
+                ) {
+                    Ok(s) => s,
+                    Err(e) => return e.to_compile_error().into(),
+                };
+                out = quote! {
+                    #out
+
+                    impl Serialize for #ident {
+                        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+                        where
+                            S: Serializer,
+                        {
+                            #struct_serializer
+                        }
+                    }
+                    impl AsInputReport for #ident {
+                        const REPORT_ID_PREFIX: Option<u8> = Some(#report_id);
+                    }
+
+                    impl #ident {
+                        /// Length in bytes of the serialized input report, including the report ID.
+                        pub const MAX_INPUT_LEN: usize = #input_len;
+
+                        /// Serializes the input report into an array of exactly `MAX_INPUT_LEN` bytes.
+                        pub fn to_array(&self) -> [u8; Self::MAX_INPUT_LEN] {
+                            let mut buf = [0u8; Self::MAX_INPUT_LEN];
+                            // The buffer is sized to fit the report, so this can never overflow.
+                            let _ = AsInputReport::serialize_into(self, &mut buf);
+                            buf
+                        }
+                    }
+                };
+            }
+            let wrapper = format_ident!("{}Id{}", ident, report_id);
+
+            out = quote! {
+                #out
+
+                /// Input report with the report ID prefix, containing only the fields of that report.
+                #vis struct #wrapper<'a>(pub &'a #ident);
+
+                impl Serialize for #wrapper<'_> {
+                    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+                    where
+                        S: Serializer,
+                    {
+                        #input_serializer
+                    }
+                }
+                impl AsInputReport for #wrapper<'_> {}
+
+                impl #wrapper<'_> {
+                    /// The report ID which prefixes the serialized input report.
+                    pub const REPORT_ID: u8 = #report_id;
+                    /// Length in bytes of the serialized input report, including the report ID.
+                    pub const MAX_INPUT_LEN: usize = #input_len;
+                }
+            };
+        }
     }
 
     TokenStream::from(out)
 }
 
+/// gen_set_report_lens generates the body of expected_set_report_len(), matching each report
+/// ID and type declared by the fields to the length of the report.
+fn gen_set_report_lens(
+    fields: &[ReportUnaryField],
+    pad_to: Option<u16>,
+) -> proc_macro2::TokenStream {
+    let mut reports: Vec<(Option<u8>, MainItemKind)> = Vec::new();
+    for field in fields {
+        let report = (field.report_id, field.descriptor_item.kind);
+        if !reports.contains(&report) {
+            reports.push(report);
+        }
+    }
+
+    let arms = reports.iter().map(|(report_id, kind)| {
+        let report_fields: Vec<ReportUnaryField> = fields
+            .iter()
+            .filter(|f| f.report_id == *report_id && f.descriptor_item.kind == *kind)
+            .cloned()
+            .collect();
+        let len =
+            wire_len(&report_fields).max(pad_to.unwrap_or(0) as usize) + report_id.map_or(0, |_| 1);
+        let id = report_id.unwrap_or(0);
+        let report_type = match kind {
+            MainItemKind::Input => quote!(ReportType::Input),
+            MainItemKind::Output => quote!(ReportType::Output),
+            _ => quote!(ReportType::Feature),
+        };
+        quote!((#id, #report_type) => Some(#len),)
+    });
+    quote! {
+        match (report_id, report_type) {
+            #(#arms)*
+            _ => None,
+        }
+    }
+}
+
 fn compile_descriptor(
     spec: GroupSpec,
     fields: &Fields,
+    quirks: ItemQuirks,
 ) -> Result<(PatSlice, Vec<ReportUnaryField>)> {
     let mut compiler = DescCompilation {
+        quirks,
+        pad_to: spec.pad_to,
+        bit_packing: spec.bit_packing,
         ..Default::default()
     };
     let mut elems = Punctuated::new();
@@ -289,10 +837,43 @@ fn compile_descriptor(
 
 #[derive(Default)]
 struct DescCompilation {
+    // Quirks applied to items which don't set their own.
+    quirks: ItemQuirks,
     logical_minimum: Option<isize>,
+    // Set while emitting a group which specifies logical_min, overriding the
+    // logical minimum derived from the type of the fields it contains.
+    logical_minimum_override: Option<isize>,
     logical_maximum: Option<isize>,
+    // Likewise for a group which specifies logical_max.
+    logical_maximum_override: Option<isize>,
+    physical_minimum: Option<isize>,
+    physical_maximum: Option<isize>,
+    // Set while emitting a group which specifies physical_min / physical_max. Items
+    // outside of it use the initial physical extents of 0, meaning the logical extents.
+    physical_minimum_override: Option<isize>,
+    physical_maximum_override: Option<isize>,
     report_size: Option<u16>,
     report_count: Option<u16>,
+    report_id: Option<u8>,
+    usage_page: Option<u32>,
+    unit: Option<u32>,
+    unit_exponent: Option<u32>,
+    // Set while emitting a group which specifies unit / unit_exponent, or from an item
+    // which specifies unit_exponent to the end of its group. Items outside of it use
+    // the initial unit & exponent of 0, meaning no unit.
+    unit_override: Option<u32>,
+    unit_exponent_override: Option<u32>,
+    // Length in bytes the reports are padded to at the end of the top-level group.
+    pad_to: Option<u16>,
+    // Number of nested groups being emitted.
+    depth: usize,
+    bit_packing: BitPacking,
+    // Bits of packed_bits fields emitted without padding, per report kind, which are
+    // yet to be padded to a byte boundary. Only used with bit_packing(report).
+    pending_bits: Vec<(MainItemKind, usize)>,
+    // Element of the array fields being emitted, and the number of elements, while
+    // emitting a repeated group.
+    element: Option<(usize, usize)>,
     processed_fields: Vec<ReportUnaryField>,
 }
 
@@ -309,11 +890,21 @@ impl DescCompilation {
         signed: bool,
     ) {
         // println!("buf: {:?}", buf);
-        if buf[1..4] == [0, 0, 0] && !(signed && buf[0] == 255) {
+        // Signed data is sign-extended by the host, so the shorter forms can be used for
+        // any value in their two's complement range, ie: -200 is 0x38 0xFF.
+        let value = i32::from_le_bytes(buf);
+        let fits = |len: usize| match signed {
+            true => {
+                let bound = 1i32 << (len * 8 - 1);
+                (-bound..bound).contains(&value)
+            }
+            false => buf[len..4].iter().all(|b| *b == 0),
+        };
+        if fits(1) {
             prefix.set_byte_count(1);
             elems.push(byte_literal(prefix.0));
             elems.push(byte_literal(buf[0]));
-        } else if buf[2..4] == [0, 0] && !(signed && buf[1] == 255) {
+        } else if fits(2) {
             prefix.set_byte_count(2);
             elems.push(byte_literal(prefix.0));
             elems.push(byte_literal(buf[0]));
@@ -366,16 +957,21 @@ impl DescCompilation {
         item: MainItem,
         quirks: ItemQuirks,
     ) {
-        if self.logical_minimum.is_none() || self.logical_minimum.unwrap() != item.logical_minimum {
+        let logical_minimum = item.logical_minimum;
+        if self.logical_minimum.is_none() && logical_minimum == 0 && quirks.implicit_logical_min {
+            // Zero is the initial value of the global, so needs no item.
+            self.logical_minimum = Some(0);
+        }
+        if self.logical_minimum.is_none() || self.logical_minimum.unwrap() != logical_minimum {
             self.emit_item(
                 elems,
                 ItemType::Global.into(),
                 GlobalItemKind::LogicalMin.into(),
-                item.logical_minimum,
+                logical_minimum,
                 true,
                 quirks.allow_short_form,
             );
-            self.logical_minimum = Some(item.logical_minimum);
+            self.logical_minimum = Some(logical_minimum);
         }
         if self.logical_maximum.is_none() || self.logical_maximum.unwrap() != item.logical_maximum {
             self.emit_item(
@@ -388,6 +984,54 @@ impl DescCompilation {
             );
             self.logical_maximum = Some(item.logical_maximum);
         }
+        let physical_minimum = self.physical_minimum_override.unwrap_or(0);
+        if self.physical_minimum.unwrap_or(0) != physical_minimum {
+            self.emit_item(
+                elems,
+                ItemType::Global.into(),
+                GlobalItemKind::PhysicalMin.into(),
+                physical_minimum,
+                true,
+                quirks.allow_short_form,
+            );
+            self.physical_minimum = Some(physical_minimum);
+        }
+        let physical_maximum = self.physical_maximum_override.unwrap_or(0);
+        if self.physical_maximum.unwrap_or(0) != physical_maximum {
+            self.emit_item(
+                elems,
+                ItemType::Global.into(),
+                GlobalItemKind::PhysicalMax.into(),
+                physical_maximum,
+                true,
+                quirks.allow_short_form,
+            );
+            self.physical_maximum = Some(physical_maximum);
+        }
+        let unit = self.unit_override.unwrap_or(0);
+        if self.unit.unwrap_or(0) != unit {
+            self.emit_item(
+                elems,
+                ItemType::Global.into(),
+                GlobalItemKind::Unit.into(),
+                unit as isize,
+                false,
+                quirks.allow_short_form,
+            );
+            self.unit = Some(unit);
+        }
+        let unit_exponent = self.unit_exponent_override.unwrap_or(0);
+        if self.unit_exponent.unwrap_or(0) != unit_exponent {
+            self.emit_item(
+                elems,
+                ItemType::Global.into(),
+                GlobalItemKind::UnitExponent.into(),
+                unit_exponent as isize,
+                false,
+                false,
+            );
+            self.unit_exponent = Some(unit_exponent);
+        }
         if self.report_size.is_none() || self.report_size.unwrap() != item.report_size {
             self.emit_item(
                 elems,
@@ -418,9 +1062,97 @@ impl DescCompilation {
         i: &ItemSpec,
         item: MainItem,
     ) {
-        self.handle_globals(elems, item.clone(), i.quirks);
+        let parent_usage_page = self.usage_page;
+        if let Some(usage_page) = i.usage_page.filter(|p| Some(*p) != parent_usage_page) {
+            self.emit_usage_page(elems, usage_page);
+        }
+        if let Some(usage) = i.usage {
+            self.emit_item(
+                elems,
+                ItemType::Local.into(),
+                LocalItemKind::Usage.into(),
+                usage as isize,
+                false,
+                false,
+            );
+        }
+        if let Some(usage_min) = i.usage_min {
+            self.emit_item(
+                elems,
+                ItemType::Local.into(),
+                LocalItemKind::UsageMin.into(),
+                usage_min as isize,
+                false,
+                false,
+            );
+        }
+        if let Some(usage_max) = i.usage_max {
+            self.emit_item(
+                elems,
+                ItemType::Local.into(),
+                LocalItemKind::UsageMax.into(),
+                usage_max as isize,
+                false,
+                false,
+            );
+        }
+        if let Some(designator_index) = i.designator_index {
+            self.emit_item(
+                elems,
+                ItemType::Local.into(),
+                LocalItemKind::DesignatorIdx.into(),
+                designator_index as isize,
+                false,
+                false,
+            );
+        }
+        if let (Some(designator_min), Some(designator_max)) = (i.designator_min, i.designator_max) {
+            self.emit_item(
+                elems,
+                ItemType::Local.into(),
+                LocalItemKind::DesignatorMin.into(),
+                designator_min as isize,
+                false,
+                false,
+            );
+            self.emit_item(
+                elems,
+                ItemType::Local.into(),
+                LocalItemKind::DesignatorMax.into(),
+                designator_max as isize,
+                false,
+                false,
+            );
+        }
+        if let Some(unit_exponent) = i.unit_exponent {
+            // The exponent is encoded as a 4-bit two's complement value (section 6.2.2.7).
+            let unit_exponent = (unit_exponent & 0x0F) as u32;
+            self.unit_exponent_override = Some(unit_exponent);
+            if self.unit_exponent != Some(unit_exponent) {
+                self.unit_exponent = Some(unit_exponent);
+                self.emit_item(
+                    elems,
+                    ItemType::Global.into(),
+                    GlobalItemKind::UnitExponent.into(),
+                    unit_exponent as isize,
+                    false,
+                    false,
+                );
+            }
+        }
+        // A logical minimum or maximum set on the item takes precedence over the group's.
+        let mut item = item;
+        if let (None, Some(logical_minimum)) = (i.logical_min, self.logical_minimum_override) {
+            item.logical_minimum = logical_minimum;
+        }
+        if let (None, Some(logical_maximum)) = (i.logical_max, self.logical_maximum_override) {
+            item.logical_maximum = logical_maximum;
+        }
+
+        let quirks = i.quirks.unwrap_or(self.quirks);
+        self.handle_globals(elems, item.clone(), quirks);
         let item_data = match &i.settings {
-            Some(s) => s.0 as isize,
+            Some(s) => s.data_with_buffered_bytes(i.buffered_bytes) as isize,
             None => 0x02, // 0x02 = Data,Var,Abs
         };
         self.emit_item(
@@ -429,17 +1161,22 @@ impl DescCompilation {
             item.kind.into(),
             item_data,
             true,
-            i.quirks.allow_short_form,
+            quirks.allow_short_form,
         );
 
         if let Some(padding) = item.padding_bits {
-            // Make another item of type constant to carry the remaining bits.
+            // Make another item of type constant to carry the remaining bits, as whole
+            // bytes if requested (analyze_field checked they add up).
+            let (report_size, report_count) = match i.pad_bytes {
+                Some(pad_bytes) => (8, pad_bytes),
+                None => (1, padding),
+            };
             let padding = MainItem {
-                report_size: 1,
-                report_count: padding,
+                report_size,
+                report_count,
                 ..item
             };
-            self.handle_globals(elems, padding, i.quirks);
+            self.handle_globals(elems, padding, quirks);
 
             let mut const_settings = MainItemSetting(0);
             const_settings.set_constant(true);
@@ -450,39 +1187,167 @@ impl DescCompilation {
                 item.kind.into(),
                 const_settings.0 as isize,
                 true,
-                i.quirks.allow_short_form,
+                quirks.allow_short_form,
             );
         }
+
+        // The usage page only applies to this item, restore the group's.
+        if let Some(usage_page) = parent_usage_page {
+            if self.usage_page != parent_usage_page {
+                self.emit_usage_page(elems, usage_page);
+            }
+        }
     }
 
-    fn emit_group(
+    fn emit_usage_page(&mut self, elems: &mut Punctuated<Pat, syn::token::Comma>, usage_page: u32) {
+        self.usage_page = Some(usage_page);
+        if self.quirks.wide_usage_page && usage_page <= 0xFFFF {
+            let mut prefix = ItemPrefix(0);
+            prefix.set_tag(GlobalItemKind::UsagePage.into());
+            prefix.set_type(ItemType::Global.into());
+            prefix.set_byte_count(2);
+            elems.push(byte_literal(prefix.0));
+            elems.push(byte_literal(usage_page as u8));
+            elems.push(byte_literal((usage_page >> 8) as u8));
+            return;
+        }
+        self.emit_item(
+            elems,
+            ItemType::Global.into(),
+            GlobalItemKind::UsagePage.into(),
+            usage_page as isize,
+            false,
+            false,
+        );
+    }
+
+    // emit_report_padding emits a constant item padding each report to pad_to bytes.
+    fn emit_report_padding(
         &mut self,
         elems: &mut Punctuated<Pat, syn::token::Comma>,
-        spec: &GroupSpec,
-        fields: &Fields,
     ) -> Result<()> {
-        // println!("GROUP: {:?}", spec);
+        let pad_to = match self.pad_to {
+            Some(pad_to) => pad_to as usize,
+            None => return Ok(()),
+        };
+        // The padding belongs inside the last top-level collection, if any.
+        let end_collection = match elems.last() {
+            Some(last) if *last == byte_literal(0xc0) => elems.pop(),
+            _ => None,
+        };
+        for kind in [
+            MainItemKind::Input,
+            MainItemKind::Output,
+            MainItemKind::Feature,
+        ] {
+            let fields: Vec<ReportUnaryField> = self
+                .processed_fields
+                .iter()
+                .filter(|f| f.descriptor_item.kind == kind)
+                .cloned()
+                .collect();
+            if fields.is_empty() {
+                continue;
+            }
+            let len = wire_len(&fields);
+            if len > pad_to {
+                return Err(parse::Error::new(
+                    fields[0].ident.span(),
+                    format!("report is {} bytes, longer than pad_to({})", len, pad_to),
+                ));
+            }
+            if len == pad_to {
+                continue;
+            }
 
-        if let Some(usage_page) = spec.usage_page {
+            let padding = MainItem {
+                kind,
+                logical_minimum: self.logical_minimum.unwrap_or(0),
+                logical_maximum: self.logical_maximum.unwrap_or(0),
+                report_size: 8,
+                report_count: (pad_to - len) as u16,
+                padding_bits: None,
+            };
+            self.handle_globals(elems, padding, self.quirks);
+
+            let mut const_settings = MainItemSetting(0);
+            const_settings.set_constant(true);
+            const_settings.set_variable(true);
             self.emit_item(
                 elems,
-                ItemType::Global.into(),
-                GlobalItemKind::UsagePage.into(),
-                usage_page as isize,
-                false,
-                false,
+                ItemType::Main.into(),
+                kind.into(),
+                const_settings.0 as isize,
+                true,
+                self.quirks.allow_short_form,
             );
         }
-        for usage in &spec.usage {
+        if let Some(end_collection) = end_collection {
+            elems.push(end_collection.into_value());
+        }
+        Ok(())
+    }
+
+    // emit_shared_bits_padding emits a single constant item padding the packed bits of the
+    // given kind (or every kind) emitted so far to a byte boundary.
+    fn emit_shared_bits_padding(
+        &mut self,
+        elems: &mut Punctuated<Pat, syn::token::Comma>,
+        kind: Option<MainItemKind>,
+    ) {
+        let pending: Vec<(MainItemKind, usize)> = self
+            .pending_bits
+            .iter()
+            .filter(|(k, _)| kind.is_none() || kind == Some(*k))
+            .cloned()
+            .collect();
+        self.pending_bits
+            .retain(|(k, _)| kind.is_some() && kind != Some(*k));
+
+        for (kind, bits) in pending {
+            if bits.is_multiple_of(8) {
+                continue;
+            }
+            let padding_bits = (8 - bits % 8) as u16;
+            // The padding is accounted to the last field, as if it was its own.
+            if let Some(field) = self
+                .processed_fields
+                .iter_mut()
+                .rev()
+                .find(|f| f.descriptor_item.kind == kind)
+            {
+                field.descriptor_item.padding_bits = Some(padding_bits);
+            }
+
+            let padding = MainItem {
+                kind,
+                logical_minimum: 0,
+                logical_maximum: 1,
+                report_size: 1,
+                report_count: padding_bits,
+                padding_bits: None,
+            };
+            self.handle_globals(elems, padding, self.quirks);
+
+            let mut const_settings = MainItemSetting(0);
+            const_settings.set_constant(true);
+            const_settings.set_variable(true);
             self.emit_item(
                 elems,
-                ItemType::Local.into(),
-                LocalItemKind::Usage.into(),
-                *usage as isize,
-                false,
-                false,
+                ItemType::Main.into(),
+                kind.into(),
+                const_settings.0 as isize,
+                true,
+                self.quirks.allow_short_form,
             );
         }
+    }
+
+    fn emit_usage_range(
+        &mut self,
+        elems: &mut Punctuated<Pat, syn::token::Comma>,
+        spec: &GroupSpec,
+    ) {
         if let Some(usage_min) = spec.usage_min {
             self.emit_item(
                 elems,
@@ -503,7 +1368,60 @@ impl DescCompilation {
                 false,
             );
         }
+    }
+
+    fn emit_group(
+        &mut self,
+        elems: &mut Punctuated<Pat, syn::token::Comma>,
+        spec: &GroupSpec,
+        fields: &Fields,
+    ) -> Result<()> {
+        // println!("GROUP: {:?}", spec);
+        if let Some(repeat) = spec.repeat {
+            if self.element.is_some() {
+                return Err(parse::Error::new(
+                    Span::call_site(),
+                    "`#[gen_hid_descriptor]` repeated groups can't be nested",
+                ));
+            }
+            let once = GroupSpec {
+                repeat: None,
+                ..spec.clone()
+            };
+            // Each instance starts from the usage page in effect before the first, as does
+            // whatever follows the instances.
+            let usage_page = self.usage_page;
+            for element in 0..repeat as usize {
+                self.element = Some((element, repeat as usize));
+                self.emit_group(elems, &once, fields)?;
+                if let Some(usage_page) = usage_page.filter(|p| Some(*p) != self.usage_page) {
+                    self.emit_usage_page(elems, usage_page);
+                }
+            }
+            self.element = None;
+            return Ok(());
+        }
+        self.depth += 1;
+
+        if let Some(usage_page) = spec.usage_page {
+            self.emit_usage_page(elems, usage_page);
+        }
+        for usage in &spec.usage {
+            self.emit_item(
+                elems,
+                ItemType::Local.into(),
+                LocalItemKind::Usage.into(),
+                *usage as isize,
+                false,
+                false,
+            );
+        }
+        self.emit_usage_range(elems, spec);
         if let Some(report_id) = spec.report_id {
+            // Packed bits can't share bytes across reports.
+            self.emit_shared_bits_padding(elems, None);
+            // Report ID is a global item, so it applies to all following items.
+            self.report_id = Some(report_id as u8);
             self.emit_item(
                 elems,
                 ItemType::Global.into(),
@@ -523,37 +1441,83 @@ impl DescCompilation {
                 false,
             );
         }
+        let parent_logical_minimum_override = self.logical_minimum_override;
         if let Some(logical_minimum) = spec.logical_min {
-            // Set to 0 to indicate that we've already set the default
-            // See handle_globals
-            self.logical_minimum = Some(0);
+            // Items within this group use the given logical minimum rather than
+            // the one derived from their type. See emit_field
+            self.logical_minimum = Some(logical_minimum as isize);
+            self.logical_minimum_override = Some(logical_minimum as isize);
             self.emit_item(
                 elems,
                 ItemType::Global.into(),
                 GlobalItemKind::LogicalMin.into(),
                 logical_minimum as isize,
-                false,
+                true,
                 false,
             );
         }
-        if let Some(unit_exponent) = spec.unit_exponent {
+        let parent_logical_maximum_override = self.logical_maximum_override;
+        if let Some(logical_maximum) = spec.logical_max {
+            self.logical_maximum = Some(logical_maximum as isize);
+            self.logical_maximum_override = Some(logical_maximum as isize);
             self.emit_item(
                 elems,
                 ItemType::Global.into(),
-                GlobalItemKind::UnitExponent.into(),
-                unit_exponent as isize,
-                false,
+                GlobalItemKind::LogicalMax.into(),
+                logical_maximum as isize,
+                true,
                 false,
             );
         }
+        // The physical extents are emitted along with the other globals of the items within
+        // this group, and reset for the items following it. See handle_globals
+        let parent_physical_overrides = (
+            self.physical_minimum_override,
+            self.physical_maximum_override,
+        );
+        if let Some(physical_minimum) = spec.physical_min {
+            self.physical_minimum_override = Some(physical_minimum as isize);
+        }
+        if let Some(physical_maximum) = spec.physical_max {
+            self.physical_maximum_override = Some(physical_maximum as isize);
+        }
+        // Likewise for the unit, which also covers the exponents set by items within
+        // this group.
+        let parent_unit_overrides = (self.unit_override, self.unit_exponent_override);
+        if let Some(unit) = spec.unit {
+            self.unit_override = Some(unit);
+        }
+        if let Some(unit_exponent) = spec.unit_exponent {
+            // The exponent is encoded as a 4-bit two's complement value (section 6.2.2.7).
+            self.unit_exponent_override = Some((unit_exponent & 0x0F) as u32);
+        }
 
         for name in spec.clone() {
             let f = spec.get(name.clone()).unwrap();
             match f {
                 Spec::MainItem(i) => {
                     let d = field_decl(fields, name);
-                    match analyze_field(d.clone(), d.ty, i) {
-                        Ok(item) => {
+                    let analyzed = match self.element {
+                        Some((element, repeat)) => analyze_element(d, i, element, repeat),
+                        None => analyze_field(d.clone(), d.ty, i),
+                    };
+                    match analyzed {
+                        Ok(mut item) => {
+                            item.report_id = self.report_id;
+                            let kind = item.descriptor_item.kind;
+                            if self.bit_packing == BitPacking::Report && item.packed_bits.is_some()
+                            {
+                                // The bits are padded along with those of the following
+                                // fields, see emit_shared_bits_padding.
+                                item.descriptor_item.padding_bits = None;
+                                let bits = item.descriptor_item.report_count as usize;
+                                match self.pending_bits.iter_mut().find(|(k, _)| *k == kind) {
+                                    Some((_, pending)) => *pending += bits,
+                                    None => self.pending_bits.push((kind, bits)),
+                                }
+                            } else {
+                                self.emit_shared_bits_padding(elems, Some(kind));
+                            }
                             self.processed_fields.push(item.clone());
                             self.emit_field(elems, i, item.descriptor_item)
                         }
@@ -566,10 +1530,27 @@ impl DescCompilation {
             }
         }
 
+        self.depth -= 1;
+        if spec.collection.is_some() || self.depth == 0 {
+            self.emit_shared_bits_padding(elems, None);
+        }
         if spec.collection.is_some() {
             // Close collection.
             elems.push(byte_literal(0xc0));
         }
+        if self.depth == 0 {
+            self.emit_report_padding(elems)?;
+        }
+
+        // The override only applies to items within this group, siblings must
+        // emit their own logical minimum again.
+        self.logical_minimum_override = parent_logical_minimum_override;
+        self.logical_maximum_override = parent_logical_maximum_override;
+        (
+            self.physical_minimum_override,
+            self.physical_maximum_override,
+        ) = parent_physical_overrides;
+        (self.unit_override, self.unit_exponent_override) = parent_unit_overrides;
         Ok(())
     }
 }
@@ -1,11 +1,11 @@
 extern crate usbd_hid_descriptors;
 use usbd_hid_descriptors::*;
 
-use alloc::vec::Vec;
+use alloc::{format, vec::Vec};
 
-use proc_macro2::TokenStream;
-use quote::quote;
-use syn::{parse, Ident, Index, Result};
+use proc_macro2::{Literal, TokenStream};
+use quote::{format_ident, quote};
+use syn::{parse, Fields, Index, Result, Type};
 
 use crate::item::*;
 use crate::spec::*;
@@ -26,38 +26,202 @@ pub fn uses_report_ids(spec: &Spec) -> bool {
     }
 }
 
-fn make_unary_serialize_invocation(bits: usize, ident: Ident, signed: bool) -> TokenStream {
-    match (bits, signed) {
-        (8, false) => quote!({ s.serialize_element(&(self.#ident as u8))?; }),
-        (16, false) => quote!({ s.serialize_element(&(self.#ident as u16))?; }),
-        (32, false) => quote!({ s.serialize_element(&(self.#ident as u32))?; }),
-        (8, true) => quote!({ s.serialize_element(&(self.#ident as i8))?; }),
-        (16, true) => quote!({ s.serialize_element(&(self.#ident as i16))?; }),
-        (32, true) => quote!({ s.serialize_element(&(self.#ident as i32))?; }),
-        _ => quote!(),
+/// field_value returns an expression reading the field from `receiver`, or the element of
+/// the array field it describes, for fields of a repeated group.
+fn field_value(receiver: &TokenStream, field: &ReportUnaryField) -> TokenStream {
+    let ident = &field.ident;
+    match field.element {
+        Some(element) => {
+            let element = Index::from(element);
+            quote!({ #receiver.#ident }[#element])
+        }
+        None => quote!(#receiver.#ident),
+    }
+}
+
+fn make_unary_serialize_invocation(
+    bits: usize,
+    receiver: &TokenStream,
+    field: &ReportUnaryField,
+    signed: bool,
+    byte_order: Endianness,
+) -> TokenStream {
+    make_value_serialize_invocation(bits, field_value(receiver, field), signed, byte_order)
+}
+
+/// make_value_serialize_invocation serializes the value of the given expression, ie: an
+/// element of an array field.
+fn make_value_serialize_invocation(
+    bits: usize,
+    expr: TokenStream,
+    signed: bool,
+    byte_order: Endianness,
+) -> TokenStream {
+    let value = match (bits, signed) {
+        (8, false) => quote!((#expr as u8)),
+        (16, false) => quote!((#expr as u16)),
+        (32, false) => quote!((#expr as u32)),
+        (8, true) => quote!((#expr as i8)),
+        (16, true) => quote!((#expr as i16)),
+        (32, true) => quote!((#expr as i32)),
+        _ => return quote!(),
+    };
+    // The serializer writes little-endian values, so swapping the bytes of the value
+    // puts it on the wire in big-endian order.
+    match byte_order {
+        Endianness::Big if bits > 8 => quote!({ s.serialize_element(&#value.swap_bytes())?; }),
+        _ => quote!({ s.serialize_element(&#value)?; }),
+    }
+}
+
+/// field_bits returns the number of bits the field occupies in the report, including the
+/// padding emitted after it.
+fn field_bits(field: &ReportUnaryField) -> usize {
+    let item = &field.descriptor_item;
+    item.report_size as usize * item.report_count as usize + item.padding_bits.unwrap_or(0) as usize
+}
+
+/// report_len returns the number of bytes the serializer generated by
+/// gen_serializer writes for the given fields.
+pub fn report_len(fields: &[ReportUnaryField], typ: MainItemKind) -> usize {
+    let bits: usize = fields
+        .iter()
+        .filter(|f| f.descriptor_item.kind == typ)
+        .map(|f| {
+            let item = &f.descriptor_item;
+            if item.report_size == 8 && item.report_count > 32 {
+                // Not serialized, see gen_serializer
+                return 0;
+            }
+            field_bits(f)
+        })
+        .sum();
+    bits / 8
+}
+
+/// wire_len returns the number of bytes the given fields occupy in the report, as
+/// described by the descriptor.
+pub fn wire_len(fields: &[ReportUnaryField]) -> usize {
+    fields.iter().map(field_bits).sum::<usize>() / 8
+}
+
+/// fields_len returns the number of bytes the given fields occupy in the struct.
+pub fn fields_len(fields: &[ReportUnaryField]) -> usize {
+    fields
+        .iter()
+        .map(|f| f.bit_width / 8 * f.array_len.unwrap_or(1))
+        .sum()
+}
+
+/// shares_bytes returns true if the packed bits of the field, placed `bit_offset` bits
+/// into the report, share bytes with the fields around it (see bit_packing(report)).
+fn shares_bytes(field: &ReportUnaryField, bit_offset: usize) -> bool {
+    field.packed_bits.is_some()
+        && (!bit_offset.is_multiple_of(8) || !field_bits(field).is_multiple_of(8))
+}
+
+/// gen_shared_bits_serializer serializes a run of packed_bits fields which share bytes,
+/// by gathering their bits into a single integer.
+fn gen_shared_bits_serializer(
+    run: &[ReportUnaryField],
+    receiver: &TokenStream,
+) -> Result<TokenStream> {
+    let total_bits: usize = run.iter().map(field_bits).sum();
+    if total_bits > 64 {
+        return Err(parse::Error::new(
+            run[0].ident.span(),
+            "`#[gen_hid_descriptor]` packed bits sharing bytes must total at most 64 bits",
+        ));
+    }
+
+    let mut terms = Vec::new();
+    let mut offset = 0;
+    for field in run {
+        let ident = field.ident.clone();
+        let packed_bits = field.packed_bits.unwrap_or(0) as usize;
+        for i in 0..field.array_len.unwrap_or(1) {
+            let start = i * field.bit_width;
+            if start >= packed_bits {
+                break;
+            }
+            let value = match field.array_len {
+                Some(_) => {
+                    let i = Index::from(i);
+                    quote!({ #receiver.#ident }[#i])
+                }
+                None => {
+                    let value = field_value(receiver, field);
+                    quote!({ #value })
+                }
+            };
+            let width = field.bit_width.min(packed_bits - start);
+            let mask = Literal::u64_unsuffixed(u64::MAX >> (64 - width));
+            let shift = Literal::usize_unsuffixed(offset + start);
+            terms.push(quote!(((#value as u64) & #mask) << #shift));
+        }
+        offset += packed_bits;
     }
+
+    let bytes = (0..total_bits / 8).map(|i| {
+        let shift = Literal::usize_unsuffixed(i * 8);
+        quote!(s.serialize_element(&((bits >> #shift) as u8))?;)
+    });
+    Ok(quote!({
+        let bits: u64 = #(#terms)|*;
+        #(#bytes)*
+    }))
 }
 
-pub fn gen_serializer(fields: Vec<ReportUnaryField>, typ: MainItemKind) -> Result<TokenStream> {
+/// gen_serializer generates the body of a serialize() implementation, which serializes the
+/// fields of the given kind read from `receiver` (ie: `self`). If a report ID is given, it is
+/// serialized before the fields.
+pub fn gen_serializer(
+    fields: Vec<ReportUnaryField>,
+    typ: MainItemKind,
+    receiver: TokenStream,
+    report_id: Option<u8>,
+    byte_order: Endianness,
+) -> Result<TokenStream> {
     let mut elems = Vec::new();
+    if let Some(report_id) = report_id {
+        elems.push(quote!({ s.serialize_element(&#report_id)?; }));
+    }
 
+    // Packed bits which share bytes (see bit_packing(report)) are serialized together,
+    // once the run of fields ends on a byte boundary.
+    let mut run = Vec::new();
+    let mut run_bits = 0;
     for field in fields {
         if field.descriptor_item.kind != typ {
             continue;
         }
+        if !run.is_empty() || shares_bytes(&field, 0) {
+            run_bits += field_bits(&field);
+            run.push(field);
+            if run_bits.is_multiple_of(8) {
+                elems.push(gen_shared_bits_serializer(&run, &receiver)?);
+                run.clear();
+                run_bits = 0;
+            }
+            continue;
+        }
         let signed = field.descriptor_item.logical_minimum < 0;
 
         let rc = match field.descriptor_item.report_size {
             1 => {
+                // Packed bits are numbered from the least significant bit of the report,
+                // so they are always little-endian.
                 if field.descriptor_item.report_count == 1 {
                     elems.push(make_unary_serialize_invocation(
                         field.bit_width,
-                        field.ident.clone(),
+                        &receiver,
+                        &field,
                         signed,
+                        Endianness::Little,
                     ));
                 } else {
-                    let ident = field.ident.clone();
-                    elems.push(quote!({ s.serialize_element(&self.#ident)?; }));
+                    let value = field_value(&receiver, &field);
+                    elems.push(quote!({ s.serialize_element(&{ #value })?; }));
                 }
                 Ok(())
             }
@@ -65,13 +229,11 @@ pub fn gen_serializer(fields: Vec<ReportUnaryField>, typ: MainItemKind) -> Resul
                 // u8 / i8
                 if field.descriptor_item.report_count == 1 {
                     elems.push(make_unary_serialize_invocation(
-                        8,
-                        field.ident.clone(),
-                        signed,
+                        8, &receiver, &field, signed, byte_order,
                     ));
                 } else if field.descriptor_item.report_count <= 32 {
                     let ident = field.ident.clone();
-                    elems.push(quote!({ s.serialize_element(&self.#ident)?; }));
+                    elems.push(quote!({ s.serialize_element(&{ #receiver.#ident })?; }));
                 } else {
                     // XXX - don't attempt to serialize arrays larger than 32
                     //       (not supported by serde, yet)
@@ -83,16 +245,26 @@ pub fn gen_serializer(fields: Vec<ReportUnaryField>, typ: MainItemKind) -> Resul
                 if field.descriptor_item.report_count == 1 {
                     elems.push(make_unary_serialize_invocation(
                         field.descriptor_item.report_size as usize,
-                        field.ident.clone(),
+                        &receiver,
+                        &field,
                         signed,
+                        byte_order,
                     ));
-                    Ok(())
                 } else {
-                    Err(parse::Error::new(
-                        field.ident.span(),
-                        "Arrays of 16/32bit fields not supported",
-                    ))
+                    // Each element is serialized on its own, so it can be converted to
+                    // the byte order of the report.
+                    let ident = field.ident.clone();
+                    for i in 0..field.descriptor_item.report_count as usize {
+                        let i = Index::from(i);
+                        elems.push(make_value_serialize_invocation(
+                            field.descriptor_item.report_size as usize,
+                            quote!({ #receiver.#ident }[#i]),
+                            signed,
+                            byte_order,
+                        ));
+                    }
                 }
+                Ok(())
             }
             _ => Err(parse::Error::new(
                 field.ident.span(),
@@ -112,3 +284,190 @@ pub fn gen_serializer(fields: Vec<ReportUnaryField>, typ: MainItemKind) -> Resul
         s.end()
     }))
 }
+
+/// gen_unpacker generates the body of a try_from() implementation, which unpacks the
+/// fields of the given kind from `buf` into a new instance of the struct. Fields of other
+/// kinds are set to zero. If pad_to is given, the report is expected to be padded to that
+/// many bytes.
+pub fn gen_unpacker(
+    fields: &[ReportUnaryField],
+    typ: MainItemKind,
+    decl: &Fields,
+    byte_order: Endianness,
+    pad_to: Option<u16>,
+) -> TokenStream {
+    let fields: Vec<ReportUnaryField> = fields
+        .iter()
+        .filter(|f| f.descriptor_item.kind == typ)
+        .cloned()
+        .collect();
+
+    // Fields are laid out in the report in the order they appear in the descriptor.
+    let mut offsets = Vec::new();
+    let mut offset = 0;
+    for field in fields.iter() {
+        offsets.push(offset);
+        offset += field_bits(field);
+    }
+    let report_len = (offset / 8).max(pad_to.unwrap_or(0) as usize);
+
+    let inits = decl.iter().map(|decl_field| {
+        let ident = decl_field.ident.clone().unwrap();
+        let (elem, array_len) = match &decl_field.ty {
+            Type::Array(arr) => (&*arr.elem, Some(&arr.len)),
+            ty => (ty, None),
+        };
+
+        // Fields of a repeated group describe one element each.
+        let described: Vec<(&ReportUnaryField, usize)> = fields
+            .iter()
+            .zip(offsets.iter().copied())
+            .filter(|(f, _)| f.ident == ident)
+            .collect();
+        if described.is_empty() {
+            return match array_len {
+                Some(len) => quote!(#ident: [0; #len]),
+                None => quote!(#ident: 0),
+            };
+        }
+
+        let mut values = Vec::new();
+        for (field, offset) in described {
+            values.extend(unpack_field_values(field, offset, elem, byte_order));
+        }
+        match array_len {
+            Some(_) => quote!(#ident: [#(#values),*]),
+            None => quote!(#ident: #(#values)*),
+        }
+    });
+
+    quote!({
+        if buf.len() != #report_len {
+            return Err(UsbError::ParseError);
+        }
+        Ok(Self {
+            #(#inits),*
+        })
+    })
+}
+
+/// unpack_field_values returns an expression unpacking each element of the field (a single
+/// one if it isn't an array) from `buf`, where the field starts `offset` bits into the report.
+fn unpack_field_values(
+    field: &ReportUnaryField,
+    offset: usize,
+    elem: &Type,
+    byte_order: Endianness,
+) -> Vec<TokenStream> {
+    if shares_bytes(field, offset) {
+        return (0..field.array_len.unwrap_or(1))
+            .map(|i| {
+                let packed_bits = field.packed_bits.unwrap_or(0) as usize;
+                let start = i * field.bit_width;
+                if start >= packed_bits {
+                    return quote!(0);
+                }
+                // Gather the bytes holding the bits of this element, then shift them out.
+                let width = field.bit_width.min(packed_bits - start);
+                let start = offset + start;
+                let bytes = (start / 8..=(start + width - 1) / 8)
+                    .enumerate()
+                    .map(|(n, b)| {
+                        let b = Literal::usize_unsuffixed(b);
+                        let shift = Literal::usize_unsuffixed(n * 8);
+                        quote!((buf[#b] as u64) << #shift)
+                    });
+                let shift = Literal::usize_unsuffixed(start % 8);
+                let mask = Literal::u64_unsuffixed(u64::MAX >> (64 - width));
+                quote!(((#(#bytes)|*) >> #shift & #mask) as #elem)
+            })
+            .collect();
+    }
+
+    let offset = offset / 8;
+    let elem_bytes = field.bit_width / 8;
+    let count = field_bits(field) / 8 / elem_bytes;
+    (0..count)
+        .map(|i| {
+            let start = offset + i * elem_bytes;
+            let bytes = (start..start + elem_bytes).map(Literal::usize_unsuffixed);
+            match (byte_order, field.packed_bits) {
+                (Endianness::Big, None) => quote!(<#elem>::from_be_bytes([#(buf[#bytes]),*])),
+                _ => quote!(<#elem>::from_le_bytes([#(buf[#bytes]),*])),
+            }
+        })
+        .collect()
+}
+
+/// gen_bit_accessors generates `<field>_bit(n)` and `set_<field>_bit(n, value)` methods for
+/// every field declared with packed_bits. Bits beyond the declared count read as false, and
+/// setting them has no effect.
+pub fn gen_bit_accessors(fields: &[ReportUnaryField], decl: &Fields) -> TokenStream {
+    let accessors = decl.iter().filter_map(|decl_field| {
+        let ident = decl_field.ident.clone().unwrap();
+        let field = fields.iter().find(|f| f.ident == ident && f.element.is_none())?;
+        let packed_bits = Literal::usize_unsuffixed(field.packed_bits? as usize);
+        let vis = &decl_field.vis;
+        let getter = format_ident!("{}_bit", ident);
+        let setter = format_ident!("set_{}_bit", ident);
+        let getter_doc = format!(
+            "Returns the state of bit `n` of `{}`, or false if `n` is not one of its {} packed bits.",
+            ident, packed_bits
+        );
+        let setter_doc = format!(
+            "Sets bit `n` of `{}`. Bits beyond its {} packed bits are left unchanged.",
+            ident, packed_bits
+        );
+
+        // Fields are copied in & out, as references to fields of a packed struct are not allowed.
+        let (get, set) = match &decl_field.ty {
+            Type::Array(arr) => {
+                let elem = &arr.elem;
+                let elem_bits = Literal::usize_unsuffixed(field.bit_width);
+                (
+                    quote!({
+                        let bits = self.#ident;
+                        (bits[n / #elem_bits] >> (n % #elem_bits)) & 1 != 0
+                    }),
+                    quote!({
+                        let mut bits = self.#ident;
+                        let mask: #elem = 1 << (n % #elem_bits);
+                        if value {
+                            bits[n / #elem_bits] |= mask;
+                        } else {
+                            bits[n / #elem_bits] &= !mask;
+                        }
+                        self.#ident = bits;
+                    }),
+                )
+            }
+            ty => (
+                quote!({ ({ self.#ident } >> n) & 1 != 0 }),
+                quote!({
+                    let mask: #ty = 1 << n;
+                    self.#ident = if value {
+                        self.#ident | mask
+                    } else {
+                        self.#ident & !mask
+                    };
+                }),
+            ),
+        };
+
+        Some(quote! {
+            #[doc = #getter_doc]
+            #vis fn #getter(&self, n: usize) -> bool {
+                n < #packed_bits && #get
+            }
+
+            #[doc = #setter_doc]
+            #vis fn #setter(&mut self, n: usize, value: bool) {
+                if n < #packed_bits #set
+            }
+        })
+    });
+
+    quote! {
+        #(#accessors)*
+    }
+}
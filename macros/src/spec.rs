@@ -4,7 +4,7 @@ use quote::quote;
 use syn::parse::{Parse, ParseStream};
 use syn::punctuated::Punctuated;
 use syn::{parse, Attribute, Expr, ExprAssign, ExprPath, Path, Result, Token};
-use syn::{Block, ExprBlock, ExprLit, ExprTuple, Lit, Stmt};
+use syn::{Block, ExprBlock, ExprLit, ExprParen, ExprTuple, ExprUnary, Lit, Stmt, UnOp};
 
 use alloc::{
     borrow::ToOwned,
@@ -30,15 +30,57 @@ pub enum Spec {
 #[derive(Debug, Clone, Default, Copy)]
 pub struct ItemQuirks {
     pub allow_short_form: bool,
+    // Omit a Logical Minimum of 0 on the first item, relying on the parser's initial state.
+    pub implicit_logical_min: bool,
+    // Emit Usage Page items in their 2-byte form, even for pages below 0x100.
+    pub wide_usage_page: bool,
+}
+
+// Endianness describes the byte order of multi-byte fields in serialized reports.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Endianness {
+    #[default]
+    Little,
+    Big,
+}
+
+// BitPacking describes how the unused bits of packed_bits fields are padded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BitPacking {
+    // Each field is padded to the width of its type.
+    #[default]
+    Field,
+    // Consecutive fields share bytes, and only the combined bits are padded.
+    Report,
 }
 
 // ItemSpec describes settings that apply to a single field.
 #[derive(Debug, Clone, Default)]
 pub struct ItemSpec {
     pub kind: MainItemKind,
-    pub quirks: ItemQuirks,
+    // Quirks set on the item, overriding the descriptor-level quirks.
+    pub quirks: Option<ItemQuirks>,
     pub settings: Option<MainItemSetting>,
+    // Sets bit 8 of the item data, which MainItemSetting can't hold.
+    pub buffered_bytes: bool,
     pub want_bits: Option<u16>,
+    // Emit the padding following packed bits as this many whole bytes.
+    pub pad_bytes: Option<u16>,
+    pub full_range: bool,
+    pub logical_min: Option<i32>,
+    pub logical_max: Option<i32>,
+    // Unit exponent in effect from this item to the end of its group, -8 to 7.
+    pub unit_exponent: Option<i32>,
+    // Usage page in effect for this item only, restored afterwards.
+    pub usage_page: Option<u32>,
+
+    // Local items, emitted just before the main item.
+    pub usage: Option<u32>,
+    pub usage_min: Option<u32>,
+    pub usage_max: Option<u32>,
+    pub designator_index: Option<u32>,
+    pub designator_min: Option<u32>,
+    pub designator_max: Option<u32>,
 }
 
 /// GroupSpec keeps track of consecutive fields with shared global
@@ -49,11 +91,26 @@ pub struct GroupSpec {
     pub fields: HashMap<String, Spec>,
     pub field_order: Vec<String>,
 
+    // Byte order of multi-byte fields, only set on the top-level spec.
+    pub byte_order: Endianness,
+    // Length in bytes reports are padded to, only set on the top-level spec.
+    pub pad_to: Option<u16>,
+    // Padding of packed_bits fields, only set on the top-level spec.
+    pub bit_packing: BitPacking,
+    // Whether the struct is left aligned rather than packed, only set on the top-level spec.
+    pub aligned: bool,
+
     pub report_id: Option<u32>,
     pub usage_page: Option<u32>,
     pub collection: Option<u32>,
-    pub logical_min: Option<u32>,
-    pub unit_exponent: Option<u32>,
+    pub logical_min: Option<i32>,
+    pub logical_max: Option<i32>,
+    pub physical_min: Option<i32>,
+    pub physical_max: Option<i32>,
+    pub unit: Option<u32>,
+    pub unit_exponent: Option<i32>,
+    // Number of times the group is emitted, each covering the next element of its fields.
+    pub repeat: Option<u32>,
 
     // Local items
     pub usage: Vec<u32>,
@@ -62,30 +119,13 @@ pub struct GroupSpec {
 }
 
 impl GroupSpec {
-    pub fn set_item(
-        &mut self,
-        name: String,
-        item_kind: MainItemKind,
-        settings: Option<MainItemSetting>,
-        bits: Option<u16>,
-        quirks: ItemQuirks,
-    ) {
+    pub fn set_item(&mut self, name: String, item: ItemSpec) {
         if let Some(field) = self.fields.get_mut(&name) {
             if let Spec::MainItem(field) = field {
-                field.kind = item_kind;
-                field.settings = settings;
-                field.want_bits = bits;
+                *field = item;
             }
         } else {
-            self.fields.insert(
-                name.clone(),
-                Spec::MainItem(ItemSpec {
-                    kind: item_kind,
-                    settings,
-                    want_bits: bits,
-                    quirks,
-                }),
-            );
+            self.fields.insert(name.clone(), Spec::MainItem(item));
             self.field_order.push(name);
         }
     }
@@ -114,7 +154,22 @@ impl GroupSpec {
                 self.collection = Some(val);
                 Ok(())
             }
+            "unit" => {
+                self.unit = Some(val);
+                Ok(())
+            }
             "unit_exponent" => {
+                // Either the exponent, or its 4-bit two's complement encoding, ie: 0x0E for -2.
+                let val = match val as i32 {
+                    val @ -8..=7 => val,
+                    val @ 8..=15 => val - 16,
+                    _ => {
+                        return Err(parse::Error::new(
+                            input.span(),
+                            "`#[gen_hid_descriptor]` unit_exponent must be between -8 and 7, or a nibble between 0x0 and 0xF",
+                        ))
+                    }
+                };
                 self.unit_exponent = Some(val);
                 Ok(())
             }
@@ -132,7 +187,29 @@ impl GroupSpec {
                 Ok(())
             }
             "logical_min" => {
-                self.logical_min = Some(val);
+                self.logical_min = Some(val as i32);
+                Ok(())
+            }
+            "logical_max" => {
+                self.logical_max = Some(val as i32);
+                Ok(())
+            }
+            "physical_min" => {
+                self.physical_min = Some(val as i32);
+                Ok(())
+            }
+            "physical_max" => {
+                self.physical_max = Some(val as i32);
+                Ok(())
+            }
+            "repeat" => {
+                if val < 1 {
+                    return Err(parse::Error::new(
+                        input.span(),
+                        "`#[gen_hid_descriptor]` repeat must be at least 1",
+                    ));
+                }
+                self.repeat = Some(val);
                 Ok(())
             }
             _ => Err(parse::Error::new(
@@ -155,101 +232,263 @@ impl IntoIterator for GroupSpec {
     }
 }
 
+/// Group spec keys whose value may be negative.
+const SIGNED_GROUP_KEYS: &[&str] = &[
+    "logical_min",
+    "logical_max",
+    "physical_min",
+    "physical_max",
+    "unit_exponent",
+];
+
+/// Named constants which may be used as values for the given group spec or item key, as
+/// (key, name, value).
+const CONSTANTS: &[(&str, &str, u32)] = &[
+    ("collection", "PHYSICAL", 0x0),
+    ("collection", "APPLICATION", 0x1),
+    ("collection", "LOGICAL", 0x2),
+    ("collection", "REPORT", 0x3),
+    ("collection", "NAMED_ARRAY", 0x4),
+    ("collection", "USAGE_SWITCH", 0x5),
+    ("collection", "USAGE_MODIFIER", 0x06),
+    ("usage_page", "UNDEFINED", 0x00),
+    ("usage_page", "GENERIC_DESKTOP", 0x01),
+    ("usage_page", "SIMULATION_CONTROLS", 0x02),
+    ("usage_page", "VR_CONTROLS", 0x03),
+    ("usage_page", "SPORT_CONTROLS", 0x04),
+    ("usage_page", "GAME_CONTROLS", 0x05),
+    ("usage_page", "GENERIC_DEVICE_CONTROLS", 0x06),
+    ("usage_page", "KEYBOARD", 0x07),
+    ("usage_page", "LEDS", 0x08),
+    ("usage_page", "BUTTON", 0x09),
+    ("usage_page", "ORDINAL", 0x0A),
+    ("usage_page", "TELEPHONY", 0x0B),
+    ("usage_page", "CONSUMER", 0x0C),
+    ("usage_page", "DIGITIZER", 0x0D),
+    ("usage_page", "EYE_HEAD_TRACKERS", 0x12),
+    ("usage_page", "ALPHANUMERIC_DISPLAY", 0x14),
+    ("usage_page", "SENSOR", 0x20),
+    ("usage_page", "LIGHTING_AND_ILLUMINATION", 0x59),
+    ("usage_page", "BARCODE_SCANNER", 0x8C),
+    ("usage_page", "POWER_DEVICE", 0x84),
+    ("usage_page", "BATTERY_SYSTEM", 0x85),
+    ("usage_page", "FIDO_ALLIANCE", 0xF1D0),
+    ("usage_page", "VENDOR_DEFINED_START", 0xFF00),
+    ("usage_page", "VENDOR_DEFINED_END", 0xFFFF),
+    // Desktop usage_page usage ID's.
+    ("usage", "POINTER", 0x01),
+    ("usage", "MOUSE", 0x02),
+    ("usage", "JOYSTICK", 0x04),
+    ("usage", "GAMEPAD", 0x05),
+    ("usage", "KEYBOARD", 0x06),
+    ("usage", "KEYPAD", 0x07),
+    ("usage", "MULTI_AXIS_CONTROLLER", 0x08),
+    ("usage", "X", 0x30),
+    ("usage_min", "X", 0x30),
+    ("usage_max", "X", 0x30),
+    ("usage", "Y", 0x31),
+    ("usage_min", "Y", 0x31),
+    ("usage_max", "Y", 0x31),
+    ("usage", "Z", 0x32),
+    ("usage_min", "Z", 0x32),
+    ("usage_max", "Z", 0x32),
+    ("usage", "RX", 0x33),
+    ("usage", "RY", 0x34),
+    ("usage", "RZ", 0x35),
+    ("usage", "WHEEL", 0x38),
+    ("usage", "DIAL", 0x37),
+    ("usage", "HAT_SWITCH", 0x39),
+    ("usage", "RESOLUTION_MULTIPLIER", 0x48),
+    ("usage", "SYSTEM_CONTROL", 0x80),
+    // Simulation Controls usage_page usage ID's.
+    ("usage", "ACCELERATOR", 0xC4),
+    ("usage", "BRAKE", 0xC5),
+    // LED usage_page usage ID's.
+    ("usage", "NUM_LOCK", 0x01),
+    ("usage", "CAPS_LOCK", 0x02),
+    ("usage", "SCROLL_LOCK", 0x03),
+    ("usage", "COMPOSE", 0x04),
+    ("usage", "KANA", 0x05),
+    ("usage", "POWER", 0x06),
+    ("usage", "SHIFT", 0x07),
+    ("usage", "MUTE", 0x09),
+    ("usage", "RING", 0x18),
+    // Keyboard/Keypad usage_page usage ID's.
+    ("usage", "KEYPAD_NUM_LOCK", 0x53),
+    ("usage_min", "KEYPAD_NUM_LOCK", 0x53),
+    ("usage", "KEYPAD_EQUAL", 0x67),
+    ("usage_max", "KEYPAD_EQUAL", 0x67),
+    // Button usage_page usage ID's.
+    ("usage", "BUTTON_NONE", 0x00),
+    ("usage", "BUTTON_1", 0x01),
+    ("usage_min", "BUTTON_1", 0x01),
+    ("usage", "BUTTON_2", 0x02),
+    ("usage", "BUTTON_3", 0x03),
+    ("usage_max", "BUTTON_3", 0x03),
+    ("usage", "BUTTON_4", 0x04),
+    ("usage_max", "BUTTON_4", 0x04),
+    ("usage", "BUTTON_5", 0x05),
+    ("usage", "BUTTON_6", 0x06),
+    ("usage", "BUTTON_7", 0x07),
+    ("usage", "BUTTON_8", 0x08),
+    ("usage_max", "BUTTON_8", 0x08),
+    // Digitizer usage_page usage ID's.
+    ("usage", "PEN", 0x02),
+    ("usage", "TOUCH_SCREEN", 0x04),
+    ("usage", "STYLUS", 0x20),
+    ("usage", "FINGER", 0x22),
+    ("usage", "TIP_PRESSURE", 0x30),
+    ("usage", "IN_RANGE", 0x32),
+    ("usage", "INVERT", 0x3C),
+    ("usage", "X_TILT", 0x3D),
+    ("usage", "Y_TILT", 0x3E),
+    ("usage", "TIP_SWITCH", 0x42),
+    ("usage", "BARREL_SWITCH", 0x44),
+    ("usage", "ERASER", 0x45),
+    ("usage", "CONTACT_IDENTIFIER", 0x51),
+    ("usage", "CONTACT_COUNT", 0x54),
+    ("usage", "SCAN_TIME", 0x56),
+    // Eye and Head Trackers usage_page usage ID's.
+    ("usage", "EYE_TRACKER", 0x01),
+    ("usage", "HEAD_TRACKER", 0x02),
+    ("usage", "TRACKING_DATA", 0x10),
+    ("usage", "TRACKER_CAPABILITIES", 0x11),
+    ("usage", "TRACKER_CONFIGURATION", 0x12),
+    ("usage", "TRACKER_STATUS", 0x13),
+    ("usage", "TRACKER_CONTROL", 0x14),
+    ("usage", "SENSOR_TIMESTAMP", 0x20),
+    ("usage", "POSITION_X", 0x21),
+    ("usage", "POSITION_Y", 0x22),
+    ("usage", "POSITION_Z", 0x23),
+    ("usage", "GAZE_POINT", 0x24),
+    ("usage", "LEFT_EYE_POSITION", 0x25),
+    ("usage", "RIGHT_EYE_POSITION", 0x26),
+    ("usage", "HEAD_POSITION", 0x27),
+    // Alpha-numeric display usage_page usage ID's.
+    ("usage", "CLEAR_DISPLAY", 0x25),
+    ("usage", "DISPLAY_ENABLE", 0x26),
+    ("usage", "CHARACTER_REPORT", 0x2B),
+    ("usage", "CHARACTER_DATA", 0x2C),
+    // Consumer usage
+    ("usage", "CONSUMER_CONTROL", 0x01),
+    ("usage", "NUMERIC_KEYPAD", 0x02),
+    ("usage", "PROGRAMMABLE_BUTTONS", 0x03),
+    ("usage", "MICROPHONE", 0x04),
+    ("usage", "HEADPHONE", 0x05),
+    ("usage", "GRAPHIC_EQUALIZER", 0x06),
+    ("usage", "SCAN_NEXT_TRACK", 0xB5),
+    ("usage", "SCAN_PREVIOUS_TRACK", 0xB6),
+    ("usage", "STOP", 0xB7),
+    ("usage", "PLAY_PAUSE", 0xCD),
+    ("usage", "VOLUME", 0xE0),
+    ("usage", "VOLUME_INCREMENT", 0xE9),
+    ("usage", "VOLUME_DECREMENT", 0xEA),
+    ("usage", "AC_BACK", 0x0224),
+    ("usage", "AC_PAN", 0x0238),
+    // sensor power states
+    ("usage", "SENSOR_POWER_STATE", 0x0319),
+    ("usage", "SENSOR_POWER_STATE_UNDEFINED", 0x0850),
+    ("usage", "SENSOR_POWER_STATE_D0_FULL_POWER", 0x0851),
+    ("usage", "SENSOR_POWER_STATE_D1_LOW_POWER", 0x0852),
+    ("usage", "SENSOR_POWER_STATE_D2_STANDBY_WITH_WAKE", 0x0853),
+    ("usage", "SENSOR_POWER_STATE_D3_SLEEP_WITH_WAKE", 0x0854),
+    ("usage", "SENSOR_POWER_STATE_D4_POWER_OFF", 0x0855),
+    // Power Device usage_page usage ID's.
+    ("usage", "PRESENT_STATUS", 0x02),
+    ("usage", "BATTERY", 0x12),
+    ("usage", "POWER_SUMMARY", 0x24),
+    ("usage", "VOLTAGE", 0x30),
+    ("usage", "CURRENT", 0x31),
+    ("usage", "PRESENT", 0x60),
+    // Battery System usage_page usage ID's.
+    ("usage", "CHARGING", 0x44),
+    ("usage", "DISCHARGING", 0x45),
+    ("usage", "REMAINING_CAPACITY", 0x66),
+    ("usage", "RUN_TIME_TO_EMPTY", 0x68),
+    // Lighting and Illumination usage_page usage ID's.
+    ("usage", "LAMP_ARRAY", 0x01),
+    ("usage", "LAMP_ARRAY_ATTRIBUTES_REPORT", 0x02),
+    ("usage", "LAMP_COUNT", 0x03),
+    ("usage", "BOUNDING_BOX_WIDTH_IN_MICROMETERS", 0x04),
+    ("usage", "BOUNDING_BOX_HEIGHT_IN_MICROMETERS", 0x05),
+    ("usage", "BOUNDING_BOX_DEPTH_IN_MICROMETERS", 0x06),
+    ("usage", "LAMP_ARRAY_KIND", 0x07),
+    ("usage", "MIN_UPDATE_INTERVAL_IN_MICROSECONDS", 0x08),
+    ("usage", "LAMP_ATTRIBUTES_REQUEST_REPORT", 0x20),
+    ("usage", "LAMP_ID", 0x21),
+    ("usage", "LAMP_ATTRIBUTES_RESPONSE_REPORT", 0x22),
+    ("usage", "POSITION_X_IN_MICROMETERS", 0x23),
+    ("usage", "POSITION_Y_IN_MICROMETERS", 0x24),
+    ("usage", "POSITION_Z_IN_MICROMETERS", 0x25),
+    ("usage", "LAMP_PURPOSES", 0x26),
+    ("usage", "UPDATE_LATENCY_IN_MICROSECONDS", 0x27),
+    ("usage", "RED_LEVEL_COUNT", 0x28),
+    ("usage", "GREEN_LEVEL_COUNT", 0x29),
+    ("usage", "BLUE_LEVEL_COUNT", 0x2A),
+    ("usage", "INTENSITY_LEVEL_COUNT", 0x2B),
+    ("usage", "IS_PROGRAMMABLE", 0x2C),
+    ("usage", "INPUT_BINDING", 0x2D),
+    ("usage", "LAMP_MULTI_UPDATE_REPORT", 0x50),
+    ("usage", "RED_UPDATE_CHANNEL", 0x51),
+    ("usage", "GREEN_UPDATE_CHANNEL", 0x52),
+    ("usage", "BLUE_UPDATE_CHANNEL", 0x53),
+    ("usage", "INTENSITY_UPDATE_CHANNEL", 0x54),
+    ("usage", "LAMP_UPDATE_FLAGS", 0x55),
+    ("usage", "LAMP_RANGE_UPDATE_REPORT", 0x60),
+    ("usage", "LAMP_ID_START", 0x61),
+    ("usage", "LAMP_ID_END", 0x62),
+    ("usage", "LAMP_ARRAY_CONTROL_REPORT", 0x70),
+    ("usage", "AUTONOMOUS_MODE", 0x71),
+    // FIDO Alliance usage_page
+    ("usage", "U2F_AUTHENTICATOR_DEVICE", 0x1),
+    ("usage", "INPUT_REPORT_DATA", 0x20),
+    ("usage", "OUTPUT_REPORT_DATA", 0x21),
+    // Units, one nibble per dimension from the system (nibble 0) to luminous intensity
+    // (nibble 6), each holding the exponent of that dimension (6.2.2.7 of the HID spec).
+    ("unit", "NONE", 0x00),
+    ("unit", "SI_LINEAR", 0x11),
+    ("unit", "SI_ROTATION", 0x12),
+    ("unit", "ENGLISH_LINEAR", 0x13),
+    ("unit", "ENGLISH_ROTATION", 0x14),
+    ("unit", "CENTIMETERS", 0x11),
+    ("unit", "RADIANS", 0x12),
+    ("unit", "INCHES", 0x13),
+    ("unit", "DEGREES", 0x14),
+    ("unit", "GRAMS", 0x0101),
+    ("unit", "SECONDS", 0x1001),
+    ("unit", "KELVIN", 0x0001_0001),
+    ("unit", "VOLTS", 0x00F0_D121),
+    ("unit", "CENTIMETERS_PER_SECOND", 0xF011),
+    ("unit", "DEGREES_PER_SECOND", 0xF014),
+];
+
 pub fn try_resolve_constant(key_name: String, path: String) -> Option<u32> {
-    match (key_name.as_str(), path.as_str()) {
-        ("collection", "PHYSICAL") => Some(0x0),
-        ("collection", "APPLICATION") => Some(0x1),
-        ("collection", "LOGICAL") => Some(0x2),
-        ("collection", "REPORT") => Some(0x3),
-        ("collection", "NAMED_ARRAY") => Some(0x4),
-        ("collection", "USAGE_SWITCH") => Some(0x5),
-        ("collection", "USAGE_MODIFIER") => Some(0x06),
-
-        ("usage_page", "UNDEFINED") => Some(0x00),
-        ("usage_page", "GENERIC_DESKTOP") => Some(0x01),
-        ("usage_page", "SIMULATION_CONTROLS") => Some(0x02),
-        ("usage_page", "VR_CONTROLS") => Some(0x03),
-        ("usage_page", "SPORT_CONTROLS") => Some(0x04),
-        ("usage_page", "GAME_CONTROLS") => Some(0x05),
-        ("usage_page", "GENERIC_DEVICE_CONTROLS") => Some(0x06),
-        ("usage_page", "KEYBOARD") => Some(0x07),
-        ("usage_page", "LEDS") => Some(0x08),
-        ("usage_page", "BUTTON") => Some(0x09),
-        ("usage_page", "ORDINAL") => Some(0x0A),
-        ("usage_page", "TELEPHONY") => Some(0x0B),
-        ("usage_page", "CONSUMER") => Some(0x0C),
-        ("usage_page", "DIGITIZER") => Some(0x0D),
-        ("usage_page", "ALPHANUMERIC_DISPLAY") => Some(0x14),
-        ("usage_page", "SENSOR") => Some(0x20),
-        ("usage_page", "BARCODE_SCANNER") => Some(0x8C),
-        ("usage_page", "FIDO_ALLIANCE") => Some(0xF1D0),
-        ("usage_page", "VENDOR_DEFINED_START") => Some(0xFF00),
-        ("usage_page", "VENDOR_DEFINED_END") => Some(0xFFFF),
-
-        // Desktop usage_page usage ID's.
-        ("usage", "POINTER") => Some(0x01),
-        ("usage", "MOUSE") => Some(0x02),
-        ("usage", "JOYSTICK") => Some(0x04),
-        ("usage", "GAMEPAD") => Some(0x05),
-        ("usage", "KEYBOARD") => Some(0x06),
-        ("usage", "KEYPAD") => Some(0x07),
-        ("usage", "MULTI_AXIS_CONTROLLER") => Some(0x08),
-        ("usage", "X") | ("usage_min", "X") | ("usage_max", "X") => Some(0x30),
-        ("usage", "Y") | ("usage_min", "Y") | ("usage_max", "Y") => Some(0x31),
-        ("usage", "Z") | ("usage_min", "Z") | ("usage_max", "Z") => Some(0x32),
-        ("usage", "WHEEL") => Some(0x38),
-        ("usage", "SYSTEM_CONTROL") => Some(0x80),
-
-        // LED usage_page usage ID's.
-        ("usage", "NUM_LOCK") => Some(0x01),
-        ("usage", "CAPS_LOCK") => Some(0x02),
-        ("usage", "SCROLL_LOCK") => Some(0x03),
-        ("usage", "POWER") => Some(0x06),
-        ("usage", "SHIFT") => Some(0x07),
-        ("usage", "MUTE") => Some(0x09),
-        ("usage", "RING") => Some(0x18),
-
-        // Button usage_page usage ID's.
-        ("usage", "BUTTON_NONE") => Some(0x00),
-        ("usage", "BUTTON_1") | ("usage_min", "BUTTON_1") => Some(0x01),
-        ("usage", "BUTTON_2") => Some(0x02),
-        ("usage", "BUTTON_3") | ("usage_max", "BUTTON_3") => Some(0x03),
-        ("usage", "BUTTON_4") | ("usage_max", "BUTTON_4") => Some(0x04),
-        ("usage", "BUTTON_5") => Some(0x05),
-        ("usage", "BUTTON_6") => Some(0x06),
-        ("usage", "BUTTON_7") => Some(0x07),
-        ("usage", "BUTTON_8") | ("usage_max", "BUTTON_8") => Some(0x08),
-
-        // Alpha-numeric display usage_page usage ID's.
-        ("usage", "CLEAR_DISPLAY") => Some(0x25),
-        ("usage", "DISPLAY_ENABLE") => Some(0x26),
-        ("usage", "CHARACTER_REPORT") => Some(0x2B),
-        ("usage", "CHARACTER_DATA") => Some(0x2C),
-
-        // Consumer usage
-        ("usage", "CONSUMER_CONTROL") => Some(0x01),
-        ("usage", "NUMERIC_KEYPAD") => Some(0x02),
-        ("usage", "PROGRAMMABLE_BUTTONS") => Some(0x03),
-        ("usage", "MICROPHONE") => Some(0x04),
-        ("usage", "HEADPHONE") => Some(0x05),
-        ("usage", "GRAPHIC_EQUALIZER") => Some(0x06),
-        ("usage", "AC_PAN") => Some(0x0238),
-
-        // sensor power states
-        ("usage", "SENSOR_POWER_STATE") => Some(0x0319),
-        ("usage", "SENSOR_POWER_STATE_UNDEFINED") => Some(0x0850),
-        ("usage", "SENSOR_POWER_STATE_D0_FULL_POWER") => Some(0x0851),
-        ("usage", "SENSOR_POWER_STATE_D1_LOW_POWER") => Some(0x0852),
-        ("usage", "SENSOR_POWER_STATE_D2_STANDBY_WITH_WAKE") => Some(0x0853),
-        ("usage", "SENSOR_POWER_STATE_D3_SLEEP_WITH_WAKE") => Some(0x0854),
-        ("usage", "SENSOR_POWER_STATE_D4_POWER_OFF") => Some(0x0855),
-
-        // FIDO Alliance usage_page
-        ("usage", "U2F_AUTHENTICATOR_DEVICE") => Some(0x1),
-        ("usage", "INPUT_REPORT_DATA") => Some(0x20),
-        ("usage", "OUTPUT_REPORT_DATA") => Some(0x21),
-
-        (_, _) => None,
+    CONSTANTS
+        .iter()
+        .find(|(key, name, _)| *key == key_name && *name == path)
+        .map(|(_, _, val)| *val)
+}
+
+/// Builds the error message for a constant which is not known for the given key, listing the
+/// constants which are. Values without a named constant can always be given as a numeric literal.
+pub fn unrecognized_constant_message(key_name: &str, path: &str) -> String {
+    let known: Vec<&str> = CONSTANTS
+        .iter()
+        .filter(|(key, _, _)| *key == key_name)
+        .map(|(_, name, _)| *name)
+        .collect();
+    if known.is_empty() {
+        format!(
+            "`#[gen_hid_descriptor]` unrecognized constant: {}; `{}` only accepts a numeric literal",
+            path, key_name
+        )
+    } else {
+        format!(
+            "`#[gen_hid_descriptor]` unrecognized constant: {}; known constants for `{}` are: {} (or use a numeric literal)",
+            path,
+            key_name,
+            known.join(", ")
+        )
     }
 }
 
@@ -257,50 +496,74 @@ fn parse_group_spec(input: ParseStream, field: Expr) -> Result<GroupSpec> {
     let mut collection_attrs: Vec<(String, u32)> = vec![];
 
     if let Expr::Assign(ExprAssign { left, .. }) = field.clone() {
-        if let Expr::Tuple(ExprTuple { elems, .. }) = *left {
-            for elem in elems {
-                let group_attr = maybe_parse_kv_lhs(elem.clone());
-                if group_attr.is_none() || group_attr.clone().unwrap().len() != 1 {
-                    return Err(parse::Error::new(
-                        input.span(),
-                        "`#[gen_hid_descriptor]` group spec key can only have a single element",
-                    ));
-                }
-                let group_attr = group_attr.unwrap()[0].clone();
-
-                let mut val: Option<u32> = None;
-                if let Expr::Assign(ExprAssign { right, .. }) = elem {
-                    if let Expr::Lit(ExprLit { lit, .. }) = *right {
-                        if let Lit::Int(lit) = lit {
-                            if let Ok(num) = lit.base10_parse::<u32>() {
-                                val = Some(num);
-                            }
+        // A single parameter without a trailing comma is parsed as a parenthesized
+        // expression rather than a tuple.
+        let elems = match *left {
+            Expr::Tuple(ExprTuple { elems, .. }) => elems.into_iter().collect(),
+            Expr::Paren(ExprParen { expr, .. }) => vec![*expr],
+            _ => vec![],
+        };
+        for elem in elems {
+            let group_attr = maybe_parse_kv_lhs(elem.clone());
+            if group_attr.is_none() || group_attr.clone().unwrap().len() != 1 {
+                return Err(parse::Error::new(
+                    input.span(),
+                    "`#[gen_hid_descriptor]` group spec key can only have a single element",
+                ));
+            }
+            let group_attr = group_attr.unwrap()[0].clone();
+
+            let mut val: Option<u32> = None;
+            if let Expr::Assign(ExprAssign { right, .. }) = elem {
+                if let Expr::Lit(ExprLit { lit, .. }) = *right {
+                    if let Lit::Int(lit) = lit {
+                        if let Ok(num) = lit.base10_parse::<u32>() {
+                            val = Some(num);
                         }
-                    } else if let Expr::Path(ExprPath {
-                        path: Path { segments, .. },
-                        ..
-                    }) = *right
+                    }
+                } else if let Expr::Unary(ExprUnary {
+                    op: UnOp::Neg(_),
+                    expr,
+                    ..
+                }) = *right
+                {
+                    // Negative values are held in two's complement, ie: for physical_min.
+                    if !SIGNED_GROUP_KEYS.contains(&group_attr.as_str()) {
+                        return Err(parse::Error::new(
+                            input.span(),
+                            format!("`#[gen_hid_descriptor]` {} can't be negative", group_attr),
+                        ));
+                    }
+                    if let Expr::Lit(ExprLit {
+                        lit: Lit::Int(lit), ..
+                    }) = *expr
                     {
-                        val = try_resolve_constant(
-                            group_attr.clone(),
-                            quote! { #segments }.to_string(),
-                        );
-                        if val.is_none() {
-                            return Err(parse::Error::new(
-                                input.span(),
-                                format!(
-                                    "`#[gen_hid_descriptor]` unrecognized constant: {}",
-                                    quote! { #segments }
-                                ),
-                            ));
+                        if let Ok(num) = lit.base10_parse::<i32>() {
+                            val = Some(num.wrapping_neg() as u32);
                         }
                     }
+                } else if let Expr::Path(ExprPath {
+                    path: Path { segments, .. },
+                    ..
+                }) = *right
+                {
+                    val =
+                        try_resolve_constant(group_attr.clone(), quote! { #segments }.to_string());
+                    if val.is_none() {
+                        return Err(parse::Error::new(
+                            input.span(),
+                            unrecognized_constant_message(
+                                &group_attr,
+                                &quote! { #segments }.to_string(),
+                            ),
+                        ));
+                    }
                 }
-                if val.is_none() {
-                    return Err(parse::Error::new(input.span(), "`#[gen_hid_descriptor]` group spec attribute value must be a numeric literal or recognized constant"));
-                }
-                collection_attrs.push((group_attr, val.unwrap()));
             }
+            if val.is_none() {
+                return Err(parse::Error::new(input.span(), "`#[gen_hid_descriptor]` group spec attribute value must be a numeric literal or recognized constant"));
+            }
+            collection_attrs.push((group_attr, val.unwrap()));
         }
     }
     if collection_attrs.is_empty() {
@@ -315,6 +578,13 @@ fn parse_group_spec(input: ParseStream, field: Expr) -> Result<GroupSpec> {
     for (key, val) in collection_attrs {
         out.try_set_attr(input, key, val)?;
     }
+    // A lone physical_max spans from 0, but a Physical Maximum of 0 makes no sense.
+    if out.physical_min.is_some() && out.physical_max.is_none() {
+        return Err(parse::Error::new(
+            input.span(),
+            "`#[gen_hid_descriptor]` physical_min requires physical_max",
+        ));
+    }
 
     // Match out the item kind on the right of the equals.
     if let Expr::Assign(ExprAssign { right, .. }) = field {
@@ -360,28 +630,142 @@ fn maybe_parse_kv_lhs(field: Expr) -> Option<Vec<String>> {
     None
 }
 
-fn parse_item_attrs(attrs: Vec<Attribute>) -> (Option<MainItemSetting>, Option<u16>, ItemQuirks) {
+/// parse_attr_value reads the value of a sub-attribute like `#[usage 0x30]`, which is either a
+/// numeric literal or a constant recognized by try_resolve_constant for the given key.
+fn parse_attr_value(key: &str, attr: &Attribute) -> Result<u32> {
+    match attr.tokens.clone().into_iter().next() {
+        Some(proc_macro2::TokenTree::Literal(lit)) => {
+            let span = lit.span();
+            syn::LitInt::from(lit).base10_parse::<u32>().map_err(|_| {
+                parse::Error::new(
+                    span,
+                    format!(
+                        "`#[gen_hid_descriptor]` {key} must be a positive integer or a constant"
+                    ),
+                )
+            })
+        }
+        Some(proc_macro2::TokenTree::Ident(id)) => {
+            try_resolve_constant(key.to_string(), id.to_string()).ok_or_else(|| {
+                parse::Error::new(
+                    id.span(),
+                    unrecognized_constant_message(key, &id.to_string()),
+                )
+            })
+        }
+        Some(tok) => Err(parse::Error::new(
+            tok.span(),
+            format!("`#[gen_hid_descriptor]` {key} must be a positive integer or a constant"),
+        )),
+        None => Err(parse::Error::new(
+            attr.span(),
+            format!("`#[gen_hid_descriptor]` {key} requires a value, ie: `#[{key} 1]`"),
+        )),
+    }
+}
+
+/// parse_signed_attr_value reads the value of a sub-attribute like `#[logical_min -127]`, which
+/// is an integer literal, optionally negated.
+fn parse_signed_attr_value(key: &str, attr: &Attribute) -> Result<i32> {
+    let err = || {
+        parse::Error::new(
+            attr.tokens.span(),
+            format!("`#[gen_hid_descriptor]` {key} must be an integer, ie: `#[{key} -1]`"),
+        )
+    };
+    let mut tokens = attr.tokens.clone().into_iter().peekable();
+    let negative = matches!(
+        tokens.peek(),
+        Some(proc_macro2::TokenTree::Punct(p)) if p.as_char() == '-'
+    );
+    if negative {
+        tokens.next();
+    }
+    match (tokens.next(), tokens.next()) {
+        (Some(proc_macro2::TokenTree::Literal(lit)), None) => {
+            let val = syn::LitInt::from(lit)
+                .base10_parse::<i64>()
+                .map_err(|_| err())?;
+            let val = if negative { -val } else { val };
+            i32::try_from(val).map_err(|_| err())
+        }
+        _ => Err(err()),
+    }
+}
+
+fn parse_item_attrs(attrs: Vec<Attribute>) -> Result<ItemSpec> {
     let mut out: MainItemSetting = MainItemSetting(0);
     let mut had_settings: bool = false;
-    let mut packed_bits: Option<u16> = None;
-    let mut quirks: ItemQuirks = ItemQuirks {
+    let mut spec = ItemSpec {
         ..Default::default()
     };
 
     for attr in attrs {
         match attr.path.segments[0].ident.to_string().as_str() {
             "packed_bits" => {
-                for tok in attr.tokens {
-                    if let proc_macro2::TokenTree::Literal(lit) = tok {
-                        if let Ok(num) = lit.to_string().parse::<u16>() {
-                            packed_bits = Some(num);
-                            break;
-                        }
-                    }
-                }
-                if packed_bits.is_none() {
-                    log::warn!("bitfield attribute specified but failed to read number of bits from token!");
+                let bits = parse_attr_value("packed_bits", &attr)?;
+                spec.want_bits = Some(u16::try_from(bits).map_err(|_| {
+                    parse::Error::new(
+                        attr.tokens.span(),
+                        "`#[gen_hid_descriptor]` packed_bits is too large",
+                    )
+                })?);
+            }
+
+            "pad_bytes" => {
+                let bytes = parse_attr_value("pad_bytes", &attr)?;
+                spec.pad_bytes = Some(u16::try_from(bytes).map_err(|_| {
+                    parse::Error::new(
+                        attr.tokens.span(),
+                        "`#[gen_hid_descriptor]` pad_bytes is too large",
+                    )
+                })?);
+            }
+
+            "full_range" => spec.full_range = true,
+
+            "unit_exponent" => {
+                let exponent = parse_signed_attr_value("unit_exponent", &attr)?;
+                if !(-8..=7).contains(&exponent) {
+                    return Err(parse::Error::new(
+                        attr.tokens.span(),
+                        "`#[gen_hid_descriptor]` unit_exponent must be between -8 and 7",
+                    ));
                 }
+                spec.unit_exponent = Some(exponent);
+            }
+
+            "percent" => {
+                spec.logical_min = Some(0);
+                spec.logical_max = Some(100);
+            }
+
+            "logical_min" => {
+                spec.logical_min = Some(parse_signed_attr_value("logical_min", &attr)?);
+            }
+
+            "logical_max" => {
+                spec.logical_max = Some(parse_signed_attr_value("logical_max", &attr)?);
+            }
+
+            "usage_page" => spec.usage_page = Some(parse_attr_value("usage_page", &attr)?),
+
+            "usage" => spec.usage = Some(parse_attr_value("usage", &attr)?),
+
+            "usage_min" => spec.usage_min = Some(parse_attr_value("usage_min", &attr)?),
+
+            "usage_max" => spec.usage_max = Some(parse_attr_value("usage_max", &attr)?),
+
+            "designator" => {
+                spec.designator_index = Some(parse_attr_value("designator", &attr)?);
+            }
+
+            "designator_min" => {
+                spec.designator_min = Some(parse_attr_value("designator_min", &attr)?);
+            }
+
+            "designator_max" => {
+                spec.designator_max = Some(parse_attr_value("designator_max", &attr)?);
             }
 
             "item_settings" => {
@@ -412,60 +796,188 @@ fn parse_item_attrs(attrs: Vec<Attribute>) -> (Option<MainItemSetting>, Option<u
 
                             "volatile" => out.set_volatile(true),
                             "not_volatile" => out.set_volatile(false),
-                            p => log::warn!("Unknown item_settings parameter: {p}"),
+
+                            "buffered_bytes" => spec.buffered_bytes = true,
+                            "bit_field" => spec.buffered_bytes = false,
+                            p => {
+                                return Err(parse::Error::new(
+                                    id.span(),
+                                    format!(
+                                    "`#[gen_hid_descriptor]` unknown item_settings parameter: {p}"
+                                ),
+                                ))
+                            }
                         }
                     }
                 }
             }
 
             "quirks" => {
-                for setting in attr.tokens {
-                    if let proc_macro2::TokenTree::Ident(id) = setting {
-                        match id.to_string().as_str() {
-                            "allow_short" => quirks.allow_short_form = true,
-                            p => log::warn!("Unknown item_settings parameter: {p}"),
-                        }
-                    }
-                }
+                let quirks = spec.quirks.get_or_insert_with(Default::default);
+                parse_quirks(quirks, attr.tokens)?;
             }
 
-            p => log::warn!("Unknown item attribute: {p}"),
+            p => {
+                return Err(parse::Error::new(
+                    attr.path.span(),
+                    format!("`#[gen_hid_descriptor]` unknown item attribute: {p}"),
+                ))
+            }
         }
     }
 
     if had_settings {
-        return (Some(out), packed_bits, quirks);
+        spec.settings = Some(out);
+    }
+    Ok(spec)
+}
+
+fn parse_quirks(quirks: &mut ItemQuirks, tokens: proc_macro2::TokenStream) -> Result<()> {
+    for setting in tokens {
+        if let proc_macro2::TokenTree::Ident(id) = setting {
+            match id.to_string().as_str() {
+                "allow_short" => quirks.allow_short_form = true,
+                "long_form" => quirks.allow_short_form = false,
+                "implicit_logical_min" => quirks.implicit_logical_min = true,
+                "wide_usage_page" => quirks.wide_usage_page = true,
+                p => {
+                    return Err(parse::Error::new(
+                        id.span(),
+                        format!("`#[gen_hid_descriptor]` unknown quirks parameter: {p}"),
+                    ))
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// take_descriptor_quirks removes any `#[quirks]` attributes from the struct, returning
+/// the quirks which apply to all items of the descriptor.
+pub fn take_descriptor_quirks(attrs: &mut Vec<Attribute>) -> Result<ItemQuirks> {
+    let mut quirks = ItemQuirks::default();
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("quirks")) {
+        // Attributes outside of the macro invocation must be delimited, ie: #[quirks(allow_short)]
+        for tok in attr.tokens.clone() {
+            if let proc_macro2::TokenTree::Group(group) = tok {
+                parse_quirks(&mut quirks, group.stream())?;
+            }
+        }
     }
-    (None, packed_bits, quirks)
+    attrs.retain(|attr| !attr.path.is_ident("quirks"));
+    Ok(quirks)
+}
+
+// maybe_parse_byte_order tries to parse a descriptor option like 'byte_order(big)'.
+fn maybe_parse_byte_order(input: ParseStream, field: &Expr) -> Result<Option<Endianness>> {
+    let call = match field {
+        Expr::Call(call) => call,
+        _ => return Ok(None),
+    };
+    match &*call.func {
+        Expr::Path(ExprPath { path, .. }) if path.is_ident("byte_order") => {}
+        _ => return Ok(None),
+    }
+    let order = match call.args.iter().next() {
+        Some(Expr::Path(ExprPath { path, .. })) if call.args.len() == 1 => {
+            if path.is_ident("little") {
+                Some(Endianness::Little)
+            } else if path.is_ident("big") {
+                Some(Endianness::Big)
+            } else {
+                None
+            }
+        }
+        _ => None,
+    };
+    match order {
+        Some(order) => Ok(Some(order)),
+        None => Err(parse::Error::new(
+            input.span(),
+            "`#[gen_hid_descriptor]` byte_order must be `byte_order(little)` or `byte_order(big)`",
+        )),
+    }
+}
+
+// maybe_parse_pad_to tries to parse a descriptor option like 'pad_to(8)'.
+fn maybe_parse_pad_to(input: ParseStream, field: &Expr) -> Result<Option<u16>> {
+    let call = match field {
+        Expr::Call(call) => call,
+        _ => return Ok(None),
+    };
+    match &*call.func {
+        Expr::Path(ExprPath { path, .. }) if path.is_ident("pad_to") => {}
+        _ => return Ok(None),
+    }
+    let len = match call.args.iter().next() {
+        Some(Expr::Lit(ExprLit {
+            lit: Lit::Int(lit), ..
+        })) if call.args.len() == 1 => lit.base10_parse::<u16>().ok(),
+        _ => None,
+    };
+    match len {
+        Some(len) => Ok(Some(len)),
+        None => Err(parse::Error::new(
+            input.span(),
+            "`#[gen_hid_descriptor]` pad_to must be given a length in bytes, ie: `pad_to(8)`",
+        )),
+    }
+}
+
+// maybe_parse_bit_packing tries to parse a descriptor option like 'bit_packing(report)'.
+fn maybe_parse_bit_packing(input: ParseStream, field: &Expr) -> Result<Option<BitPacking>> {
+    let call = match field {
+        Expr::Call(call) => call,
+        _ => return Ok(None),
+    };
+    match &*call.func {
+        Expr::Path(ExprPath { path, .. }) if path.is_ident("bit_packing") => {}
+        _ => return Ok(None),
+    }
+    let packing = match call.args.iter().next() {
+        Some(Expr::Path(ExprPath { path, .. })) if call.args.len() == 1 => {
+            if path.is_ident("field") {
+                Some(BitPacking::Field)
+            } else if path.is_ident("report") {
+                Some(BitPacking::Report)
+            } else {
+                None
+            }
+        }
+        _ => None,
+    };
+    match packing {
+        Some(packing) => Ok(Some(packing)),
+        None => Err(parse::Error::new(
+            input.span(),
+            "`#[gen_hid_descriptor]` bit_packing must be `bit_packing(field)` or `bit_packing(report)`",
+        )),
+    }
+}
+
+// is_aligned_option returns true for the descriptor option 'aligned'.
+fn is_aligned_option(field: &Expr) -> bool {
+    matches!(field, Expr::Path(ExprPath { path, .. }) if path.is_ident("aligned"))
 }
 
 // maybe_parse_kv tries to parse an expression like 'blah=blah'.
-#[allow(clippy::type_complexity)]
-fn maybe_parse_kv(
-    field: Expr,
-) -> Option<(
-    String,
-    String,
-    Option<MainItemSetting>,
-    Option<u16>,
-    ItemQuirks,
-)> {
+fn maybe_parse_kv(field: Expr) -> Result<Option<(String, String, ItemSpec)>> {
     // Match out the identifier on the left of the equals.
     let name: String;
     if let Some(lhs) = maybe_parse_kv_lhs(field.clone()) {
         if lhs.len() != 1 {
-            return None;
+            return Ok(None);
         }
         name = lhs[0].clone();
     } else {
-        return None;
+        return Ok(None);
     }
 
     // Decode item settings.
-    let item_settings = if let Some(attrs) = AttributeCollector::all(&field) {
-        parse_item_attrs(attrs)
+    let item_spec = if let Some(attrs) = AttributeCollector::all(&field) {
+        parse_item_attrs(attrs)?
     } else {
-        (None, None, ItemQuirks::default())
+        ItemSpec::default()
     };
 
     // Match out the item kind on the right of the equals.
@@ -479,15 +991,7 @@ fn maybe_parse_kv(
             val = Some(segments[0].ident.clone().to_string());
         }
     };
-    val.as_ref()?;
-
-    Some((
-        name,
-        val.unwrap(),
-        item_settings.0,
-        item_settings.1,
-        item_settings.2,
-    ))
+    Ok(val.map(|val| (name, val, item_spec)))
 }
 
 struct AttributeCollector(Vec<Attribute>);
@@ -529,6 +1033,22 @@ impl Parse for GroupSpec {
             ));
         }
         for field in fields {
+            if let Some(byte_order) = maybe_parse_byte_order(input, &field)? {
+                out.byte_order = byte_order;
+                continue;
+            }
+            if let Some(pad_to) = maybe_parse_pad_to(input, &field)? {
+                out.pad_to = Some(pad_to);
+                continue;
+            }
+            if let Some(bit_packing) = maybe_parse_bit_packing(input, &field)? {
+                out.bit_packing = bit_packing;
+                continue;
+            }
+            if is_aligned_option(&field) {
+                out.aligned = true;
+                continue;
+            }
             out.from_field(input, field)?;
         }
         Ok(out)
@@ -538,9 +1058,15 @@ impl Parse for GroupSpec {
 impl GroupSpec {
     #[allow(clippy::wrong_self_convention)]
     fn from_field(&mut self, input: ParseStream, field: Expr) -> Result<()> {
-        if let Some(i) = maybe_parse_kv(field.clone()) {
-            let (name, item_kind, settings, bits, quirks) = i;
-            self.set_item(name, item_kind.as_str().into(), settings, bits, quirks);
+        if let Some(i) = maybe_parse_kv(field.clone())? {
+            let (name, item_kind, item) = i;
+            self.set_item(
+                name,
+                ItemSpec {
+                    kind: item_kind.as_str().into(),
+                    ..item
+                },
+            );
             return Ok(());
         };
         match parse_group_spec(input, field) {
@@ -550,3 +1076,92 @@ impl GroupSpec {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unrecognized_constant_message() {
+        let msg = unrecognized_constant_message("collection", "PHYSICALL");
+        assert!(msg.contains("unrecognized constant: PHYSICALL"));
+        assert!(msg.contains("PHYSICAL, APPLICATION, LOGICAL"));
+        assert!(msg.contains("numeric literal"));
+
+        let msg = unrecognized_constant_message("usage_page", "BRAILLE_DISPLAY");
+        assert!(msg.contains("GENERIC_DESKTOP"));
+        assert!(!msg.contains("MOUSE"));
+    }
+
+    fn parse_group(spec: &str) -> Result<GroupSpec> {
+        let field: Expr = syn::parse_str(spec).unwrap();
+        syn::parse::Parser::parse_str(|input: ParseStream| parse_group_spec(input, field), "")
+    }
+
+    #[test]
+    fn test_group_signed_values() {
+        let group = parse_group(
+            "(physical_min = -90, physical_max = 90, logical_min = -1, logical_max = 1) = {}",
+        )
+        .unwrap();
+        assert_eq!(group.physical_min, Some(-90));
+        assert_eq!(group.physical_max, Some(90));
+        assert_eq!(group.logical_min, Some(-1));
+        assert_eq!(group.logical_max, Some(1));
+
+        let err = parse_group("(usage_page = -1,) = {}").err().unwrap();
+        assert!(err.to_string().contains("usage_page can't be negative"));
+        assert!(parse_group("(report_id = -2,) = {}").is_err());
+        assert!(parse_group("(physical_max = 359,) = {}").is_ok());
+        let err = parse_group("(physical_min = -90,) = {}").err().unwrap();
+        assert!(err
+            .to_string()
+            .contains("physical_min requires physical_max"));
+    }
+
+    #[test]
+    fn test_group_repeat() {
+        let group = parse_group("(usage = 0x22, repeat = 2) = {}").unwrap();
+        assert_eq!(group.repeat, Some(2));
+
+        let err = parse_group("(usage = 0x22, repeat = 0) = {}")
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("repeat must be at least 1"));
+    }
+
+    #[test]
+    fn test_item_attr_errors() {
+        let group = parse_group(
+            "(usage_page = 1,) = { #[usage 0x30] #[unit_exponent -3] #[quirks allow_short] x=input; }",
+        )
+        .unwrap();
+        assert!(matches!(&group.fields["x"], Spec::MainItem(item) if item.usage == Some(0x30)));
+
+        for (spec, msg) in [
+            ("#[usage MOUSEE]", "unrecognized constant: MOUSEE"),
+            ("#[usage_page -1]", "usage_page must be a positive integer"),
+            ("#[logical_max x]", "logical_max must be an integer"),
+            (
+                "#[unit_exponent 8]",
+                "unit_exponent must be between -8 and 7",
+            ),
+            ("#[pad_bytes]", "pad_bytes requires a value"),
+            (
+                "#[designator_min 0x1_0000_0000]",
+                "designator_min must be a positive integer",
+            ),
+            (
+                "#[item_settings data,varable]",
+                "unknown item_settings parameter: varable",
+            ),
+            ("#[usages 1]", "unknown item attribute: usages"),
+            ("#[quirks short]", "unknown quirks parameter: short"),
+        ] {
+            let err = parse_group(&format!("(usage_page = 1,) = {{ {spec} x=input; }}"))
+                .err()
+                .unwrap();
+            assert!(err.to_string().contains(msg), "{spec}: {err}");
+        }
+    }
+}
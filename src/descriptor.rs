@@ -1,22 +1,205 @@
 //! Implements generation of HID report descriptors as well as common reports
 extern crate serde;
 extern crate usbd_hid_macros;
+use crate::hid_class::ReportType;
 use serde::ser::{Serialize, SerializeTuple, Serializer};
+use usb_device::UsbError;
 
 pub use usbd_hid_macros::gen_hid_descriptor;
 
+#[cfg(doctest)]
+mod compile_fail;
+pub mod parser;
+mod ser;
+pub use parser::{is_valid_report_id, report_ids};
+
+#[cfg(any(test, feature = "std"))]
+mod diff;
+#[cfg(any(test, feature = "std"))]
+pub use diff::{diff, DiffEntry};
+
+#[cfg(any(test, feature = "std"))]
+mod describe;
+#[cfg(any(test, feature = "std"))]
+pub use describe::describe;
+
 /// Report types where serialized HID report descriptors are available.
 pub trait SerializedDescriptor {
+    /// Collection type of the outermost collection, ie: 0x01 for Application. None if
+    /// the descriptor declares several top-level collections, told apart by report IDs.
+    const TOP_COLLECTION: Option<u8> = None;
+
     fn desc() -> &'static [u8];
+
+    /// Copies the descriptor into the given buffer, ie: to patch it at runtime, returning the
+    /// number of bytes written. A BufferOverflow error is returned if the buffer is too small.
+    fn copy_to(buf: &mut [u8]) -> crate::Result<usize> {
+        let desc = Self::desc();
+        let dest = buf.get_mut(..desc.len()).ok_or(UsbError::BufferOverflow)?;
+        dest.copy_from_slice(desc);
+        Ok(desc.len())
+    }
 }
 
 /// Report types which serialize into input reports, ready for transmission.
-pub trait AsInputReport: Serialize {}
+pub trait AsInputReport: Serialize {
+    /// Report ID written ahead of the serialized fields by serialize_into, if any.
+    const REPORT_ID_PREFIX: Option<u8> = None;
+    /// Length serialize_into pads the report to with zero bytes, when longer than the
+    /// serialized fields.
+    const PADDED_LEN: usize = 0;
+
+    /// Serializes the report into the given buffer, returning the number of bytes written.
+    /// A BufferOverflow error is returned if the report does not fit in the buffer.
+    fn serialize_into(&self, buf: &mut [u8]) -> crate::Result<usize>
+    where
+        Self: Sized,
+    {
+        let len = match Self::REPORT_ID_PREFIX {
+            Some(id) => {
+                let (first, rest) = buf.split_first_mut().ok_or(UsbError::BufferOverflow)?;
+                *first = id;
+                ser::serialize(rest, self)
+                    .map(|n| n + 1)
+                    .map_err(|_| UsbError::BufferOverflow)?
+            }
+            None => ser::serialize(buf, self).map_err(|_| UsbError::BufferOverflow)?,
+        };
+        if len < Self::PADDED_LEN {
+            buf.get_mut(len..Self::PADDED_LEN)
+                .ok_or(UsbError::BufferOverflow)?
+                .fill(0);
+            return Ok(Self::PADDED_LEN);
+        }
+        Ok(len)
+    }
+
+    /// Serializes the report into the given buffer prefixed by the given report ID, ie: to
+    /// assemble the reports of a descriptor with several report IDs by hand. The fields are
+    /// padded to PADDED_LEN as by serialize_into. Returns the number of bytes written,
+    /// including the report ID. A BufferOverflow error is returned if the report does not fit
+    /// in the buffer.
+    fn serialize_with_id(&self, id: u8, buf: &mut [u8]) -> crate::Result<usize>
+    where
+        Self: Sized,
+    {
+        let (first, rest) = buf.split_first_mut().ok_or(UsbError::BufferOverflow)?;
+        *first = id;
+        let len = ser::serialize(rest, self).map_err(|_| UsbError::BufferOverflow)?;
+        if len < Self::PADDED_LEN {
+            rest.get_mut(len..Self::PADDED_LEN)
+                .ok_or(UsbError::BufferOverflow)?
+                .fill(0);
+            return Ok(Self::PADDED_LEN + 1);
+        }
+        Ok(len + 1)
+    }
+}
+
+/// Report types with output fields, which unpack from the output reports sent by the host.
+pub trait AsOutputReport: Sized {
+    /// Unpacks an output report. Fields which aren't output fields are set to zero.
+    /// A ParseError is returned if the report has the wrong length.
+    fn from_output_report(buf: &[u8]) -> crate::Result<Self>;
+}
+
+/// Report types with feature fields, which the host both reads with GET_REPORT(Feature)
+/// and writes with SET_REPORT(Feature), ie: device configuration.
+pub trait AsFeatureReport {
+    /// Length serialize_feature_into pads the report to with zero bytes, when longer than
+    /// the serialized fields.
+    const PADDED_LEN: usize = 0;
+
+    /// Serializes the feature fields of the report with the given serializer.
+    fn serialize_feature<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error>;
+
+    /// Unpacks a feature report. Fields which aren't feature fields are set to zero.
+    /// A ParseError is returned if the report has the wrong length.
+    fn try_from_feature(buf: &[u8]) -> crate::Result<Self>
+    where
+        Self: Sized;
+
+    /// Serializes the feature report into the given buffer, returning the number of bytes
+    /// written. A BufferOverflow error is returned if the report does not fit in the buffer.
+    fn serialize_feature_into(&self, buf: &mut [u8]) -> crate::Result<usize>
+    where
+        Self: Sized,
+    {
+        struct Feature<'a, T>(&'a T);
+        impl<T: AsFeatureReport> Serialize for Feature<'_, T> {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                self.0.serialize_feature(serializer)
+            }
+        }
+
+        let len = ser::serialize(buf, &Feature(self)).map_err(|_| UsbError::BufferOverflow)?;
+        if len < Self::PADDED_LEN {
+            buf.get_mut(len..Self::PADDED_LEN)
+                .ok_or(UsbError::BufferOverflow)?
+                .fill(0);
+            return Ok(Self::PADDED_LEN);
+        }
+        Ok(len)
+    }
+}
+
+/// Returns the combined length of the given report descriptors.
+pub const fn total_len(descs: &[&[u8]]) -> usize {
+    let mut len = 0;
+    let mut i = 0;
+    while i < descs.len() {
+        len += descs[i].len();
+        i += 1;
+    }
+    len
+}
+
+/// Concatenates report descriptors into one, ie: to present several top-level
+/// collections distinguished by report IDs through a single interface.
+/// N must be the combined length of the descriptors, see total_len. Both can be
+/// evaluated in a const context, ie: `const DESC: [u8; LEN] = join(&[&A::DESC, &B::DESC]);`
+pub const fn join<const N: usize>(descs: &[&[u8]]) -> [u8; N] {
+    assert!(
+        total_len(descs) == N,
+        "N must be the combined length of the descriptors"
+    );
+    let mut out = [0u8; N];
+    let mut offset = 0;
+    let mut i = 0;
+    while i < descs.len() {
+        let mut j = 0;
+        while j < descs[i].len() {
+            out[offset] = descs[i][j];
+            offset += 1;
+            j += 1;
+        }
+        i += 1;
+    }
+    out
+}
+
+/// Returns the 32-bit FNV-1a hash of the given report descriptor. Asserting a known
+/// fingerprint in a test catches accidental changes to a descriptor, without spelling
+/// out every byte of it.
+pub const fn fingerprint(desc: &[u8]) -> u32 {
+    let mut hash: u32 = 0x811c_9dc5;
+    let mut i = 0;
+    while i < desc.len() {
+        hash ^= desc[i] as u32;
+        hash = hash.wrapping_mul(0x0100_0193);
+        i += 1;
+    }
+    hash
+}
 
 /// Prelude for modules which use the `gen_hid_descriptor` macro.
 pub mod generator_prelude {
-    pub use crate::descriptor::{AsInputReport, SerializedDescriptor};
+    pub use crate::descriptor::{
+        AsFeatureReport, AsInputReport, AsOutputReport, SerializedDescriptor,
+    };
+    pub use crate::hid_class::ReportType;
     pub use serde::ser::{Serialize, SerializeTuple, Serializer};
+    pub use usb_device::UsbError;
     pub use usbd_hid_macros::gen_hid_descriptor;
 }
 
@@ -56,6 +239,87 @@ pub struct MouseReport {
     pub pan: i8,   // Scroll left (negative) or right (positive) this many units
 }
 
+/// HighResScrollMouseReport describes a mouse with high-resolution scrolling, which
+/// sends the same input report as MouseReport.
+///
+/// The descriptor follows the layout documented by Microsoft in "Enhanced Wheel Support
+/// in Windows": a Resolution Multiplier feature, logical range 0..1 and physical range
+/// 1..4, shares a logical collection with the wheel, and another with AC Pan. A host
+/// which supports high-resolution scrolling sets the feature report, one byte per
+/// multiplier, through SET_REPORT, after which each unit of `wheel` or `pan` is a quarter
+/// of a detent. The feature report can be read with `HIDClass::pull_feature()`, see
+/// `wheel_multipliers()`.
+///
+/// Reference: <https://learn.microsoft.com/en-us/previous-versions/windows/hardware/design/dn613912(v=vs.85)>
+#[gen_hid_descriptor(
+    (collection = APPLICATION, usage_page = GENERIC_DESKTOP, usage = MOUSE) = {
+        (collection = LOGICAL, usage = MOUSE) = {
+            (collection = PHYSICAL, usage = POINTER) = {
+                (usage_page = BUTTON, usage_min = BUTTON_1, usage_max = BUTTON_8) = {
+                    #[packed_bits 8] #[item_settings data,variable,absolute] buttons=input;
+                };
+                (usage_page = GENERIC_DESKTOP,) = {
+                    (usage = X,) = {
+                        #[item_settings data,variable,relative] x=input;
+                    };
+                    (usage = Y,) = {
+                        #[item_settings data,variable,relative] y=input;
+                    };
+                };
+                (collection = LOGICAL,) = {
+                    (usage = RESOLUTION_MULTIPLIER, physical_min = 1, physical_max = 4) = {
+                        #[packed_bits 1] #[item_settings data,variable,absolute] wheel_multiplier=feature;
+                    };
+                    (usage = WHEEL,) = {
+                        #[item_settings data,variable,relative] wheel=input;
+                    };
+                };
+                (collection = LOGICAL,) = {
+                    (usage = RESOLUTION_MULTIPLIER, physical_min = 1, physical_max = 4) = {
+                        #[packed_bits 1] #[item_settings data,variable,absolute] pan_multiplier=feature;
+                    };
+                    (usage_page = CONSUMER, usage = AC_PAN) = {
+                        #[item_settings data,variable,relative] pan=input;
+                    };
+                };
+            };
+        };
+    }
+)]
+#[allow(dead_code)]
+pub struct HighResScrollMouseReport {
+    pub buttons: u8,
+    pub x: i8,
+    pub y: i8,
+    /// Set by the host to enable high-resolution scrolling of the wheel.
+    pub wheel_multiplier: u8,
+    pub wheel: i8,
+    /// Set by the host to enable high-resolution scrolling of AC Pan.
+    pub pan_multiplier: u8,
+    pub pan: i8,
+}
+
+impl HighResScrollMouseReport {
+    /// Resolution multiplier applied once the host enables high-resolution scrolling.
+    pub const RESOLUTION_MULTIPLIER: u8 = 4;
+
+    /// Returns the (wheel, pan) resolution multipliers selected by the feature report,
+    /// as set by the host. Each is 1 until the host enables high-resolution scrolling.
+    pub const fn wheel_multipliers(&self) -> (u8, u8) {
+        const fn multiplier(bit: u8) -> u8 {
+            if bit & 1 != 0 {
+                HighResScrollMouseReport::RESOLUTION_MULTIPLIER
+            } else {
+                1
+            }
+        }
+        (
+            multiplier(self.wheel_multiplier),
+            multiplier(self.pan_multiplier),
+        )
+    }
+}
+
 /// KeyboardReport describes a report and its companion descriptor that can be
 /// used to send keyboard button presses to a host and receive the status of the
 /// keyboard LEDs.
@@ -92,6 +356,78 @@ impl KeyboardReport {
             keycodes: [0u8; 6],
         }
     }
+
+    /// Builds a report with the given keys pressed. Modifier keys (LeftControl to
+    /// RightGUI, 0xE0..=0xE7) set the corresponding bit of `modifier`, other keys fill
+    /// `keycodes` in order.
+    ///
+    /// A BufferOverflow error is returned if more than 6 non-modifier keys are given;
+    /// a keyboard should then report the ErrorRollOver state instead.
+    pub fn from_keys(keys: &[KeyboardUsage]) -> crate::Result<Self> {
+        let mut report = Self::default();
+        let mut count = 0;
+        for key in keys {
+            let usage = *key as u8;
+            if (0xE0..=0xE7).contains(&usage) {
+                report.modifier |= 1 << (usage - 0xE0);
+            } else {
+                let slot = report
+                    .keycodes
+                    .get_mut(count)
+                    .ok_or(UsbError::BufferOverflow)?;
+                *slot = usage;
+                count += 1;
+            }
+        }
+        Ok(report)
+    }
+}
+
+/// KeyboardLedsReport is the decoded form of the LED output report of a
+/// KeyboardReport, sent by the host to indicate the state of the lock keys.
+///
+/// Reference: <https://usb.org/sites/default/files/hut1_3_0.pdf> (Section 11, page 101)
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct KeyboardLedsReport {
+    pub num_lock: bool,
+    pub caps_lock: bool,
+    pub scroll_lock: bool,
+    pub compose: bool,
+    pub kana: bool,
+}
+
+impl From<u8> for KeyboardLedsReport {
+    fn from(leds: u8) -> Self {
+        Self {
+            num_lock: leds & 0x01 != 0,
+            caps_lock: leds & 0x02 != 0,
+            scroll_lock: leds & 0x04 != 0,
+            compose: leds & 0x08 != 0,
+            kana: leds & 0x10 != 0,
+        }
+    }
+}
+
+impl TryFrom<&[u8]> for KeyboardLedsReport {
+    type Error = UsbError;
+
+    fn try_from(buf: &[u8]) -> Result<Self, Self::Error> {
+        match buf {
+            [leds] => Ok((*leds).into()),
+            _ => Err(UsbError::ParseError),
+        }
+    }
+}
+
+impl From<KeyboardLedsReport> for u8 {
+    fn from(leds: KeyboardLedsReport) -> u8 {
+        leds.num_lock as u8
+            | (leds.caps_lock as u8) << 1
+            | (leds.scroll_lock as u8) << 2
+            | (leds.compose as u8) << 3
+            | (leds.kana as u8) << 4
+    }
 }
 
 /// KeyboardUsage describes the key codes to be used in implementing a USB keyboard.
@@ -778,12 +1114,113 @@ impl From<u8> for KeyboardUsage {
     }
 }
 
+/// Physical keyboard layouts, which differ in the keys present beyond those of the
+/// US (ANSI) layout.
+#[non_exhaustive]
+#[derive(Copy, Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum KeyboardLayout {
+    /// US layout.
+    Ansi,
+    /// European layout, with an extra key beside the left shift key.
+    Iso,
+    /// Japanese layout.
+    Jis,
+    /// Korean layout.
+    Korean,
+    /// Brazilian layout.
+    Abnt2,
+}
+
+/// Keys found only on some keyboard layouts, see KeyboardLayout::usage.
+#[non_exhaustive]
+#[derive(Copy, Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum LayoutKey {
+    /// The key between the left shift key and Z.
+    IsoBackslash,
+    /// The key beside the return key, which is also present on the JIS layout.
+    IsoHash,
+    /// The key left of the right shift key: Ro on JIS, slash and question mark on ABNT2.
+    Ro,
+    /// The yen key left of the backspace key on JIS.
+    Yen,
+    /// Katakana/Hiragana on JIS.
+    KatakanaHiragana,
+    /// Henkan (conversion) on JIS.
+    Henkan,
+    /// Muhenkan (no conversion) on JIS.
+    Muhenkan,
+    /// Hangul/English toggle on Korean keyboards.
+    HangulEnglish,
+    /// Hanja conversion on Korean keyboards.
+    Hanja,
+    /// The keypad separator key below the keypad plus key on ABNT2.
+    KeypadSeparator,
+}
+
+impl KeyboardLayout {
+    /// Returns the usage reported for the given key on this layout, or None if
+    /// the layout does not have the key.
+    ///
+    /// Reference: <https://usb.org/sites/default/files/hut1_3_0.pdf> (Section 10, footnotes 15 to 29)
+    pub const fn usage(self, key: LayoutKey) -> Option<KeyboardUsage> {
+        use KeyboardLayout::*;
+        use LayoutKey::*;
+        let usage = match (self, key) {
+            (Iso | Abnt2, IsoBackslash) => KeyboardUsage::KeyboardNonUSSlash,
+            (Iso | Jis | Abnt2, IsoHash) => KeyboardUsage::KeyboardNonUSHash,
+            (Jis | Abnt2, Ro) => KeyboardUsage::KeyboardInternational1,
+            (Jis, KatakanaHiragana) => KeyboardUsage::KeyboardInternational2,
+            (Jis, Yen) => KeyboardUsage::KeyboardInternational3,
+            (Jis, Henkan) => KeyboardUsage::KeyboardInternational4,
+            (Jis, Muhenkan) => KeyboardUsage::KeyboardInternational5,
+            (Korean, HangulEnglish) => KeyboardUsage::KeyboardLANG1,
+            (Korean, Hanja) => KeyboardUsage::KeyboardLANG2,
+            (Abnt2, KeypadSeparator) => KeyboardUsage::KeypadComma,
+            _ => return None,
+        };
+        Some(usage)
+    }
+}
+
+/// KeypadReport describes a report and its companion descriptor that can be
+/// used to send key presses from a standalone numeric keypad to a host.
+///
+/// Key codes are the keypad values of KeyboardUsage, from KeypadNumLock (0x53)
+/// to KeypadEqual (0x67), which is also the logical range of the array. Slots without
+/// a key hold zero: it is outside of the logical range, so hosts ignore it.
+#[gen_hid_descriptor(
+    (collection = APPLICATION, usage_page = GENERIC_DESKTOP, usage = KEYPAD) = {
+        (usage_page = KEYBOARD, usage_min = KEYPAD_NUM_LOCK, usage_max = KEYPAD_EQUAL, logical_min = 0x53, logical_max = 0x67) = {
+            #[item_settings data,array,absolute] keycodes=input;
+        };
+    }
+)]
+#[allow(dead_code)]
+pub struct KeypadReport {
+    pub keycodes: [u8; 6],
+}
+
+impl KeypadReport {
+    pub const fn default() -> Self {
+        Self { keycodes: [0u8; 6] }
+    }
+}
+
 /// MediaKeyboardReport describes a report and descriptor that can be used to
 /// send consumer control commands to the host.
 ///
 /// This is commonly used for sending media player for keyboards with media player
 /// keys, but can be used for all sorts of Consumer Page functionality.
 ///
+/// The usage ID is an array item over the Consumer page usages 0x00..0x514. To release
+/// a key, send `MediaKey::Zero`: usage 0x00 is Unassigned, so the host sees no control
+/// asserted. The item is declared `not_null` as it has no null state of its own, every
+/// value within the logical range selects a usage. Hosts don't require the null state
+/// flag on array items; to declare it anyway, use `#[item_settings data,array,absolute,null]`
+/// in a custom descriptor.
+///
 /// Reference: <https://usb.org/sites/default/files/hut1_2.pdf>
 ///
 #[gen_hid_descriptor(
@@ -853,6 +1290,210 @@ impl From<u16> for MediaKey {
     }
 }
 
+/// KeyboardWithMediaReport combines KeyboardReport and MediaKeyboardReport in one
+/// descriptor, for a keyboard with media keys on a single interface. Key presses are
+/// sent as report ID 1, media keys as report ID 2, and the LED status is received as
+/// output report ID 1.
+///
+/// Use `keyboard()` or `media()` to select the report to push, ie:
+/// `hid.push_input(&report.media())`.
+#[gen_hid_descriptor(
+    (collection = APPLICATION, usage_page = GENERIC_DESKTOP, usage = KEYBOARD, report_id = 0x01) = {
+        (usage_page = KEYBOARD, usage_min = 0xE0, usage_max = 0xE7) = {
+            #[packed_bits 8] #[item_settings data,variable,absolute] modifier=input;
+        };
+        (usage_min = 0x00, usage_max = 0xFF) = {
+            #[item_settings constant,variable,absolute] reserved=input;
+        };
+        (usage_page = LEDS, usage_min = 0x01, usage_max = 0x05) = {
+            #[packed_bits 5] #[item_settings data,variable,absolute] leds=output;
+        };
+        (usage_page = KEYBOARD, usage_min = 0x00, usage_max = 0xDD) = {
+            #[item_settings data,array,absolute] keycodes=input;
+        };
+    },
+    (collection = APPLICATION, usage_page = CONSUMER, usage = CONSUMER_CONTROL, report_id = 0x02) = {
+        (usage_page = CONSUMER, usage_min = 0x00, usage_max = 0x514) = {
+            #[item_settings data,array,absolute,not_null] media_usage_id=input;
+        };
+    }
+)]
+#[allow(dead_code)]
+pub struct KeyboardWithMediaReport {
+    pub modifier: u8,
+    pub reserved: u8,
+    pub leds: u8,
+    pub keycodes: [u8; 6],
+    pub media_usage_id: u16,
+}
+
+impl KeyboardWithMediaReport {
+    pub const fn default() -> Self {
+        Self {
+            modifier: 0,
+            reserved: 0,
+            leds: 0,
+            keycodes: [0u8; 6],
+            media_usage_id: 0,
+        }
+    }
+
+    /// Returns the keyboard input report (report ID 1).
+    pub fn keyboard(&self) -> KeyboardWithMediaReportId1<'_> {
+        KeyboardWithMediaReportId1(self)
+    }
+
+    /// Returns the media key input report (report ID 2).
+    pub fn media(&self) -> KeyboardWithMediaReportId2<'_> {
+        KeyboardWithMediaReportId2(self)
+    }
+}
+
+/// VolumeKnobReport describes a report and descriptor that can be used by a volume
+/// knob: a rotary encoder whose push button mutes the audio.
+///
+/// The knob is reported as the Volume Increment & Volume Decrement one-shot controls
+/// of the Consumer page rather than a relative Volume value, as hosts map those to
+/// the system volume. Each detent is a press followed by a release of the control, so
+/// send `VolumeKnobReport::new(false, steps)` then `VolumeKnobReport::default()` for
+/// every detent turned. The Mute control (usage 0xE2, which has no named constant as
+/// `MUTE` is the LED page usage) toggles mute on press.
+///
+/// Reference: <https://usb.org/sites/default/files/hut1_2.pdf> (15.9.1: Audio Control)
+#[gen_hid_descriptor(
+    (collection = APPLICATION, usage_page = CONSUMER, usage = CONSUMER_CONTROL) = {
+        (usage = 0xE2, usage = VOLUME_INCREMENT, usage = VOLUME_DECREMENT) = {
+            #[packed_bits 3] #[item_settings data,variable,absolute] controls=input;
+        };
+    }
+)]
+#[allow(dead_code)]
+pub struct VolumeKnobReport {
+    /// Bit 0 is Mute, bit 1 Volume Increment and bit 2 Volume Decrement.
+    pub controls: u8,
+}
+
+impl VolumeKnobReport {
+    pub const MUTE: u8 = 0x01;
+    pub const VOLUME_INCREMENT: u8 = 0x02;
+    pub const VOLUME_DECREMENT: u8 = 0x04;
+
+    /// Builds a report for the given state of the mute button, and direction the knob
+    /// was turned in: clockwise (positive) raises the volume, anticlockwise (negative)
+    /// lowers it. Zero releases both volume controls.
+    pub const fn new(mute: bool, turn: i8) -> Self {
+        let mut controls = if mute { Self::MUTE } else { 0 };
+        if turn > 0 {
+            controls |= Self::VOLUME_INCREMENT;
+        } else if turn < 0 {
+            controls |= Self::VOLUME_DECREMENT;
+        }
+        Self { controls }
+    }
+}
+
+impl Default for VolumeKnobReport {
+    fn default() -> Self {
+        Self::new(false, 0)
+    }
+}
+
+/// XboxGamepadReport describes a report and its companion descriptor that can be used
+/// by a gamepad with the layout of an Xbox controller: two sticks, two analog triggers,
+/// a D-pad, ten buttons and the View / Back button.
+///
+/// Xbox controllers don't use a vendor usage page for their inputs: over Bluetooth they
+/// present a standard HID gamepad laid out like this report, and wired ones aren't HID
+/// devices at all (they use Microsoft's vendor-specific XUSB / GIP interfaces). Windows
+/// only maps a HID gamepad to XInput for Microsoft's own vendor & product IDs, so a device
+/// using this report enumerates as a "HID-compliant game controller": it works with
+/// DirectInput, Windows.Gaming.Input, SDL and browsers, but not with XInput-only games.
+///
+/// The sticks are unsigned, centered on 0x8000, with Y increasing downwards. The triggers
+/// range from 0 to 1023. The D-pad is a hat switch: 0 when released, then 1 (up) to 8
+/// (up-left) clockwise, in steps of 45 degrees. See the `BUTTON_*` constants for the
+/// buttons, in the order SDL and browsers expect them for Xbox controllers.
+///
+/// Reference: <https://usb.org/sites/default/files/hut1_4.pdf> (4: Generic Desktop Page,
+/// 5: Simulation Controls Page)
+#[gen_hid_descriptor(
+    (collection = APPLICATION, usage_page = GENERIC_DESKTOP, usage = GAMEPAD) = {
+        (collection = PHYSICAL, usage = POINTER) = {
+            (usage = X, usage = Y) = {
+                #[item_settings data,variable,absolute] left_stick=input;
+            };
+        };
+        (collection = PHYSICAL, usage = POINTER) = {
+            (usage = RX, usage = RY) = {
+                #[item_settings data,variable,absolute] right_stick=input;
+            };
+        };
+        (usage_page = SIMULATION_CONTROLS, usage = BRAKE) = {
+            #[logical_max 1023] #[item_settings data,variable,absolute] left_trigger=input;
+        };
+        (usage = ACCELERATOR,) = {
+            #[logical_max 1023] #[item_settings data,variable,absolute] right_trigger=input;
+        };
+        (usage_page = GENERIC_DESKTOP, usage = HAT_SWITCH, logical_min = 1, logical_max = 8,
+         physical_max = 315, unit = DEGREES) = {
+            #[item_settings data,variable,absolute,null] dpad=input;
+        };
+        (usage_page = BUTTON, usage_min = 0x01, usage_max = 0x0A) = {
+            #[packed_bits 10] #[item_settings data,variable,absolute] buttons=input;
+        };
+        (usage_page = CONSUMER, usage = AC_BACK) = {
+            #[packed_bits 1] #[item_settings data,variable,absolute] view=input;
+        };
+    }
+)]
+#[allow(dead_code)]
+pub struct XboxGamepadReport {
+    /// Left stick X & Y.
+    pub left_stick: [u16; 2],
+    /// Right stick X & Y.
+    pub right_stick: [u16; 2],
+    pub left_trigger: u16,
+    pub right_trigger: u16,
+    /// Hat switch position: 0 when released, 1 (up) to 8 (up-left) clockwise.
+    pub dpad: u8,
+    /// One bit per button, see the `BUTTON_*` constants.
+    pub buttons: u16,
+    /// Bit 0 is set while the View (Back) button is pressed.
+    pub view: u8,
+}
+
+impl XboxGamepadReport {
+    pub const BUTTON_A: u16 = 1 << 0;
+    pub const BUTTON_B: u16 = 1 << 1;
+    pub const BUTTON_X: u16 = 1 << 2;
+    pub const BUTTON_Y: u16 = 1 << 3;
+    pub const BUTTON_LEFT_BUMPER: u16 = 1 << 4;
+    pub const BUTTON_RIGHT_BUMPER: u16 = 1 << 5;
+    /// The Menu (Start) button.
+    pub const BUTTON_MENU: u16 = 1 << 6;
+    /// The Xbox (Guide) button.
+    pub const BUTTON_GUIDE: u16 = 1 << 7;
+    /// Pressing the left stick.
+    pub const BUTTON_LEFT_STICK: u16 = 1 << 8;
+    /// Pressing the right stick.
+    pub const BUTTON_RIGHT_STICK: u16 = 1 << 9;
+    /// Stick value when the stick is at rest.
+    pub const STICK_CENTER: u16 = 0x8000;
+
+    /// Builds a report with the sticks centered and nothing pressed.
+    pub const fn default() -> Self {
+        Self {
+            left_stick: [Self::STICK_CENTER; 2],
+            right_stick: [Self::STICK_CENTER; 2],
+            left_trigger: 0,
+            right_trigger: 0,
+            dpad: 0,
+            buttons: 0,
+            view: 0,
+        }
+    }
+}
+
 /// SystemControlReport describes a report and descriptor that can be used to
 /// send system control commands to the host.
 ///
@@ -875,6 +1516,17 @@ pub struct SystemControlReport {
     pub usage_id: u8,
 }
 
+impl SystemControlReport {
+    /// Builds a report which releases any previously reported key.
+    ///
+    /// The report holds an array item, so a usage ID of 0 is valid even though the
+    /// descriptor's logical minimum is 1: the HID specification treats an out of range
+    /// array value as no control being asserted, which hosts report as a release.
+    pub const fn release() -> Self {
+        Self { usage_id: 0 }
+    }
+}
+
 /// System control usage ids to use with SystemControlReport
 #[non_exhaustive]
 #[repr(u8)]
@@ -979,6 +1631,117 @@ impl From<u8> for SystemControlKey {
     }
 }
 
+/// LampArrayReport describes the feature reports and their companion descriptor that can
+/// be used to expose per-key RGB lighting to the host as a LampArray, ie: for Windows
+/// Dynamic Lighting. All of the reports are feature reports, exchanged with GET_REPORT and
+/// SET_REPORT requests, so this struct only documents their layout:
+///
+///  - Report 1 (LampArrayAttributesReport, GET_REPORT): the number of lamps & their bounds.
+///  - Report 2 (LampAttributesRequestReport, SET_REPORT): selects the lamp described next.
+///  - Report 3 (LampAttributesResponseReport, GET_REPORT): the attributes of that lamp.
+///  - Report 4 (LampMultiUpdateReport, SET_REPORT): sets the color of up to 8 lamps.
+///  - Report 5 (LampRangeUpdateReport, SET_REPORT): sets the color of a range of lamps.
+///  - Report 6 (LampArrayControlReport, SET_REPORT): enables or disables autonomous mode.
+///
+/// Reference: <https://usb.org/sites/default/files/hut1_4.pdf> (26: Lighting And Illumination
+/// Page), and Microsoft's "Dynamic Lighting devices" documentation.
+#[gen_hid_descriptor(
+    (collection = APPLICATION, usage_page = LIGHTING_AND_ILLUMINATION, usage = LAMP_ARRAY) = {
+        (collection = LOGICAL, usage = LAMP_ARRAY_ATTRIBUTES_REPORT, report_id = 0x01) = {
+            (usage = LAMP_COUNT,) = {
+                #[item_settings constant,variable,absolute] lamp_count=feature;
+            };
+            (usage = BOUNDING_BOX_WIDTH_IN_MICROMETERS, usage = BOUNDING_BOX_HEIGHT_IN_MICROMETERS,
+             usage = BOUNDING_BOX_DEPTH_IN_MICROMETERS, usage = LAMP_ARRAY_KIND,
+             usage = MIN_UPDATE_INTERVAL_IN_MICROSECONDS) = {
+                #[item_settings constant,variable,absolute] array_attributes=feature;
+            };
+        };
+        (collection = LOGICAL, usage = LAMP_ATTRIBUTES_REQUEST_REPORT, report_id = 0x02) = {
+            (usage = LAMP_ID,) = {
+                #[item_settings data,variable,absolute] requested_lamp_id=feature;
+            };
+        };
+        (collection = LOGICAL, usage = LAMP_ATTRIBUTES_RESPONSE_REPORT, report_id = 0x03) = {
+            (usage = LAMP_ID,) = {
+                #[item_settings data,variable,absolute] lamp_id=feature;
+            };
+            (usage = POSITION_X_IN_MICROMETERS, usage = POSITION_Y_IN_MICROMETERS,
+             usage = POSITION_Z_IN_MICROMETERS, usage = UPDATE_LATENCY_IN_MICROSECONDS,
+             usage = LAMP_PURPOSES) = {
+                #[item_settings data,variable,absolute] lamp_attributes=feature;
+            };
+            (usage = RED_LEVEL_COUNT, usage = GREEN_LEVEL_COUNT, usage = BLUE_LEVEL_COUNT,
+             usage = INTENSITY_LEVEL_COUNT, usage = IS_PROGRAMMABLE, usage = INPUT_BINDING) = {
+                #[item_settings data,variable,absolute] lamp_capabilities=feature;
+            };
+        };
+        (collection = LOGICAL, usage = LAMP_MULTI_UPDATE_REPORT, report_id = 0x04) = {
+            (usage = LAMP_COUNT, usage = LAMP_UPDATE_FLAGS) = {
+                #[item_settings data,variable,absolute] multi_update_header=feature;
+            };
+            (usage = LAMP_ID,) = {
+                #[item_settings data,variable,absolute] multi_update_lamp_ids=feature;
+            };
+            (usage = RED_UPDATE_CHANNEL, usage = GREEN_UPDATE_CHANNEL, usage = BLUE_UPDATE_CHANNEL, usage = INTENSITY_UPDATE_CHANNEL,
+                usage = RED_UPDATE_CHANNEL, usage = GREEN_UPDATE_CHANNEL, usage = BLUE_UPDATE_CHANNEL, usage = INTENSITY_UPDATE_CHANNEL,
+                usage = RED_UPDATE_CHANNEL, usage = GREEN_UPDATE_CHANNEL, usage = BLUE_UPDATE_CHANNEL, usage = INTENSITY_UPDATE_CHANNEL,
+                usage = RED_UPDATE_CHANNEL, usage = GREEN_UPDATE_CHANNEL, usage = BLUE_UPDATE_CHANNEL, usage = INTENSITY_UPDATE_CHANNEL,
+                usage = RED_UPDATE_CHANNEL, usage = GREEN_UPDATE_CHANNEL, usage = BLUE_UPDATE_CHANNEL, usage = INTENSITY_UPDATE_CHANNEL,
+                usage = RED_UPDATE_CHANNEL, usage = GREEN_UPDATE_CHANNEL, usage = BLUE_UPDATE_CHANNEL, usage = INTENSITY_UPDATE_CHANNEL,
+                usage = RED_UPDATE_CHANNEL, usage = GREEN_UPDATE_CHANNEL, usage = BLUE_UPDATE_CHANNEL, usage = INTENSITY_UPDATE_CHANNEL,
+                usage = RED_UPDATE_CHANNEL, usage = GREEN_UPDATE_CHANNEL, usage = BLUE_UPDATE_CHANNEL, usage = INTENSITY_UPDATE_CHANNEL) = {
+                #[item_settings data,variable,absolute] multi_update_colors=feature;
+            };
+        };
+        (collection = LOGICAL, usage = LAMP_RANGE_UPDATE_REPORT, report_id = 0x05) = {
+            (usage = LAMP_UPDATE_FLAGS,) = {
+                #[item_settings data,variable,absolute] range_update_flags=feature;
+            };
+            (usage = LAMP_ID_START, usage = LAMP_ID_END) = {
+                #[item_settings data,variable,absolute] range_update_lamp_ids=feature;
+            };
+            (usage = RED_UPDATE_CHANNEL, usage = GREEN_UPDATE_CHANNEL,
+             usage = BLUE_UPDATE_CHANNEL, usage = INTENSITY_UPDATE_CHANNEL) = {
+                #[item_settings data,variable,absolute] range_update_color=feature;
+            };
+        };
+        (collection = LOGICAL, usage = LAMP_ARRAY_CONTROL_REPORT, report_id = 0x06) = {
+            (usage = AUTONOMOUS_MODE,) = {
+                #[item_settings data,variable,absolute] autonomous_mode=feature;
+            };
+        };
+    }
+)]
+#[allow(dead_code)]
+pub struct LampArrayReport {
+    /// Number of lamps in the array.
+    pub lamp_count: u16,
+    /// Bounding box width, height & depth in micrometers, the LampArrayKind and the
+    /// minimal update interval in microseconds.
+    pub array_attributes: [u32; 5],
+    /// Lamp whose attributes the host requests next.
+    pub requested_lamp_id: u16,
+    /// Lamp described by the attributes response.
+    pub lamp_id: u16,
+    /// Position x, y & z in micrometers, update latency in microseconds and LampPurposes.
+    pub lamp_attributes: [u32; 5],
+    /// Red, green, blue & intensity level counts, IsProgrammable and InputBinding.
+    pub lamp_capabilities: [u8; 6],
+    /// Number of lamps updated and the LampUpdateFlags.
+    pub multi_update_header: [u8; 2],
+    pub multi_update_lamp_ids: [u16; 8],
+    /// Red, green, blue & intensity of each updated lamp.
+    pub multi_update_colors: [u8; 32],
+    pub range_update_flags: u8,
+    /// First and last lamp of the range.
+    pub range_update_lamp_ids: [u16; 2],
+    /// Red, green, blue & intensity of the lamps in the range.
+    pub range_update_color: [u8; 4],
+    /// Non-zero while the device controls its own lighting.
+    pub autonomous_mode: u8,
+}
+
 /// CtapReport describes a report and its companion descriptor that can be
 /// used to present a FIDO-compatible authenticator device to the host.
 #[gen_hid_descriptor(
@@ -996,3 +1759,527 @@ pub struct CtapReport {
     pub data_in: [u8; 64],
     pub data_out: [u8; 64],
 }
+
+/// PowerDeviceReport describes a report and its companion descriptor that can
+/// be used to report the state of a battery to the host, using the Power Device
+/// and Battery System usage pages.
+///
+/// This is a minimal skeleton: a complete power device typically exposes most
+/// values as feature reports.
+///
+/// Reference: <https://www.usb.org/sites/default/files/pdcv11.pdf>
+#[gen_hid_descriptor(
+    (collection = APPLICATION, usage_page = POWER_DEVICE, usage = POWER_SUMMARY) = {
+        (usage_page = BATTERY_SYSTEM, usage = REMAINING_CAPACITY) = {
+            #[percent] #[item_settings data,variable,absolute] remaining_capacity=input;
+        };
+        (usage = RUN_TIME_TO_EMPTY, unit = SECONDS) = {
+            #[item_settings data,variable,absolute] run_time_to_empty=input;
+        };
+        (usage_page = POWER_DEVICE, usage = VOLTAGE, unit = VOLTS, unit_exponent = 4) = {
+            #[item_settings data,variable,absolute] voltage=input;
+        };
+        (usage = PRESENT,) = {
+            #[packed_bits 1] #[item_settings data,variable,absolute] present=input;
+        };
+    }
+)]
+pub struct PowerDeviceReport {
+    /// Remaining capacity, in percent (0 to 100).
+    pub remaining_capacity: u8,
+    /// Estimated time until the battery is empty, in seconds.
+    pub run_time_to_empty: u16,
+    /// Battery voltage, in millivolts.
+    pub voltage: u16,
+    /// Bit 0 is set when the battery is present.
+    pub present: u8,
+}
+
+/// StylusReport describes a report and its companion descriptor that can be used
+/// to send the state of a digitizer pen to a host: its position, tip pressure,
+/// tilt and buttons.
+///
+/// Reference: <https://usb.org/sites/default/files/hut1_4.pdf> (16: Digitizers Page)
+#[gen_hid_descriptor(
+    (collection = APPLICATION, usage_page = DIGITIZER, usage = PEN) = {
+        (collection = PHYSICAL, usage = STYLUS) = {
+            (usage = TIP_SWITCH, usage = BARREL_SWITCH, usage = ERASER, usage = IN_RANGE) = {
+                #[packed_bits 4] #[item_settings data,variable,absolute] buttons=input;
+            };
+            (usage = TIP_PRESSURE,) = {
+                #[item_settings data,variable,absolute] tip_pressure=input;
+            };
+            (usage = X_TILT,) = {
+                #[logical_min -90] #[logical_max 90] #[item_settings data,variable,absolute] x_tilt=input;
+            };
+            (usage = Y_TILT,) = {
+                #[logical_min -90] #[logical_max 90] #[item_settings data,variable,absolute] y_tilt=input;
+            };
+            (usage_page = GENERIC_DESKTOP,) = {
+                (usage = X,) = {
+                    #[item_settings data,variable,absolute] x=input;
+                };
+                (usage = Y,) = {
+                    #[item_settings data,variable,absolute] y=input;
+                };
+            };
+        };
+    }
+)]
+#[allow(dead_code)]
+pub struct StylusReport {
+    /// Bit 0: tip switch, bit 1: barrel switch, bit 2: eraser, bit 3: in range.
+    pub buttons: u8,
+    pub tip_pressure: u16,
+    /// Tilt along the X axis, in degrees.
+    pub x_tilt: i8,
+    /// Tilt along the Y axis, in degrees.
+    pub y_tilt: i8,
+    pub x: u16,
+    pub y: u16,
+}
+
+/// GazeReport describes a report and its companion descriptor that can be used
+/// by an eye tracker to send the point on the screen the user is looking at.
+///
+/// The coordinates are in micrometers, relative to the top-left corner of the
+/// screen, as declared by the descriptor's unit.
+///
+/// Reference: <https://usb.org/sites/default/files/hut1_4.pdf> (18: Eye and Head Trackers Page)
+#[gen_hid_descriptor(
+    (collection = APPLICATION, usage_page = EYE_HEAD_TRACKERS, usage = EYE_TRACKER) = {
+        (collection = PHYSICAL, usage = GAZE_POINT, unit = CENTIMETERS, unit_exponent = -4) = {
+            (usage = POSITION_X,) = {
+                #[item_settings data,variable,absolute] x=input;
+            };
+            (usage = POSITION_Y,) = {
+                #[item_settings data,variable,absolute] y=input;
+            };
+        };
+    }
+)]
+#[allow(dead_code)]
+pub struct GazeReport {
+    pub x: i32,
+    pub y: i32,
+}
+
+/// DigitizerReport describes a report and its companion descriptor that can be used
+/// by a touch screen to send a single contact to the host.
+///
+/// Touch digitizers must report a scan time: the time at which the contacts were
+/// sampled, in units of 100 microseconds, relative to an arbitrary origin. The value
+/// wraps around at 65536 (about 6.5 seconds); hosts only use the difference between
+/// consecutive reports, to compute the velocity of each contact. Derive it from a
+/// monotonic clock with `scan_time()`, ie:
+/// `report.scan_time = DigitizerReport::scan_time(timer.now_micros())`.
+///
+/// Reference: <https://usb.org/sites/default/files/hut1_4.pdf> (16: Digitizers Page)
+#[gen_hid_descriptor(
+    (collection = APPLICATION, usage_page = DIGITIZER, usage = TOUCH_SCREEN) = {
+        (collection = LOGICAL, usage = FINGER) = {
+            (usage = TIP_SWITCH,) = {
+                #[packed_bits 1] #[item_settings data,variable,absolute] tip_switch=input;
+            };
+            (usage = CONTACT_IDENTIFIER,) = {
+                #[item_settings data,variable,absolute] contact_id=input;
+            };
+            (usage_page = GENERIC_DESKTOP,) = {
+                (usage = X,) = {
+                    #[item_settings data,variable,absolute] x=input;
+                };
+                (usage = Y,) = {
+                    #[item_settings data,variable,absolute] y=input;
+                };
+            };
+        };
+        (usage_page = DIGITIZER, usage = SCAN_TIME, unit = SECONDS, unit_exponent = -4) = {
+            #[item_settings data,variable,absolute] scan_time=input;
+        };
+        (usage = CONTACT_COUNT,) = {
+            #[item_settings data,variable,absolute] contact_count=input;
+        };
+    }
+)]
+#[allow(dead_code)]
+pub struct DigitizerReport {
+    /// Bit 0 is set while the contact touches the screen.
+    pub tip_switch: u8,
+    pub contact_id: u8,
+    pub x: u16,
+    pub y: u16,
+    /// Time the contact was sampled, in units of 100 microseconds.
+    pub scan_time: u16,
+    /// Number of contacts currently on the screen.
+    pub contact_count: u8,
+}
+
+impl DigitizerReport {
+    /// Converts a monotonic timestamp in microseconds to a scan time, in units of
+    /// 100 microseconds which wrap around at 65536.
+    pub const fn scan_time(micros: u64) -> u16 {
+        (micros / 100) as u16
+    }
+}
+
+/// RotaryEncoderReport describes a report and its companion descriptor that can be
+/// used by a dial or rotary encoder to send its absolute angle, in degrees, and the
+/// state of its push button to the host.
+///
+/// The angle has a logical range of 0..359 mapped to a physical range of 0..359, in
+/// the English Rotation unit system (degrees) with a unit exponent of 0, so each
+/// count is one degree on the host. The Unit and physical items are declared after
+/// the button, so they only apply to the angle.
+///
+/// Reference: <https://www.usb.org/sites/default/files/hid1_11.pdf> (6.2.2.7: Unit)
+#[gen_hid_descriptor(
+    (collection = APPLICATION, usage_page = GENERIC_DESKTOP, usage = MULTI_AXIS_CONTROLLER) = {
+        (usage_page = BUTTON, usage = BUTTON_1) = {
+            #[packed_bits 1] #[item_settings data,variable,absolute] buttons=input;
+        };
+        (usage_page = GENERIC_DESKTOP, usage = DIAL, unit = DEGREES, logical_max = 359, physical_max = 359) = {
+            #[item_settings data,variable,absolute] angle=input;
+        };
+    }
+)]
+#[allow(dead_code)]
+pub struct RotaryEncoderReport {
+    /// Bit 0 is set while the button is pressed.
+    pub buttons: u8,
+    /// Absolute angle in degrees, from 0 to 359.
+    pub angle: u16,
+}
+
+impl RotaryEncoderReport {
+    /// Creates a report for the given angle in degrees, which wraps around at 360.
+    pub const fn new(degrees: u16, pressed: bool) -> Self {
+        RotaryEncoderReport {
+            buttons: pressed as u8,
+            angle: degrees % 360,
+        }
+    }
+}
+
+/// TouchpadContact describes one contact of a PrecisionTouchpadReport.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+#[repr(C, packed)]
+pub struct TouchpadContact {
+    /// Bit 0 is Confidence, bit 1 is Tip Switch and bits 2..7 the contact identifier.
+    pub flags: u8,
+    /// Horizontal position, from 0 to 4095.
+    pub x: u16,
+    /// Vertical position, from 0 to 4095.
+    pub y: u16,
+}
+
+impl TouchpadContact {
+    /// Creates a contact with the given identifier, which must be below 64. Confidence
+    /// should be cleared for contacts which are too large to be a finger, ie: a palm.
+    pub const fn new(contact_id: u8, x: u16, y: u16, confidence: bool, tip_switch: bool) -> Self {
+        TouchpadContact {
+            flags: (contact_id << 2) | ((tip_switch as u8) << 1) | confidence as u8,
+            x,
+            y,
+        }
+    }
+}
+
+impl Serialize for TouchpadContact {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut s = serializer.serialize_tuple(3)?;
+        s.serialize_element(&{ self.flags })?;
+        s.serialize_element(&{ self.x })?;
+        s.serialize_element(&{ self.y })?;
+        s.end()
+    }
+}
+
+/// PrecisionTouchpadReport describes a report and its companion descriptor that can be
+/// used by a Windows Precision Touchpad, which reports up to five contacts and a button.
+///
+/// Besides the input report (ID 1), Windows requires the following feature reports,
+/// answered by the device with GET_REPORT and SET_REPORT requests:
+///
+///  - Report 2 (GET_REPORT): Contact Count Maximum & Pad Type, see `DEVICE_CAPABILITIES`,
+///    ie: `hid.set_get_report_response(ReportType::Feature, 2, &DEVICE_CAPABILITIES)`, with
+///    room for the response enabled by `HIDClass::with_storage`.
+///  - Report 4 (SET_REPORT): the input mode, `INPUT_MODE_TOUCHPAD` once the touchpad
+///    should send reports in this format rather than as a mouse.
+///  - Report 5 (SET_REPORT): the surface (bit 0) and button (bit 1) switches.
+///
+/// The contacts are reported in parallel mode: all of them, every scan, with
+/// `contact_count` set to the number of contacts in use. Scan time is in units of 100
+/// microseconds, see `DigitizerReport::scan_time()`. X & Y have a logical range of
+/// 0..4095 on a 4.00 x 2.75 inch surface.
+///
+/// The descriptor leaves out the device certification status feature report (ID 3): its
+/// 256 byte blob doesn't fit in the control buffer, so GET_REPORT requests for it could
+/// never be answered. Windows then treats the touchpad as uncertified, but still uses it.
+///
+/// The descriptor is written out by hand, as `gen_hid_descriptor` can't describe the
+/// array of `TouchpadContact` structs, each of which is a logical collection.
+///
+/// Reference: Microsoft's "Windows Precision Touchpad required HID top-level
+/// collections" documentation, and its sample report descriptor.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+#[repr(C, packed)]
+pub struct PrecisionTouchpadReport {
+    pub contacts: [TouchpadContact; 5],
+    /// Time the contacts were sampled, in units of 100 microseconds.
+    pub scan_time: u16,
+    /// Number of valid contacts in this report.
+    pub contact_count: u8,
+    /// Bit 0 is set while the touchpad is clicked.
+    pub buttons: u8,
+}
+
+impl PrecisionTouchpadReport {
+    /// Report ID of the input report.
+    pub const REPORT_ID: u8 = 0x01;
+    /// Report ID of the Device Capabilities feature report.
+    pub const DEVICE_CAPABILITIES_REPORT_ID: u8 = 0x02;
+    /// Report ID of the input mode feature report.
+    pub const INPUT_MODE_REPORT_ID: u8 = 0x04;
+    /// Report ID of the function switch feature report.
+    pub const FUNCTION_SWITCH_REPORT_ID: u8 = 0x05;
+    /// Number of contacts in each report.
+    pub const MAX_CONTACTS: usize = 5;
+    /// Input mode value the host sets to select touchpad reports.
+    pub const INPUT_MODE_TOUCHPAD: u8 = 0x03;
+    /// Response to a GET_REPORT request for the Device Capabilities feature report: five
+    /// contacts and a click-pad (pad type 0).
+    pub const DEVICE_CAPABILITIES: [u8; 2] = [Self::DEVICE_CAPABILITIES_REPORT_ID, 0x05];
+
+    /// Length in bytes of the report descriptor.
+    pub const DESC_LEN: usize = Self::DESC.len();
+    /// The report descriptor, usable in const contexts.
+    #[rustfmt::skip]
+    pub const DESC: [u8; 409] = [
+        0x05, 0x0D,                   // Usage Page (Digitizer)
+        0x09, 0x05,                   // Usage (Touch Pad)
+        0xA1, 0x01,                   // Collection (Application)
+        0x85, 0x01,                   //   Report ID (1)
+        0x09, 0x22,                   //   Usage (Finger)
+        0xA1, 0x02,                   //   Collection (Logical)
+        0x15, 0x00,                   //     Logical Minimum (0)
+        0x25, 0x01,                   //     Logical Maximum (1)
+        0x09, 0x47,                   //     Usage (Confidence)
+        0x09, 0x42,                   //     Usage (Tip Switch)
+        0x95, 0x02,                   //     Report Count (2)
+        0x75, 0x01,                   //     Report Size (1)
+        0x81, 0x02,                   //     Input (Data,Var,Abs)
+        0x95, 0x01,                   //     Report Count (1)
+        0x75, 0x06,                   //     Report Size (6)
+        0x25, 0x3F,                   //     Logical Maximum (63)
+        0x09, 0x51,                   //     Usage (Contact Identifier)
+        0x81, 0x02,                   //     Input (Data,Var,Abs)
+        0x05, 0x01,                   //     Usage Page (Generic Desktop)
+        0x26, 0xFF, 0x0F,             //     Logical Maximum (4095)
+        0x75, 0x10,                   //     Report Size (16)
+        0x55, 0x0E,                   //     Unit Exponent (-2)
+        0x65, 0x13,                   //     Unit (English Linear: Inch)
+        0x09, 0x30,                   //     Usage (X)
+        0x35, 0x00,                   //     Physical Minimum (0)
+        0x46, 0x90, 0x01,             //     Physical Maximum (400)
+        0x81, 0x02,                   //     Input (Data,Var,Abs)
+        0x46, 0x13, 0x01,             //     Physical Maximum (275)
+        0x09, 0x31,                   //     Usage (Y)
+        0x81, 0x02,                   //     Input (Data,Var,Abs)
+        0xC0,                         //   End Collection
+        0x05, 0x0D,                   //   Usage Page (Digitizer)
+        0x09, 0x22,                   //   Usage (Finger)
+        0xA1, 0x02,                   //   Collection (Logical)
+        0x15, 0x00,                   //     Logical Minimum (0)
+        0x25, 0x01,                   //     Logical Maximum (1)
+        0x09, 0x47,                   //     Usage (Confidence)
+        0x09, 0x42,                   //     Usage (Tip Switch)
+        0x95, 0x02,                   //     Report Count (2)
+        0x75, 0x01,                   //     Report Size (1)
+        0x81, 0x02,                   //     Input (Data,Var,Abs)
+        0x95, 0x01,                   //     Report Count (1)
+        0x75, 0x06,                   //     Report Size (6)
+        0x25, 0x3F,                   //     Logical Maximum (63)
+        0x09, 0x51,                   //     Usage (Contact Identifier)
+        0x81, 0x02,                   //     Input (Data,Var,Abs)
+        0x05, 0x01,                   //     Usage Page (Generic Desktop)
+        0x26, 0xFF, 0x0F,             //     Logical Maximum (4095)
+        0x75, 0x10,                   //     Report Size (16)
+        0x55, 0x0E,                   //     Unit Exponent (-2)
+        0x65, 0x13,                   //     Unit (English Linear: Inch)
+        0x09, 0x30,                   //     Usage (X)
+        0x35, 0x00,                   //     Physical Minimum (0)
+        0x46, 0x90, 0x01,             //     Physical Maximum (400)
+        0x81, 0x02,                   //     Input (Data,Var,Abs)
+        0x46, 0x13, 0x01,             //     Physical Maximum (275)
+        0x09, 0x31,                   //     Usage (Y)
+        0x81, 0x02,                   //     Input (Data,Var,Abs)
+        0xC0,                         //   End Collection
+        0x05, 0x0D,                   //   Usage Page (Digitizer)
+        0x09, 0x22,                   //   Usage (Finger)
+        0xA1, 0x02,                   //   Collection (Logical)
+        0x15, 0x00,                   //     Logical Minimum (0)
+        0x25, 0x01,                   //     Logical Maximum (1)
+        0x09, 0x47,                   //     Usage (Confidence)
+        0x09, 0x42,                   //     Usage (Tip Switch)
+        0x95, 0x02,                   //     Report Count (2)
+        0x75, 0x01,                   //     Report Size (1)
+        0x81, 0x02,                   //     Input (Data,Var,Abs)
+        0x95, 0x01,                   //     Report Count (1)
+        0x75, 0x06,                   //     Report Size (6)
+        0x25, 0x3F,                   //     Logical Maximum (63)
+        0x09, 0x51,                   //     Usage (Contact Identifier)
+        0x81, 0x02,                   //     Input (Data,Var,Abs)
+        0x05, 0x01,                   //     Usage Page (Generic Desktop)
+        0x26, 0xFF, 0x0F,             //     Logical Maximum (4095)
+        0x75, 0x10,                   //     Report Size (16)
+        0x55, 0x0E,                   //     Unit Exponent (-2)
+        0x65, 0x13,                   //     Unit (English Linear: Inch)
+        0x09, 0x30,                   //     Usage (X)
+        0x35, 0x00,                   //     Physical Minimum (0)
+        0x46, 0x90, 0x01,             //     Physical Maximum (400)
+        0x81, 0x02,                   //     Input (Data,Var,Abs)
+        0x46, 0x13, 0x01,             //     Physical Maximum (275)
+        0x09, 0x31,                   //     Usage (Y)
+        0x81, 0x02,                   //     Input (Data,Var,Abs)
+        0xC0,                         //   End Collection
+        0x05, 0x0D,                   //   Usage Page (Digitizer)
+        0x09, 0x22,                   //   Usage (Finger)
+        0xA1, 0x02,                   //   Collection (Logical)
+        0x15, 0x00,                   //     Logical Minimum (0)
+        0x25, 0x01,                   //     Logical Maximum (1)
+        0x09, 0x47,                   //     Usage (Confidence)
+        0x09, 0x42,                   //     Usage (Tip Switch)
+        0x95, 0x02,                   //     Report Count (2)
+        0x75, 0x01,                   //     Report Size (1)
+        0x81, 0x02,                   //     Input (Data,Var,Abs)
+        0x95, 0x01,                   //     Report Count (1)
+        0x75, 0x06,                   //     Report Size (6)
+        0x25, 0x3F,                   //     Logical Maximum (63)
+        0x09, 0x51,                   //     Usage (Contact Identifier)
+        0x81, 0x02,                   //     Input (Data,Var,Abs)
+        0x05, 0x01,                   //     Usage Page (Generic Desktop)
+        0x26, 0xFF, 0x0F,             //     Logical Maximum (4095)
+        0x75, 0x10,                   //     Report Size (16)
+        0x55, 0x0E,                   //     Unit Exponent (-2)
+        0x65, 0x13,                   //     Unit (English Linear: Inch)
+        0x09, 0x30,                   //     Usage (X)
+        0x35, 0x00,                   //     Physical Minimum (0)
+        0x46, 0x90, 0x01,             //     Physical Maximum (400)
+        0x81, 0x02,                   //     Input (Data,Var,Abs)
+        0x46, 0x13, 0x01,             //     Physical Maximum (275)
+        0x09, 0x31,                   //     Usage (Y)
+        0x81, 0x02,                   //     Input (Data,Var,Abs)
+        0xC0,                         //   End Collection
+        0x05, 0x0D,                   //   Usage Page (Digitizer)
+        0x09, 0x22,                   //   Usage (Finger)
+        0xA1, 0x02,                   //   Collection (Logical)
+        0x15, 0x00,                   //     Logical Minimum (0)
+        0x25, 0x01,                   //     Logical Maximum (1)
+        0x09, 0x47,                   //     Usage (Confidence)
+        0x09, 0x42,                   //     Usage (Tip Switch)
+        0x95, 0x02,                   //     Report Count (2)
+        0x75, 0x01,                   //     Report Size (1)
+        0x81, 0x02,                   //     Input (Data,Var,Abs)
+        0x95, 0x01,                   //     Report Count (1)
+        0x75, 0x06,                   //     Report Size (6)
+        0x25, 0x3F,                   //     Logical Maximum (63)
+        0x09, 0x51,                   //     Usage (Contact Identifier)
+        0x81, 0x02,                   //     Input (Data,Var,Abs)
+        0x05, 0x01,                   //     Usage Page (Generic Desktop)
+        0x26, 0xFF, 0x0F,             //     Logical Maximum (4095)
+        0x75, 0x10,                   //     Report Size (16)
+        0x55, 0x0E,                   //     Unit Exponent (-2)
+        0x65, 0x13,                   //     Unit (English Linear: Inch)
+        0x09, 0x30,                   //     Usage (X)
+        0x35, 0x00,                   //     Physical Minimum (0)
+        0x46, 0x90, 0x01,             //     Physical Maximum (400)
+        0x81, 0x02,                   //     Input (Data,Var,Abs)
+        0x46, 0x13, 0x01,             //     Physical Maximum (275)
+        0x09, 0x31,                   //     Usage (Y)
+        0x81, 0x02,                   //     Input (Data,Var,Abs)
+        0xC0,                         //   End Collection
+        0x55, 0x0C,                   //   Unit Exponent (-4)
+        0x66, 0x01, 0x10,             //   Unit (SI Linear: Seconds)
+        0x47, 0xFF, 0xFF, 0x00, 0x00, //   Physical Maximum (65535)
+        0x27, 0xFF, 0xFF, 0x00, 0x00, //   Logical Maximum (65535)
+        0x75, 0x10,                   //   Report Size (16)
+        0x95, 0x01,                   //   Report Count (1)
+        0x05, 0x0D,                   //   Usage Page (Digitizer)
+        0x09, 0x56,                   //   Usage (Scan Time)
+        0x81, 0x02,                   //   Input (Data,Var,Abs)
+        0x09, 0x54,                   //   Usage (Contact Count)
+        0x25, 0x7F,                   //   Logical Maximum (127)
+        0x75, 0x08,                   //   Report Size (8)
+        0x81, 0x02,                   //   Input (Data,Var,Abs)
+        0x05, 0x09,                   //   Usage Page (Button)
+        0x09, 0x01,                   //   Usage (Button 1)
+        0x25, 0x01,                   //   Logical Maximum (1)
+        0x75, 0x01,                   //   Report Size (1)
+        0x81, 0x02,                   //   Input (Data,Var,Abs)
+        0x95, 0x07,                   //   Report Count (7)
+        0x81, 0x03,                   //   Input (Const,Var,Abs)
+        0x05, 0x0D,                   //   Usage Page (Digitizer)
+        0x85, 0x02,                   //   Report ID (2)
+        0x09, 0x55,                   //   Usage (Contact Count Maximum)
+        0x09, 0x59,                   //   Usage (Pad Type)
+        0x75, 0x04,                   //   Report Size (4)
+        0x95, 0x02,                   //   Report Count (2)
+        0x25, 0x0F,                   //   Logical Maximum (15)
+        0xB1, 0x02,                   //   Feature (Data,Var,Abs)
+        0xC0,                         // End Collection
+        0x05, 0x0D,                   // Usage Page (Digitizer)
+        0x09, 0x0E,                   // Usage (Device Configuration)
+        0xA1, 0x01,                   // Collection (Application)
+        0x85, 0x04,                   //   Report ID (4)
+        0x09, 0x22,                   //   Usage (Finger)
+        0xA1, 0x02,                   //   Collection (Logical)
+        0x09, 0x52,                   //     Usage (Input Mode)
+        0x15, 0x00,                   //     Logical Minimum (0)
+        0x25, 0x0A,                   //     Logical Maximum (10)
+        0x75, 0x08,                   //     Report Size (8)
+        0x95, 0x01,                   //     Report Count (1)
+        0xB1, 0x02,                   //     Feature (Data,Var,Abs)
+        0xC0,                         //   End Collection
+        0x09, 0x22,                   //   Usage (Finger)
+        0xA1, 0x00,                   //   Collection (Physical)
+        0x85, 0x05,                   //     Report ID (5)
+        0x09, 0x57,                   //     Usage (Surface Switch)
+        0x09, 0x58,                   //     Usage (Button Switch)
+        0x75, 0x01,                   //     Report Size (1)
+        0x95, 0x02,                   //     Report Count (2)
+        0x25, 0x01,                   //     Logical Maximum (1)
+        0xB1, 0x02,                   //     Feature (Data,Var,Abs)
+        0x95, 0x06,                   //     Report Count (6)
+        0xB1, 0x03,                   //     Feature (Const,Var,Abs)
+        0xC0,                         //   End Collection
+        0xC0,                         // End Collection
+    ];
+}
+
+impl SerializedDescriptor for PrecisionTouchpadReport {
+    const TOP_COLLECTION: Option<u8> = None;
+
+    fn desc() -> &'static [u8] {
+        &Self::DESC
+    }
+}
+
+impl Serialize for PrecisionTouchpadReport {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut s = serializer.serialize_tuple(4)?;
+        s.serialize_element(&{ self.contacts })?;
+        s.serialize_element(&{ self.scan_time })?;
+        s.serialize_element(&{ self.contact_count })?;
+        s.serialize_element(&{ self.buttons })?;
+        s.end()
+    }
+}
+impl AsInputReport for PrecisionTouchpadReport {
+    const REPORT_ID_PREFIX: Option<u8> = Some(Self::REPORT_ID);
+}
@@ -0,0 +1,48 @@
+//! Descriptors and reports which `gen_hid_descriptor` must refuse to compile.
+//!
+//! Every field of the struct must be described by the descriptor. Where report IDs
+//! are not used, a struct whose size differs from that of its report fields fails
+//! to compile:
+//!
+//! ```compile_fail,E0080
+//! use usbd_hid::descriptor::generator_prelude::*;
+//!
+//! #[gen_hid_descriptor(
+//!     (collection = APPLICATION, usage_page = VENDOR_DEFINED_START, usage = 0x01) = {
+//!         f1=input;
+//!     }
+//! )]
+//! struct Padded {
+//!     f1: u8,
+//!     unused: u16,
+//! }
+//! ```
+//!
+//! Where several top-level collections are declared, each must declare a report ID:
+//!
+//! ```compile_fail
+//! use usbd_hid::descriptor::generator_prelude::*;
+//!
+//! #[gen_hid_descriptor(
+//!     (collection = APPLICATION, usage_page = GENERIC_DESKTOP, usage = KEYBOARD, report_id = 0x01) = {
+//!         keycode=input;
+//!     },
+//!     (collection = APPLICATION, usage_page = GENERIC_DESKTOP, usage = SYSTEM_CONTROL) = {
+//!         usage_id=input;
+//!     }
+//! )]
+//! struct KeyboardAndSystemControl {
+//!     keycode: u8,
+//!     usage_id: u8,
+//! }
+//! ```
+//!
+//! Generated code never derives serde's `Deserialize`. Output reports are unpacked by a
+//! `TryFrom<&[u8]>` implementation, which is only generated when the descriptor has output
+//! fields, so input-only reports carry no unpacking code:
+//!
+//! ```compile_fail,E0277
+//! use usbd_hid::descriptor::MouseReport;
+//!
+//! let report = MouseReport::try_from(&[0u8; 5][..]);
+//! ```
@@ -0,0 +1,219 @@
+//! Renders a report descriptor in a human-readable form, for debugging.
+use core::fmt::Write;
+use std::string::String;
+
+use usbd_hid_descriptors::{GlobalItemKind, ItemType, LocalItemKind, MainItemKind};
+
+use super::parser::{items, DescriptorItem};
+
+/// Renders the items of the given report descriptor one per line, in the style
+/// of `lsusb -v`, ie: `Usage Page (Generic Desktop)`. Items within a collection
+/// are indented.
+///
+/// Rendering stops at the first malformed item, which is reported on the last line.
+pub fn describe(desc: &[u8]) -> String {
+    let mut out = String::new();
+    let mut depth = 0usize;
+    for item in items(desc) {
+        let item = match item {
+            Ok(item) => item,
+            Err(_) => {
+                out.push_str("<malformed item>\n");
+                break;
+            }
+        };
+        if item.typ == ItemType::Main.into() && item.tag == MainItemKind::EndCollection.into() {
+            depth = depth.saturating_sub(1);
+        }
+        for _ in 0..depth {
+            out.push_str("  ");
+        }
+        write_item(&mut out, &item);
+        out.push('\n');
+        if item.typ == ItemType::Main.into() && item.tag == MainItemKind::Collection.into() {
+            depth += 1;
+        }
+    }
+    out
+}
+
+fn write_item(out: &mut String, item: &DescriptorItem) {
+    let (name, value) = match item.typ {
+        t if t == ItemType::Main.into() => main_item(item),
+        t if t == ItemType::Global.into() => global_item(item),
+        t if t == ItemType::Local.into() => local_item(item),
+        _ => (None, None),
+    };
+    match name {
+        Some(name) => out.push_str(name),
+        None => {
+            write!(out, "Item (type {}, tag 0x{:02X})", item.typ, item.tag).ok();
+        }
+    }
+    match value {
+        Some(value) => {
+            write!(out, " ({})", value).ok();
+        }
+        None if name.is_none() => {
+            write!(out, " (0x{:X})", item.value()).ok();
+        }
+        None => {}
+    }
+}
+
+fn main_item(item: &DescriptorItem) -> (Option<&'static str>, Option<String>) {
+    let tag = item.tag;
+    if tag == MainItemKind::Input.into()
+        || tag == MainItemKind::Output.into()
+        || tag == MainItemKind::Feature.into()
+    {
+        let name = if tag == MainItemKind::Input.into() {
+            "Input"
+        } else if tag == MainItemKind::Output.into() {
+            "Output"
+        } else {
+            "Feature"
+        };
+        (Some(name), Some(main_item_flags(item.value())))
+    } else if tag == MainItemKind::Collection.into() {
+        let kind = match item.value() {
+            0x00 => String::from("Physical"),
+            0x01 => String::from("Application"),
+            0x02 => String::from("Logical"),
+            0x03 => String::from("Report"),
+            0x04 => String::from("Named Array"),
+            0x05 => String::from("Usage Switch"),
+            0x06 => String::from("Usage Modifier"),
+            v => std::format!("0x{:02X}", v),
+        };
+        (Some("Collection"), Some(kind))
+    } else if tag == MainItemKind::EndCollection.into() {
+        (Some("End Collection"), None)
+    } else {
+        (None, None)
+    }
+}
+
+fn main_item_flags(value: u32) -> String {
+    let flag = |bit: u32, set: &'static str, clear: &'static str| {
+        if value & (1 << bit) != 0 {
+            set
+        } else {
+            clear
+        }
+    };
+    let mut out = String::new();
+    out.push_str(flag(0, "Const", "Data"));
+    out.push(',');
+    out.push_str(flag(1, "Var", "Array"));
+    out.push(',');
+    out.push_str(flag(2, "Rel", "Abs"));
+    // Only report the less common settings when they are set.
+    for (bit, name) in [
+        (3, "Wrap"),
+        (4, "Non Linear"),
+        (5, "No Preferred State"),
+        (6, "Null State"),
+        (7, "Volatile"),
+        (8, "Buffered Bytes"),
+    ] {
+        if value & (1 << bit) != 0 {
+            out.push(',');
+            out.push_str(name);
+        }
+    }
+    out
+}
+
+fn global_item(item: &DescriptorItem) -> (Option<&'static str>, Option<String>) {
+    let signed = std::format!("{}", item.signed_value());
+    let unsigned = std::format!("{}", item.value());
+    match item.tag {
+        t if t == GlobalItemKind::UsagePage.into() => {
+            (Some("Usage Page"), Some(usage_page_name(item.value())))
+        }
+        t if t == GlobalItemKind::LogicalMin.into() => (Some("Logical Minimum"), Some(signed)),
+        t if t == GlobalItemKind::LogicalMax.into() => (Some("Logical Maximum"), Some(signed)),
+        t if t == GlobalItemKind::PhysicalMin.into() => (Some("Physical Minimum"), Some(signed)),
+        t if t == GlobalItemKind::PhysicalMax.into() => (Some("Physical Maximum"), Some(signed)),
+        t if t == GlobalItemKind::UnitExponent.into() => (Some("Unit Exponent"), Some(signed)),
+        t if t == GlobalItemKind::Unit.into() => {
+            (Some("Unit"), Some(std::format!("0x{:X}", item.value())))
+        }
+        t if t == GlobalItemKind::ReportSize.into() => (Some("Report Size"), Some(unsigned)),
+        t if t == GlobalItemKind::ReportID.into() => (Some("Report ID"), Some(unsigned)),
+        t if t == GlobalItemKind::ReportCount.into() => (Some("Report Count"), Some(unsigned)),
+        10 => (Some("Push"), None),
+        11 => (Some("Pop"), None),
+        _ => (None, None),
+    }
+}
+
+fn local_item(item: &DescriptorItem) -> (Option<&'static str>, Option<String>) {
+    let name = match item.tag {
+        t if t == LocalItemKind::Usage.into() => "Usage",
+        t if t == LocalItemKind::UsageMin.into() => "Usage Minimum",
+        t if t == LocalItemKind::UsageMax.into() => "Usage Maximum",
+        t if t == LocalItemKind::DesignatorIdx.into() => "Designator Index",
+        t if t == LocalItemKind::DesignatorMin.into() => "Designator Minimum",
+        t if t == LocalItemKind::DesignatorMax.into() => "Designator Maximum",
+        t if t == LocalItemKind::StringIdx.into() => "String Index",
+        t if t == LocalItemKind::StringMin.into() => "String Minimum",
+        t if t == LocalItemKind::StringMax.into() => "String Maximum",
+        t if t == LocalItemKind::Delimiter.into() => "Delimiter",
+        _ => return (None, None),
+    };
+    (Some(name), Some(std::format!("0x{:02X}", item.value())))
+}
+
+fn usage_page_name(page: u32) -> String {
+    let name = match page {
+        0x01 => "Generic Desktop",
+        0x02 => "Simulation Controls",
+        0x03 => "VR Controls",
+        0x04 => "Sport Controls",
+        0x05 => "Game Controls",
+        0x06 => "Generic Device Controls",
+        0x07 => "Keyboard/Keypad",
+        0x08 => "LEDs",
+        0x09 => "Button",
+        0x0A => "Ordinal",
+        0x0B => "Telephony",
+        0x0C => "Consumer",
+        0x0D => "Digitizer",
+        0x12 => "Eye and Head Trackers",
+        0x14 => "Alphanumeric Display",
+        0x20 => "Sensor",
+        0x59 => "Lighting And Illumination",
+        0x84 => "Power Device",
+        0x85 => "Battery System",
+        0x8C => "Barcode Scanner",
+        0xF1D0 => "FIDO Alliance",
+        0xFF00..=0xFFFF => return std::format!("Vendor Defined 0x{:04X}", page),
+        _ => return std::format!("0x{:02X}", page),
+    };
+    String::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::describe;
+    use crate::descriptor::{MouseReport, SerializedDescriptor};
+    use std::vec::Vec;
+
+    #[test]
+    fn test_describe() {
+        let text = describe(MouseReport::desc());
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "Usage Page (Generic Desktop)");
+        assert_eq!(lines[1], "Usage (0x02)");
+        assert_eq!(lines[2], "Collection (Application)");
+        assert_eq!(lines[4], "  Collection (Physical)");
+        assert!(lines.contains(&"    Usage Page (Button)"));
+        assert!(lines.contains(&"    Input (Data,Var,Rel)"));
+        assert!(lines.contains(&"    Logical Minimum (-127)"));
+        assert_eq!(lines.last(), Some(&"End Collection"));
+
+        assert!(describe(&[0x05, 0x01, 0x26, 0xFF]).ends_with("<malformed item>\n"));
+    }
+}
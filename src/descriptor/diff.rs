@@ -0,0 +1,133 @@
+//! Compares two report descriptors item-by-item.
+use std::{vec, vec::Vec};
+
+use super::parser::{items, DescriptorItem};
+
+/// DiffEntry describes a single difference between two report descriptors.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum DiffEntry<'a> {
+    /// The item is only present in the second descriptor.
+    Added(DescriptorItem<'a>),
+    /// The item is only present in the first descriptor.
+    Removed(DescriptorItem<'a>),
+    /// The item is present in both descriptors, but with different data.
+    Changed {
+        old: DescriptorItem<'a>,
+        new: DescriptorItem<'a>,
+    },
+}
+
+/// Parses both descriptors and reports which items were added, removed or
+/// changed going from `a` to `b`. Each entry carries the offset of the item
+/// within its descriptor.
+///
+/// Parsing of a descriptor stops at the first malformed item, so only the
+/// well-formed prefix of each descriptor is compared.
+pub fn diff<'a>(a: &'a [u8], b: &'a [u8]) -> Vec<DiffEntry<'a>> {
+    let a: Vec<DescriptorItem> = items(a).map_while(|i| i.ok()).collect();
+    let b: Vec<DescriptorItem> = items(b).map_while(|i| i.ok()).collect();
+
+    // Longest common subsequence of identical items.
+    let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i].same_item(&b[j]) {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut out = Vec::new();
+    let mut removed = Vec::new();
+    let mut added = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i].same_item(&b[j]) {
+            flush_run(&mut out, &mut removed, &mut added);
+            i += 1;
+            j += 1;
+        } else if j < b.len() && (i == a.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            added.push(b[j]);
+            j += 1;
+        } else {
+            removed.push(a[i]);
+            i += 1;
+        }
+    }
+    flush_run(&mut out, &mut removed, &mut added);
+    out
+}
+
+/// Emits a run of removed & added items which sit between two common items,
+/// pairing up items of the same kind as changes.
+fn flush_run<'a>(
+    out: &mut Vec<DiffEntry<'a>>,
+    removed: &mut Vec<DescriptorItem<'a>>,
+    added: &mut Vec<DescriptorItem<'a>>,
+) {
+    let mut added = added.drain(..).peekable();
+    for old in removed.drain(..) {
+        match added.peek() {
+            Some(new) if new.typ == old.typ && new.tag == old.tag => {
+                out.push(DiffEntry::Changed { old, new: *new });
+                added.next();
+            }
+            _ => out.push(DiffEntry::Removed(old)),
+        }
+    }
+    out.extend(added.map(DiffEntry::Added));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{diff, DiffEntry};
+    use crate::descriptor::{MouseReport, SerializedDescriptor};
+    use std::vec::Vec;
+
+    #[test]
+    fn test_descriptor_diff() {
+        let a = MouseReport::desc();
+        let mut b = Vec::from(a);
+        // Logical Maximum (127) -> (63), and drop the trailing End Collection.
+        assert_eq!(b[32..34], [0x25, 0x7f]);
+        b[33] = 0x3f;
+        b.pop();
+
+        let d = diff(a, &b);
+        assert_eq!(d.len(), 2);
+        match d[0] {
+            DiffEntry::Changed { old, new } => {
+                assert_eq!(old.offset, 32);
+                assert_eq!(new.offset, 32);
+                assert_eq!(old.signed_value(), 127);
+                assert_eq!(new.signed_value(), 63);
+            }
+            e => panic!("unexpected diff entry: {:?}", e),
+        }
+        match d[1] {
+            DiffEntry::Removed(item) => {
+                assert_eq!(item.offset, a.len() - 1);
+                assert_eq!(item.tag, 0xc);
+            }
+            e => panic!("unexpected diff entry: {:?}", e),
+        }
+
+        assert!(diff(a, a).is_empty());
+    }
+
+    #[test]
+    fn test_descriptor_diff_added() {
+        let a = [0x05, 0x01, 0xc0];
+        let b = [0x05, 0x01, 0x09, 0x02, 0xc0];
+        match diff(&a, &b)[..] {
+            [DiffEntry::Added(item)] => {
+                assert_eq!(item.offset, 2);
+                assert_eq!(item.value(), 0x02);
+            }
+            ref d => panic!("unexpected diff: {:?}", d),
+        }
+    }
+}
@@ -0,0 +1,209 @@
+//! Decodes the items of a serialized HID report descriptor.
+use heapless::Vec;
+use usb_device::{Result, UsbError};
+use usbd_hid_descriptors::{GlobalItemKind, ItemPrefix, ItemType};
+
+/// Prefix byte which introduces a long item.
+/// See (6.2.2.3): <https://www.usb.org/sites/default/files/hid1_11.pdf>
+const LONG_ITEM_PREFIX: u8 = 0xfe;
+
+/// DescriptorItem describes a single item decoded from a report descriptor.
+///
+/// For long items, `tag` is the bLongItemTag and `typ` is 3 (reserved).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DescriptorItem<'a> {
+    /// Byte offset of the item prefix within the descriptor.
+    pub offset: usize,
+    pub typ: u8,
+    pub tag: u8,
+    pub data: &'a [u8],
+}
+
+impl DescriptorItem<'_> {
+    /// Returns the item data as an unsigned little-endian value.
+    pub fn value(&self) -> u32 {
+        self.data
+            .iter()
+            .take(4)
+            .rev()
+            .fold(0, |acc, b| (acc << 8) | *b as u32)
+    }
+
+    /// Returns the item data as a sign-extended little-endian value.
+    pub fn signed_value(&self) -> i32 {
+        match self.data.len() {
+            0 => 0,
+            1 => self.data[0] as i8 as i32,
+            2 => self.value() as u16 as i16 as i32,
+            _ => self.value() as i32,
+        }
+    }
+
+    /// Returns true if both items encode the same item with the same data,
+    /// regardless of where they are located in their descriptors.
+    pub fn same_item(&self, other: &DescriptorItem) -> bool {
+        self.typ == other.typ && self.tag == other.tag && self.data == other.data
+    }
+}
+
+/// Iterator over the items of a report descriptor.
+///
+/// A malformed (truncated) item yields a ParseError, after which iteration stops.
+pub struct DescriptorItems<'a> {
+    desc: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> DescriptorItems<'a> {
+    pub fn new(desc: &'a [u8]) -> Self {
+        Self { desc, offset: 0 }
+    }
+}
+
+impl<'a> Iterator for DescriptorItems<'a> {
+    type Item = Result<DescriptorItem<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let offset = self.offset;
+        let prefix = *self.desc.get(offset)?;
+
+        let (typ, tag, data_start, data_len) = if prefix == LONG_ITEM_PREFIX {
+            match (self.desc.get(offset + 1), self.desc.get(offset + 2)) {
+                (Some(len), Some(tag)) => (3, *tag, offset + 3, *len as usize),
+                _ => {
+                    self.offset = self.desc.len();
+                    return Some(Err(UsbError::ParseError));
+                }
+            }
+        } else {
+            let prefix = ItemPrefix(prefix);
+            let len = match prefix.byte_count() {
+                3 => 4,
+                n => n as usize,
+            };
+            (prefix.typ(), prefix.tag(), offset + 1, len)
+        };
+
+        let data_end = data_start + data_len;
+        if data_end > self.desc.len() {
+            self.offset = self.desc.len();
+            return Some(Err(UsbError::ParseError));
+        }
+        self.offset = data_end;
+
+        Some(Ok(DescriptorItem {
+            offset,
+            typ,
+            tag,
+            data: &self.desc[data_start..data_end],
+        }))
+    }
+}
+
+/// Returns an iterator over the items of the given report descriptor.
+pub fn items(desc: &[u8]) -> DescriptorItems<'_> {
+    DescriptorItems::new(desc)
+}
+
+/// Returns the distinct Report ID values declared in the given report descriptor,
+/// in the order they first appear.
+///
+/// A BufferOverflow error is returned if the descriptor declares more than N report IDs.
+pub fn report_ids<const N: usize>(desc: &[u8]) -> Result<Vec<u8, N>> {
+    let mut ids = Vec::new();
+    for item in items(desc) {
+        let item = item?;
+        if item.typ == ItemType::Global.into()
+            && item.tag == GlobalItemKind::ReportID.into()
+            && !ids.contains(&(item.value() as u8))
+        {
+            ids.push(item.value() as u8)
+                .map_err(|_| UsbError::BufferOverflow)?;
+        }
+    }
+    Ok(ids)
+}
+
+/// Returns true if reports with the given Report ID are described by the given report
+/// descriptor: any declared Report ID, or 0 if the descriptor doesn't use Report IDs.
+pub fn is_valid_report_id(desc: &[u8], report_id: u8) -> Result<bool> {
+    let mut uses_report_ids = false;
+    for item in items(desc) {
+        let item = item?;
+        if item.typ == ItemType::Global.into() && item.tag == GlobalItemKind::ReportID.into() {
+            if item.value() == report_id as u32 {
+                return Ok(true);
+            }
+            uses_report_ids = true;
+        }
+    }
+    Ok(!uses_report_ids && report_id == 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::descriptor::{
+        KeyboardReport, KeyboardWithMediaReport, MouseReport, SerializedDescriptor,
+    };
+
+    #[test]
+    fn test_items() {
+        // Usage Page (Generic Desktop), Logical Minimum (-127), a long item with 2 bytes
+        // of data, and Logical Maximum (65535) in its 4-byte form.
+        let desc = [
+            0x05, 0x01, 0x15, 0x81, 0xfe, 0x02, 0x10, 0xaa, 0xbb, 0x27, 0xff, 0xff, 0x00, 0x00,
+        ];
+        let mut it = items(&desc);
+
+        let item = it.next().unwrap().unwrap();
+        assert_eq!((item.offset, item.typ, item.tag), (0, 1, 0));
+        assert_eq!(item.value(), 0x01);
+
+        let item = it.next().unwrap().unwrap();
+        assert_eq!(item.offset, 2);
+        assert_eq!(item.value(), 0x81);
+        assert_eq!(item.signed_value(), -127);
+
+        let item = it.next().unwrap().unwrap();
+        assert_eq!((item.offset, item.typ, item.tag), (4, 3, 0x10));
+        assert_eq!(item.data, [0xaa, 0xbb]);
+
+        let item = it.next().unwrap().unwrap();
+        assert_eq!(item.offset, 9);
+        assert_eq!(item.signed_value(), 65535);
+        assert!(it.next().is_none());
+
+        // A truncated item ends the iteration.
+        let mut it = items(&[0x05, 0x01, 0x26, 0xff]);
+        assert!(it.next().unwrap().is_ok());
+        assert_eq!(it.next(), Some(Err(UsbError::ParseError)));
+        assert!(it.next().is_none());
+    }
+
+    #[test]
+    fn test_report_ids() {
+        let ids = report_ids::<4>(KeyboardWithMediaReport::desc()).unwrap();
+        assert_eq!(ids, [1, 2]);
+        assert!(report_ids::<4>(MouseReport::desc()).unwrap().is_empty());
+        assert_eq!(
+            report_ids::<1>(KeyboardWithMediaReport::desc()),
+            Err(UsbError::BufferOverflow)
+        );
+    }
+
+    #[test]
+    fn test_is_valid_report_id() {
+        assert_eq!(
+            is_valid_report_id(KeyboardWithMediaReport::desc(), 2),
+            Ok(true)
+        );
+        assert_eq!(
+            is_valid_report_id(KeyboardWithMediaReport::desc(), 0),
+            Ok(false)
+        );
+        assert_eq!(is_valid_report_id(KeyboardReport::desc(), 0), Ok(true));
+        assert_eq!(is_valid_report_id(KeyboardReport::desc(), 1), Ok(false));
+    }
+}
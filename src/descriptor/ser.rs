@@ -0,0 +1,263 @@
+//! A minimal serde serializer which writes reports into a byte buffer.
+//!
+//! Unlike ssmarshal, the serialized report may be larger than the serialized value
+//! itself, ie: for the report ID wrappers which hold a reference to the struct.
+use core::fmt;
+
+use serde::ser::{self, Impossible, Serialize};
+
+/// The report does not fit in the buffer, or contains a type reports don't use.
+#[derive(Debug)]
+pub(crate) struct Error;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("report does not fit in the buffer or has an unsupported field")
+    }
+}
+
+impl ser::StdError for Error {}
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(_msg: T) -> Self {
+        Error
+    }
+}
+
+/// Serializes the value into the buffer, returning the number of bytes written.
+/// Integers are written in little-endian order, tuples & arrays as their elements
+/// without any length prefix.
+pub(crate) fn serialize<T: Serialize>(buf: &mut [u8], val: &T) -> Result<usize, Error> {
+    let mut writer = Writer { buf, len: 0 };
+    val.serialize(&mut writer)?;
+    Ok(writer.len)
+}
+
+struct Writer<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl Writer<'_> {
+    fn write(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        let end = self.len + bytes.len();
+        self.buf
+            .get_mut(self.len..end)
+            .ok_or(Error)?
+            .copy_from_slice(bytes);
+        self.len = end;
+        Ok(())
+    }
+}
+
+impl<'a> ser::Serializer for &mut Writer<'a> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Impossible<(), Error>;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Impossible<(), Error>;
+    type SerializeMap = Impossible<(), Error>;
+    type SerializeStruct = Impossible<(), Error>;
+    type SerializeStructVariant = Impossible<(), Error>;
+
+    fn serialize_bool(self, v: bool) -> Result<(), Error> {
+        self.write(&[v as u8])
+    }
+    fn serialize_i8(self, v: i8) -> Result<(), Error> {
+        self.write(&v.to_le_bytes())
+    }
+    fn serialize_i16(self, v: i16) -> Result<(), Error> {
+        self.write(&v.to_le_bytes())
+    }
+    fn serialize_i32(self, v: i32) -> Result<(), Error> {
+        self.write(&v.to_le_bytes())
+    }
+    fn serialize_i64(self, v: i64) -> Result<(), Error> {
+        self.write(&v.to_le_bytes())
+    }
+    fn serialize_u8(self, v: u8) -> Result<(), Error> {
+        self.write(&[v])
+    }
+    fn serialize_u16(self, v: u16) -> Result<(), Error> {
+        self.write(&v.to_le_bytes())
+    }
+    fn serialize_u32(self, v: u32) -> Result<(), Error> {
+        self.write(&v.to_le_bytes())
+    }
+    fn serialize_u64(self, v: u64) -> Result<(), Error> {
+        self.write(&v.to_le_bytes())
+    }
+    fn serialize_f32(self, v: f32) -> Result<(), Error> {
+        self.write(&v.to_le_bytes())
+    }
+    fn serialize_f64(self, v: f64) -> Result<(), Error> {
+        self.write(&v.to_le_bytes())
+    }
+    fn serialize_char(self, _v: char) -> Result<(), Error> {
+        Err(Error)
+    }
+    fn serialize_str(self, _v: &str) -> Result<(), Error> {
+        Err(Error)
+    }
+    fn collect_str<T: ?Sized + fmt::Display>(self, _value: &T) -> Result<(), Error> {
+        Err(Error)
+    }
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), Error> {
+        self.write(v)
+    }
+    fn serialize_none(self) -> Result<(), Error> {
+        Err(Error)
+    }
+    fn serialize_some<T: ?Sized + Serialize>(self, _value: &T) -> Result<(), Error> {
+        Err(Error)
+    }
+    fn serialize_unit(self) -> Result<(), Error> {
+        Ok(())
+    }
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), Error> {
+        Ok(())
+    }
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<(), Error> {
+        Err(Error)
+    }
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        value.serialize(self)
+    }
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<(), Error> {
+        Err(Error)
+    }
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Error> {
+        Err(Error)
+    }
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Error> {
+        Ok(self)
+    }
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Error> {
+        Ok(self)
+    }
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Error> {
+        Err(Error)
+    }
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Error> {
+        Err(Error)
+    }
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, Error> {
+        Err(Error)
+    }
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Error> {
+        Err(Error)
+    }
+}
+
+impl<'a> ser::SerializeTuple for &mut Writer<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        value.serialize(&mut **self)
+    }
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<'a> ser::SerializeTupleStruct for &mut Writer<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        value.serialize(&mut **self)
+    }
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::serialize;
+
+    #[test]
+    fn test_integers_little_endian() {
+        let mut buf = [0u8; 16];
+        assert_eq!(serialize(&mut buf, &0x0102u16).unwrap(), 2);
+        assert_eq!(buf[..2], [0x02, 0x01]);
+        assert_eq!(serialize(&mut buf, &-2i32).unwrap(), 4);
+        assert_eq!(buf[..4], [0xFE, 0xFF, 0xFF, 0xFF]);
+        assert_eq!(serialize(&mut buf, &true).unwrap(), 1);
+        assert_eq!(buf[0], 0x01);
+    }
+
+    #[test]
+    fn test_tuples_and_arrays() {
+        let mut buf = [0u8; 16];
+        assert_eq!(serialize(&mut buf, &(0x01u8, 0x0203u16)).unwrap(), 3);
+        assert_eq!(buf[..3], [0x01, 0x03, 0x02]);
+
+        // Arrays are written without a length prefix.
+        assert_eq!(serialize(&mut buf, &[0x0102u16, 0x0304]).unwrap(), 4);
+        assert_eq!(buf[..4], [0x02, 0x01, 0x04, 0x03]);
+        assert_eq!(serialize(&mut buf, &[[1u8, 2], [3, 4]]).unwrap(), 4);
+        assert_eq!(buf[..4], [1, 2, 3, 4]);
+
+        assert_eq!(serialize(&mut buf, &()).unwrap(), 0);
+        assert_eq!(serialize(&mut buf, &[0u8; 0]).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_buffer_overflow() {
+        let mut buf = [0u8; 3];
+        assert!(serialize(&mut buf, &0u32).is_err());
+        assert!(serialize(&mut buf, &[0u8; 4]).is_err());
+        assert!(serialize(&mut [], &0u8).is_err());
+        // Exactly fitting values are fine.
+        assert_eq!(serialize(&mut buf, &[7u8; 3]).unwrap(), 3);
+    }
+
+    #[test]
+    fn test_unsupported_types() {
+        let mut buf = [0u8; 16];
+        assert!(serialize(&mut buf, &'a').is_err());
+        assert!(serialize(&mut buf, &"a").is_err());
+        assert!(serialize(&mut buf, &Some(1u8)).is_err());
+        assert!(serialize(&mut buf, &None::<u8>).is_err());
+        // Slices have a runtime length, so serialize as sequences.
+        assert!(serialize(&mut buf, &&[1u8, 2][..]).is_err());
+    }
+}
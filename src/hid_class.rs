@@ -1,10 +1,12 @@
 //! Implements HID functionality for a usb-device device.
+use core::cell::Cell;
+use heapless::Vec;
 use usb_device::class_prelude::*;
 use usb_device::Result;
 
-use crate::descriptor::AsInputReport;
-extern crate ssmarshal;
-use ssmarshal::serialize;
+use crate::descriptor::{
+    is_valid_report_id, AsFeatureReport, AsInputReport, AsOutputReport, KeyboardLedsReport,
+};
 
 const USB_CLASS_HID: u8 = 0x03;
 
@@ -43,7 +45,14 @@ const HID_REQ_SET_REPORT: u8 = 0x09;
 
 /// See CONTROL_BUF_LEN from usb-device.git src/control_pipe.rs
 /// Will need to revisit how this is set once usb-device has true HiSpeed USB support.
+#[cfg(not(feature = "control-buffer-256"))]
 const CONTROL_BUF_LEN: usize = 128;
+#[cfg(feature = "control-buffer-256")]
+const CONTROL_BUF_LEN: usize = 256;
+
+/// Size of the buffer input reports are serialized into before transmission.
+/// This is also the default wMaxPacketSize of the interrupt endpoints.
+const REPORT_BUF_LEN: usize = 64;
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -182,16 +191,66 @@ pub enum ProtocolModeConfig {
     ForceReport,
 }
 
+/// Interval at which the host polls the interrupt endpoints.
+///
+/// Full-speed devices express the interval in 1ms frames. High-speed devices
+/// express it in 125µs microframes, encoded in bInterval as a power of two.
+/// See (9.6.6): USB 2.0 specification
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PollInterval {
+    /// Poll every given number of milliseconds (full-speed and low-speed).
+    FullSpeedMs(u8),
+    /// Poll every given number of 125µs microframes (high-speed). Intervals which
+    /// are not a power of two are rounded down to the nearest power of two.
+    HighSpeedMicroframes(u8),
+}
+
+impl PollInterval {
+    /// Returns the value of the bInterval field of the endpoint descriptor.
+    pub fn b_interval(self) -> u8 {
+        match self {
+            PollInterval::FullSpeedMs(ms) => ms,
+            // The period is 2^(bInterval-1) microframes.
+            PollInterval::HighSpeedMicroframes(uframes) => {
+                (u8::BITS - uframes.max(1).leading_zeros()) as u8
+            }
+        }
+    }
+}
+
 /// Used to define specialized HID device settings
 /// Most commonly used to setup Boot Mode (6KRO) or Report Mode (NKRO) keyboards.
 /// Some OSs will also respect the HID locale setting of the keyboard to help choose the OS
 /// keyboard layout.
+///
+/// Settings can be built from the defaults with the `with_*` methods, ie:
+/// `HidClassSettings::default().with_protocol(HidProtocol::Keyboard)`.
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct HidClassSettings {
     pub subclass: HidSubClass,
     pub protocol: HidProtocol,
     pub config: ProtocolModeConfig,
     pub locale: HidCountryCode,
+    /// Overrides the poll_ms constructor argument when set.
+    pub poll_interval: Option<PollInterval>,
+    /// wMaxPacketSize advertised for the interrupt endpoints, 64 bytes by default.
+    /// Must not be greater than 64 bytes, the size of the internal report buffer.
+    /// Reports are sent and received in a single packet, so it must be at least the length
+    /// of the largest report (including its report ID) exchanged over the endpoints: longer
+    /// input reports fail to send with BufferOverflow. This is not checked against the
+    /// descriptor.
+    pub max_packet_size: u16,
+    /// Overrides the bInterfaceProtocol derived from protocol when set, ie: for a vendor
+    /// driver to match on. Boot protocol behavior is still determined by protocol.
+    pub interface_protocol: Option<u8>,
+    /// Transfer type of the IN endpoint, interrupt by default as required by the HID
+    /// specification. Other types are only understood by custom host drivers.
+    pub in_endpoint_type: EndpointType,
+    /// Omits the OUT endpoint from the configuration descriptor while keeping it allocated,
+    /// as a workaround for hosts which mishandle it. The host then sends output reports
+    /// through SET_REPORT.
+    pub hide_out_endpoint: bool,
 }
 
 impl Default for HidClassSettings {
@@ -201,15 +260,95 @@ impl Default for HidClassSettings {
             protocol: HidProtocol::Generic,
             config: ProtocolModeConfig::DefaultBehavior,
             locale: HidCountryCode::NotSupported,
+            poll_interval: None,
+            max_packet_size: REPORT_BUF_LEN as u16,
+            interface_protocol: None,
+            in_endpoint_type: EndpointType::Interrupt,
+            hide_out_endpoint: false,
         }
     }
 }
 
+impl HidClassSettings {
+    /// Sets the subclass, ie: HidSubClass::Boot for a boot protocol keyboard or mouse.
+    pub const fn with_subclass(mut self, subclass: HidSubClass) -> Self {
+        self.subclass = subclass;
+        self
+    }
+
+    /// Sets the boot protocol of the interface.
+    pub const fn with_protocol(mut self, protocol: HidProtocol) -> Self {
+        self.protocol = protocol;
+        self
+    }
+
+    /// Sets how the protocol mode is chosen, see ProtocolModeConfig.
+    pub const fn with_config(mut self, config: ProtocolModeConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Sets the country code of the HID descriptor.
+    pub const fn with_locale(mut self, locale: HidCountryCode) -> Self {
+        self.locale = locale;
+        self
+    }
+
+    /// Sets the poll interval, overriding the poll_ms constructor argument.
+    pub const fn with_poll_interval(mut self, poll_interval: PollInterval) -> Self {
+        self.poll_interval = Some(poll_interval);
+        self
+    }
+
+    /// Sets the wMaxPacketSize of the interrupt endpoints, which must fit the largest report,
+    /// see max_packet_size.
+    pub const fn with_max_packet_size(mut self, max_packet_size: u16) -> Self {
+        self.max_packet_size = max_packet_size;
+        self
+    }
+
+    /// Sets the bInterfaceProtocol, overriding the one derived from the protocol.
+    pub const fn with_interface_protocol(mut self, interface_protocol: u8) -> Self {
+        self.interface_protocol = Some(interface_protocol);
+        self
+    }
+
+    /// Sets the transfer type of the IN endpoint, see in_endpoint_type.
+    pub const fn with_in_endpoint_type(mut self, in_endpoint_type: EndpointType) -> Self {
+        self.in_endpoint_type = in_endpoint_type;
+        self
+    }
+
+    /// Omits the OUT endpoint from the configuration descriptor, see hide_out_endpoint.
+    pub const fn with_hide_out_endpoint(mut self, hide_out_endpoint: bool) -> Self {
+        self.hide_out_endpoint = hide_out_endpoint;
+        self
+    }
+}
+
 /// HIDClass provides an interface to declare, read & write HID reports.
 ///
 /// Users are expected to provide the report descriptor, as well as pack
 /// and unpack reports which are read or staged for transmission.
-pub struct HIDClass<'a, B: UsbBus> {
+///
+/// The optional report stores are sized by the const parameters, and take no memory by
+/// default. See with_storage() to enable them:
+///   - `INPUT_QUEUE_LEN` is the number of input reports push_input_queued() can stage.
+///   - `GET_REPORT_RESPONSES` is the number of reports, of distinct types or IDs, held by
+///     set_get_report_response() to answer GET_REPORT requests.
+///   - `LAST_INPUT_LEN` is the length of the longest input report recorded for last_input()
+///     and push_input_if_changed().
+///
+/// The methods which rely on a store fail while it is disabled: push_input_if_changed(),
+/// push_feature() and set_get_report_response() return an Unsupported error, and
+/// last_input() fails to compile.
+pub struct HIDClass<
+    'a,
+    B: UsbBus,
+    const INPUT_QUEUE_LEN: usize = 0,
+    const GET_REPORT_RESPONSES: usize = 0,
+    const LAST_INPUT_LEN: usize = 0,
+> {
     if_num: InterfaceNumber,
     /// Low-latency OUT buffer
     out_ep: Option<EndpointOut<'a, B>>,
@@ -230,6 +369,22 @@ pub struct HIDClass<'a, B: UsbBus> {
     /// See <https://www.usb.org/sites/default/files/hid1_11.pdf> Section 7.2.6
     protocol: Option<HidProtocolMode>,
     settings: HidClassSettings,
+    /// Set by the application while the bus is suspended, see set_suspended.
+    suspended: bool,
+    /// Last input report written, see last_input. A Cell so the input report can be
+    /// recorded by push_input, which only borrows the class.
+    last_input: Cell<Option<Vec<u8, LAST_INPUT_LEN>>>,
+    /// Input reports staged by push_input_queued, oldest first, and their lengths.
+    input_queue: Vec<([u8; REPORT_BUF_LEN], usize), INPUT_QUEUE_LEN>,
+    /// Last GET_REPORT(Input) or GET_REPORT(Feature) request, see take_get_report_request.
+    get_report_request: Option<ReportInfo>,
+    /// Reports served to GET_REPORT requests for their type and ID, see
+    /// set_get_report_response.
+    get_report_responses: Vec<Report, GET_REPORT_RESPONSES>,
+    /// Last output report received, from either SET_REPORT or the OUT endpoint, served to
+    /// GET_REPORT(Output) requests. A Cell so it can be recorded by pull_raw_output, which
+    /// only borrows the class.
+    last_output: Cell<Option<Report>>,
 }
 
 fn determine_protocol_setting(settings: &HidClassSettings) -> Option<HidProtocolMode> {
@@ -245,13 +400,44 @@ fn determine_protocol_setting(settings: &HidClassSettings) -> Option<HidProtocol
     }
 }
 
-impl<B: UsbBus> HIDClass<'_, B> {
+fn determine_interval(settings: &HidClassSettings, poll_ms: u8) -> u8 {
+    let interval = settings
+        .poll_interval
+        .unwrap_or(PollInterval::FullSpeedMs(poll_ms))
+        .b_interval();
+    // An interval of 0 is invalid for interrupt endpoints, and rejected by some hosts.
+    assert!(interval >= 1, "poll_ms must be at least 1");
+    interval
+}
+
+fn determine_max_packet_size(settings: &HidClassSettings) -> u16 {
+    assert!(
+        settings.max_packet_size as usize <= REPORT_BUF_LEN,
+        "max_packet_size must not exceed the {REPORT_BUF_LEN} byte report buffer"
+    );
+    settings.max_packet_size
+}
+
+fn alloc_in_ep<'a, B: UsbBus>(
+    alloc: &'a UsbBusAllocator<B>,
+    settings: &HidClassSettings,
+    max_packet_size: u16,
+    interval: u8,
+) -> EndpointIn<'a, B> {
+    alloc
+        .alloc(None, settings.in_endpoint_type, max_packet_size, interval)
+        .expect("alloc_ep failed")
+}
+
+impl<'a, B: UsbBus> HIDClass<'a, B> {
     /// Creates a new HIDClass with the provided UsbBus & HID report descriptor.
     ///
     /// poll_ms configures how frequently the host should poll for reading/writing
     /// HID reports. A lower value means better throughput & latency, at the expense
     /// of CPU on the device & bandwidth on the bus. A value of 10 is reasonable for
     /// high performance uses, and a value of 255 is good for best-effort usecases.
+    /// Gaming devices typically use 1, for 1000Hz polling. A value of 0 is invalid
+    /// and panics.
     ///
     /// This allocates two endpoints (IN and OUT).
     /// See new_ep_in (IN endpoint only) and new_ep_out (OUT endpoint only) to only create a single
@@ -259,160 +445,401 @@ impl<B: UsbBus> HIDClass<'_, B> {
     ///
     /// See new_with_settings() if you need to define protocol or locale settings for a IN/OUT
     /// HID interface.
-    pub fn new<'a>(
+    pub fn new(
         alloc: &'a UsbBusAllocator<B>,
         report_descriptor: &'static [u8],
         poll_ms: u8,
     ) -> HIDClass<'a, B> {
         let settings = HidClassSettings::default();
+        let interval = determine_interval(&settings, poll_ms);
         HIDClass {
             if_num: alloc.interface(),
-            out_ep: Some(alloc.interrupt(64, poll_ms)),
-            in_ep: Some(alloc.interrupt(64, poll_ms)),
+            out_ep: Some(alloc.interrupt(REPORT_BUF_LEN as u16, interval)),
+            in_ep: Some(alloc.interrupt(REPORT_BUF_LEN as u16, interval)),
             report_descriptor,
             set_report_buf: None,
             protocol: determine_protocol_setting(&settings),
             settings,
+            suspended: false,
+            last_input: Cell::new(None),
+            input_queue: Vec::new(),
+            get_report_request: None,
+            get_report_responses: Vec::new(),
+            last_output: Cell::new(None),
         }
     }
 
     /// Same as new() but includes a settings field.
     /// The settings field is used to define both locale and protocol settings of the HID
-    /// device (needed for HID keyboard and Mice).
-    pub fn new_with_settings<'a>(
+    /// device (needed for HID keyboard and Mice). It can also override poll_ms with a
+    /// PollInterval, such as a high-speed interval in microframes, and set the advertised
+    /// wMaxPacketSize and the IN endpoint type of the endpoints.
+    pub fn new_with_settings(
         alloc: &'a UsbBusAllocator<B>,
         report_descriptor: &'static [u8],
         poll_ms: u8,
         settings: HidClassSettings,
     ) -> HIDClass<'a, B> {
+        let interval = determine_interval(&settings, poll_ms);
+        let max_packet_size = determine_max_packet_size(&settings);
         HIDClass {
             if_num: alloc.interface(),
-            out_ep: Some(alloc.interrupt(64, poll_ms)),
-            in_ep: Some(alloc.interrupt(64, poll_ms)),
+            out_ep: Some(alloc.interrupt(max_packet_size, interval)),
+            in_ep: Some(alloc_in_ep(alloc, &settings, max_packet_size, interval)),
             report_descriptor,
             set_report_buf: None,
             protocol: determine_protocol_setting(&settings),
             settings,
+            suspended: false,
+            last_input: Cell::new(None),
+            input_queue: Vec::new(),
+            get_report_request: None,
+            get_report_responses: Vec::new(),
+            last_output: Cell::new(None),
         }
     }
 
     /// Creates a new HIDClass with the provided UsbBus & HID report descriptor.
     /// See new() for more details.
     /// Please use new_ep_in_with_settings() if you are creating a keyboard or mouse.
-    pub fn new_ep_in<'a>(
+    pub fn new_ep_in(
         alloc: &'a UsbBusAllocator<B>,
         report_descriptor: &'static [u8],
         poll_ms: u8,
     ) -> HIDClass<'a, B> {
         let settings = HidClassSettings::default();
+        let interval = determine_interval(&settings, poll_ms);
         HIDClass {
             if_num: alloc.interface(),
             out_ep: None,
-            in_ep: Some(alloc.interrupt(64, poll_ms)),
+            in_ep: Some(alloc.interrupt(REPORT_BUF_LEN as u16, interval)),
             report_descriptor,
             set_report_buf: None,
             protocol: determine_protocol_setting(&settings),
             settings,
+            suspended: false,
+            last_input: Cell::new(None),
+            input_queue: Vec::new(),
+            get_report_request: None,
+            get_report_responses: Vec::new(),
+            last_output: Cell::new(None),
         }
     }
 
     /// Same as new_ep_in() but includes a settings field.
     /// The settings field is used to define both locale and protocol settings of the HID
     /// device (needed for HID keyboard and Mice).
-    pub fn new_ep_in_with_settings<'a>(
+    pub fn new_ep_in_with_settings(
         alloc: &'a UsbBusAllocator<B>,
         report_descriptor: &'static [u8],
         poll_ms: u8,
         settings: HidClassSettings,
     ) -> HIDClass<'a, B> {
+        let interval = determine_interval(&settings, poll_ms);
+        let max_packet_size = determine_max_packet_size(&settings);
         HIDClass {
             if_num: alloc.interface(),
             out_ep: None,
-            in_ep: Some(alloc.interrupt(64, poll_ms)),
+            in_ep: Some(alloc_in_ep(alloc, &settings, max_packet_size, interval)),
             report_descriptor,
             set_report_buf: None,
             protocol: determine_protocol_setting(&settings),
             settings,
+            suspended: false,
+            last_input: Cell::new(None),
+            input_queue: Vec::new(),
+            get_report_request: None,
+            get_report_responses: Vec::new(),
+            last_output: Cell::new(None),
         }
     }
 
     /// Creates a new HIDClass with the provided UsbBus & HID report descriptor.
     /// See new() for more details.
     /// Please use new_ep_out_with_settings if you need the settings field.
-    pub fn new_ep_out<'a>(
+    pub fn new_ep_out(
         alloc: &'a UsbBusAllocator<B>,
         report_descriptor: &'static [u8],
         poll_ms: u8,
     ) -> HIDClass<'a, B> {
         let settings = HidClassSettings::default();
+        let interval = determine_interval(&settings, poll_ms);
         HIDClass {
             if_num: alloc.interface(),
-            out_ep: Some(alloc.interrupt(64, poll_ms)),
+            out_ep: Some(alloc.interrupt(REPORT_BUF_LEN as u16, interval)),
             in_ep: None,
             report_descriptor,
             set_report_buf: None,
             protocol: determine_protocol_setting(&settings),
             settings,
+            suspended: false,
+            last_input: Cell::new(None),
+            input_queue: Vec::new(),
+            get_report_request: None,
+            get_report_responses: Vec::new(),
+            last_output: Cell::new(None),
         }
     }
 
     /// Same as new_ep_out() but includes a settings field.
     /// This should be uncommon (non-standard), but is included for completeness as there
     /// may be cases where setting the locale is useful.
-    pub fn new_ep_out_with_settings<'a>(
+    pub fn new_ep_out_with_settings(
         alloc: &'a UsbBusAllocator<B>,
         report_descriptor: &'static [u8],
         poll_ms: u8,
         settings: HidClassSettings,
     ) -> HIDClass<'a, B> {
+        let interval = determine_interval(&settings, poll_ms);
+        let max_packet_size = determine_max_packet_size(&settings);
         HIDClass {
             if_num: alloc.interface(),
-            out_ep: Some(alloc.interrupt(64, poll_ms)),
+            out_ep: Some(alloc.interrupt(max_packet_size, interval)),
             in_ep: None,
             report_descriptor,
             set_report_buf: None,
             protocol: determine_protocol_setting(&settings),
             settings,
+            suspended: false,
+            last_input: Cell::new(None),
+            input_queue: Vec::new(),
+            get_report_request: None,
+            get_report_responses: Vec::new(),
+            last_output: Cell::new(None),
         }
     }
 
+    /// Creates a new HIDClass with the provided UsbBus & HID report descriptor, which
+    /// allocates no interrupt endpoints. Reports can only be exchanged through the
+    /// control pipe, using SET_REPORT (see pull_raw_report) and GET_REPORT requests.
+    /// Please use new_control_only_with_settings if you need the settings field.
+    pub fn new_control_only(
+        alloc: &'a UsbBusAllocator<B>,
+        report_descriptor: &'static [u8],
+    ) -> HIDClass<'a, B> {
+        Self::new_control_only_with_settings(alloc, report_descriptor, HidClassSettings::default())
+    }
+
+    /// Same as new_control_only() but includes a settings field.
+    pub fn new_control_only_with_settings(
+        alloc: &'a UsbBusAllocator<B>,
+        report_descriptor: &'static [u8],
+        settings: HidClassSettings,
+    ) -> HIDClass<'a, B> {
+        HIDClass {
+            if_num: alloc.interface(),
+            out_ep: None,
+            in_ep: None,
+            report_descriptor,
+            set_report_buf: None,
+            protocol: determine_protocol_setting(&settings),
+            settings,
+            suspended: false,
+            last_input: Cell::new(None),
+            input_queue: Vec::new(),
+            get_report_request: None,
+            get_report_responses: Vec::new(),
+            last_output: Cell::new(None),
+        }
+    }
+
+    /// Enables the optional report stores, sized by the const parameters of the returned
+    /// class (see HIDClass), ie:
+    /// `let hid: HIDClass<_, 4, 2, 64> = HIDClass::new(&alloc, desc, 10).with_storage();`
+    /// to stage up to 4 input reports, answer GET_REPORT with 2 reports, and record input
+    /// reports of up to 64 bytes.
+    pub fn with_storage<
+        const INPUT_QUEUE_LEN: usize,
+        const GET_REPORT_RESPONSES: usize,
+        const LAST_INPUT_LEN: usize,
+    >(
+        self,
+    ) -> HIDClass<'a, B, INPUT_QUEUE_LEN, GET_REPORT_RESPONSES, LAST_INPUT_LEN> {
+        HIDClass {
+            if_num: self.if_num,
+            out_ep: self.out_ep,
+            in_ep: self.in_ep,
+            report_descriptor: self.report_descriptor,
+            set_report_buf: self.set_report_buf,
+            protocol: self.protocol,
+            settings: self.settings,
+            suspended: self.suspended,
+            last_input: Cell::new(None),
+            input_queue: Vec::new(),
+            get_report_request: self.get_report_request,
+            get_report_responses: Vec::new(),
+            last_output: self.last_output,
+        }
+    }
+}
+
+impl<
+        B: UsbBus,
+        const INPUT_QUEUE_LEN: usize,
+        const GET_REPORT_RESPONSES: usize,
+        const LAST_INPUT_LEN: usize,
+    > HIDClass<'_, B, INPUT_QUEUE_LEN, GET_REPORT_RESPONSES, LAST_INPUT_LEN>
+{
+    /// Evaluated by last_input(), so that it fails to compile without a last input store.
+    const LAST_INPUT_ENABLED: () = assert!(
+        LAST_INPUT_LEN > 0,
+        "last_input() needs a LAST_INPUT_LEN store, see HIDClass::with_storage()"
+    );
+
+    /// Returns the report descriptor this class was created with.
+    pub fn report_descriptor(&self) -> &'static [u8] {
+        self.report_descriptor
+    }
+
+    /// Returns the length of the buffer used for control transfers, which bounds the
+    /// size of reports received through SET_REPORT. This is 128 bytes, or 256 with the
+    /// `control-buffer-256` feature. Reports are assembled from as many control endpoint
+    /// packets as they span. The report descriptor is served directly from static memory,
+    /// in packets of the control endpoint's max packet size.
+    pub fn control_buffer_len(&self) -> usize {
+        CONTROL_BUF_LEN
+    }
+
+    /// Marks the bus as suspended (or resumed), in which case input reports are
+    /// rejected with InvalidState rather than queued.
+    ///
+    /// Classes are not notified when the host suspends the bus, so this should follow
+    /// the device state after polling, ie:
+    /// `hid.set_suspended(usb_dev.state() == UsbDeviceState::Suspend)`.
+    /// A bus reset clears the suspended state.
+    pub fn set_suspended(&mut self, suspended: bool) {
+        self.suspended = suspended;
+    }
+
+    /// Returns true if the bus was marked as suspended with set_suspended.
+    pub fn is_suspended(&self) -> bool {
+        self.suspended
+    }
+
     /// Tries to write an input report by serializing the given report structure.
     /// A BufferOverflow error is returned if the serialized report is greater than
-    /// 64 bytes in size.
+    /// 64 bytes in size, or than the max_packet_size given in HidClassSettings. An
+    /// InvalidState error is returned while the bus is suspended.
     pub fn push_input<IR: AsInputReport>(&self, r: &IR) -> Result<usize> {
-        // Do not push data if protocol settings do not match (only for keyboard and mouse)
-        match self.settings.protocol {
-            HidProtocol::Keyboard | HidProtocol::Mouse => {
-                if let Some(protocol) = self.protocol {
-                    if (protocol == HidProtocolMode::Report
-                        && self.settings.subclass != HidSubClass::NoSubClass)
-                        || (protocol == HidProtocolMode::Boot
-                            && self.settings.subclass != HidSubClass::Boot)
-                    {
-                        return Err(UsbError::InvalidState);
-                    }
+        self.check_input_allowed()?;
+
+        let ep = self.in_ep.as_ref().ok_or(UsbError::InvalidEndpoint)?;
+        let mut buff = [0u8; REPORT_BUF_LEN];
+        let size = r.serialize_into(&mut buff)?;
+        let written = ep.write(&buff[0..size])?;
+        self.remember_input(&buff[..size]);
+        Ok(written)
+    }
+
+    /// Same as push_input(), but skips the write and returns Ok(0) if the serialized
+    /// report is identical to the last input report written, ie: to only send a report
+    /// when the state of the device changes.
+    ///
+    /// The last report is forgotten on bus reset, so the next report is always sent. Reports
+    /// are only compared if they fit in the LAST_INPUT_LEN store (see HIDClass), otherwise
+    /// they are always sent. An Unsupported error is returned if LAST_INPUT_LEN is 0, as it
+    /// is unless enabled with with_storage().
+    pub fn push_input_if_changed<IR: AsInputReport>(&mut self, r: &IR) -> Result<usize> {
+        if LAST_INPUT_LEN == 0 {
+            return Err(UsbError::Unsupported);
+        }
+        self.check_input_allowed()?;
+
+        let mut buff = [0u8; REPORT_BUF_LEN];
+        let size = r.serialize_into(&mut buff)?;
+        let last_input = self.last_input.take();
+        let unchanged = last_input.as_deref() == Some(&buff[..size]);
+        self.last_input.set(last_input);
+        if unchanged {
+            return Ok(0);
+        }
+        self.push_raw_input(&buff[..size])
+    }
+
+    /// Returns the bytes of the last input report written, including the report ID if any,
+    /// ie: to inspect what was last sent while debugging. Reports staged by
+    /// push_input_queued() are returned once written.
+    ///
+    /// Returns None if no report was written since the last bus reset, or if the last report
+    /// written was longer than LAST_INPUT_LEN bytes (see HIDClass).
+    ///
+    /// The report is returned as a copy rather than a `&[u8]`: push_input() only borrows the
+    /// class, so it can replace the recorded report while a returned slice is still held.
+    ///
+    /// LAST_INPUT_LEN must be enabled with with_storage(), ie:
+    /// `let hid: HIDClass<_, 0, 0, 64> = HIDClass::new(&alloc, desc, 10).with_storage();`
+    /// Calls to this method fail to compile while it is 0.
+    pub fn last_input(&self) -> Option<Vec<u8, LAST_INPUT_LEN>> {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::LAST_INPUT_ENABLED;
+        let last_input = self.last_input.take();
+        self.last_input.set(last_input.clone());
+        last_input
+    }
+
+    /// Same as push_input(), but if the IN endpoint is still busy with an earlier report,
+    /// the report is staged and written once the host has read the reports before it. This
+    /// suits bursts of reports, ie: replaying a key sequence, which would otherwise have to
+    /// be retried on WouldBlock.
+    ///
+    /// Up to INPUT_QUEUE_LEN reports can be staged (see HIDClass): a WouldBlock error is
+    /// returned when the queue is full. Staged reports are written one per poll of the device, in order, and are
+    /// discarded on bus reset. Returns the length of the report, whether it was written
+    /// immediately or staged.
+    pub fn push_input_queued<IR: AsInputReport>(&mut self, r: &IR) -> Result<usize> {
+        self.check_input_allowed()?;
+
+        let ep = self.in_ep.as_ref().ok_or(UsbError::InvalidEndpoint)?;
+        let mut buff = [0u8; REPORT_BUF_LEN];
+        let size = r.serialize_into(&mut buff)?;
+        if self.input_queue.is_empty() {
+            match ep.write(&buff[0..size]) {
+                Err(UsbError::WouldBlock) => {}
+                Ok(written) => {
+                    self.remember_input(&buff[..size]);
+                    return Ok(written);
                 }
+                result => return result,
             }
-            _ => {}
         }
+        self.input_queue
+            .push((buff, size))
+            .map_err(|_| UsbError::WouldBlock)?;
+        Ok(size)
+    }
 
-        if let Some(ep) = &self.in_ep {
-            let mut buff: [u8; 64] = [0; 64];
-            let size = match serialize(&mut buff, r) {
-                Ok(l) => l,
-                Err(_) => return Err(UsbError::BufferOverflow),
-            };
-            ep.write(&buff[0..size])
-        } else {
-            Err(UsbError::InvalidEndpoint)
+    /// Returns the number of input reports staged by push_input_queued which are yet
+    /// to be written.
+    pub fn queued_input_len(&self) -> usize {
+        self.input_queue.len()
+    }
+
+    /// Writes the oldest staged input report, if the IN endpoint is ready for it.
+    fn flush_input_queue(&mut self) {
+        if self.suspended {
+            return;
+        }
+        if let (Some(ep), Some(&(buff, size))) = (&self.in_ep, self.input_queue.first()) {
+            match ep.write(&buff[..size]) {
+                Err(UsbError::WouldBlock) => {}
+                result => {
+                    if result.is_ok() {
+                        self.remember_input(&buff[..size]);
+                    }
+                    // Reports which can't be written are dropped rather than blocking the queue.
+                    self.input_queue.remove(0);
+                }
+            }
         }
     }
 
-    /// Tries to write an input (device-to-host) report from the given raw bytes.
-    /// Data is expected to be a valid HID report for INPUT items. If report ID's
-    /// were used in the descriptor, the report ID corresponding to this report
-    /// must be be present before the contents of the report.
-    pub fn push_raw_input(&self, data: &[u8]) -> Result<usize> {
+    /// Returns an InvalidState error if input reports can't currently be sent.
+    fn check_input_allowed(&self) -> Result<()> {
+        // Do not push data while the host has suspended the bus
+        if self.suspended {
+            return Err(UsbError::InvalidState);
+        }
+
         // Do not push data if protocol settings do not match (only for keyboard and mouse)
         match self.settings.protocol {
             HidProtocol::Keyboard | HidProtocol::Mouse => {
@@ -428,22 +855,94 @@ impl<B: UsbBus> HIDClass<'_, B> {
             }
             _ => {}
         }
+        Ok(())
+    }
 
-        if let Some(ep) = &self.in_ep {
-            ep.write(data)
-        } else {
-            Err(UsbError::InvalidEndpoint)
-        }
+    /// Tries to write an input (device-to-host) report from the given raw bytes.
+    /// Data is expected to be a valid HID report for INPUT items. If report ID's
+    /// were used in the descriptor, the report ID corresponding to this report
+    /// must be be present before the contents of the report. A BufferOverflow error is
+    /// returned if the report is longer than the endpoint's max_packet_size.
+    pub fn push_raw_input(&self, data: &[u8]) -> Result<usize> {
+        self.check_input_allowed()?;
+
+        let ep = self.in_ep.as_ref().ok_or(UsbError::InvalidEndpoint)?;
+        let written = ep.write(data)?;
+        self.remember_input(data);
+        Ok(written)
+    }
+
+    /// Stores the report returned by last_input. Reports which don't fit clear it, so
+    /// push_input_if_changed never compares against an older report.
+    fn remember_input(&self, data: &[u8]) {
+        self.last_input.set(Vec::from_slice(data).ok());
     }
 
     /// Tries to read an output (host-to-device) report as raw bytes. Data
     /// is expected to be sized appropriately to contain any valid HID report
     /// for OUTPUT items, including the report ID prefix if report IDs are used.
+    ///
+    /// Returns the number of bytes received, which may be less than the length of data:
+    /// only that many bytes of data are valid. A BufferOverflow error is returned if the
+    /// received packet doesn't fit in data.
+    ///
+    /// The report is kept to answer GET_REPORT(Output) requests, as are output reports
+    /// received through SET_REPORT.
     pub fn pull_raw_output(&self, data: &mut [u8]) -> Result<usize> {
-        if let Some(ep) = &self.out_ep {
-            ep.read(data)
-        } else {
-            Err(UsbError::InvalidEndpoint)
+        let ep = self.out_ep.as_ref().ok_or(UsbError::InvalidEndpoint)?;
+        let len = ep.read(data)?;
+
+        // Descriptors which can't be parsed are treated as not using report IDs.
+        let uses_report_ids = !is_valid_report_id(self.report_descriptor, 0).unwrap_or(true);
+        let report_id = match data[..len].first() {
+            Some(&report_id) if uses_report_ids => report_id,
+            _ => 0,
+        };
+        self.remember_output(report_id, &data[..len]);
+        Ok(len)
+    }
+
+    /// Stores the report served to GET_REPORT(Output) requests. Reports which don't fit in
+    /// the control buffer clear it, so an older report is never served in their place.
+    fn remember_output(&self, report_id: u8, data: &[u8]) {
+        let report = (data.len() <= CONTROL_BUF_LEN).then(|| {
+            let mut buf = [0; CONTROL_BUF_LEN];
+            buf[..data.len()].copy_from_slice(data);
+            Report {
+                info: ReportInfo {
+                    report_type: ReportType::Output,
+                    report_id,
+                    len: data.len(),
+                },
+                buf,
+            }
+        });
+        self.last_output.set(report);
+    }
+
+    /// Tries to read an output (host-to-device) report from the OUT endpoint, and calls
+    /// handler with its report ID and contents, returning the handler's result.
+    ///
+    /// If report IDs are used in the descriptor, the report ID prefix is removed from the
+    /// contents, and a ParseError is returned for a report ID the descriptor doesn't declare.
+    /// Otherwise the report ID is 0 and the contents are the whole packet.
+    pub fn read_output_dispatch<R>(&self, handler: impl FnOnce(u8, &[u8]) -> R) -> Result<R> {
+        let mut buf = [0u8; REPORT_BUF_LEN];
+        let len = self.pull_raw_output(&mut buf)?;
+
+        // Descriptors which can't be parsed are treated as not using report IDs.
+        let uses_report_ids = !is_valid_report_id(self.report_descriptor, 0).unwrap_or(true);
+        if !uses_report_ids {
+            return Ok(handler(0, &buf[..len]));
+        }
+
+        match buf[..len].split_first() {
+            Some((&report_id, data))
+                if is_valid_report_id(self.report_descriptor, report_id).unwrap_or(false) =>
+            {
+                Ok(handler(report_id, data))
+            }
+            _ => Err(UsbError::ParseError),
         }
     }
 
@@ -454,6 +953,9 @@ impl<B: UsbBus> HIDClass<'_, B> {
     /// The most common usage of pull_raw_report is for keyboard lock LED status if an OUT endpoint
     /// is not defined. It is not necessary to call this function if you're not going to be using
     /// SET_REPORT functionality.
+    ///
+    /// SET_REPORT requests for a report ID which isn't declared in the report descriptor (or
+    /// a non-zero report ID, if the descriptor doesn't use report IDs) are rejected.
     pub fn pull_raw_report(&mut self, data: &mut [u8]) -> Result<ReportInfo> {
         let info = match &self.set_report_buf {
             Some(set_report_buf) => {
@@ -478,6 +980,180 @@ impl<B: UsbBus> HIDClass<'_, B> {
         Ok(info)
     }
 
+    /// Tries to read the LED output report of a KeyboardReport device, from either
+    /// the OUT endpoint or an incoming SET_REPORT(Output) request.
+    ///
+    /// If report IDs are used in the descriptor, ie: for KeyboardWithMediaReport, the LED
+    /// byte follows the report ID prefix, and a ParseError is returned for a report ID the
+    /// descriptor doesn't declare.
+    ///
+    /// WouldBlock is returned if neither has a pending report. Other pending
+    /// SET_REPORT reports are left for pull_raw_report.
+    pub fn pull_keyboard_leds(&mut self) -> Result<KeyboardLedsReport> {
+        let mut buf = [0u8; REPORT_BUF_LEN];
+        if self.out_ep.is_some() {
+            match self.pull_raw_output(&mut buf) {
+                Ok(0) => {}
+                Ok(len) => return self.keyboard_leds(&buf[..len]),
+                Err(UsbError::WouldBlock) => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        match &self.set_report_buf {
+            Some(report) if report.info.report_type == ReportType::Output => {
+                let info = self.pull_raw_report(&mut buf)?;
+                self.keyboard_leds(&buf[..info.len])
+            }
+            _ => Err(UsbError::WouldBlock),
+        }
+    }
+
+    /// Unpacks the LED byte of an output report, which follows the report ID prefix if
+    /// report IDs are used in the descriptor.
+    fn keyboard_leds(&self, report: &[u8]) -> Result<KeyboardLedsReport> {
+        // Descriptors which can't be parsed are treated as not using report IDs.
+        let uses_report_ids = !is_valid_report_id(self.report_descriptor, 0).unwrap_or(true);
+        let leds = if uses_report_ids {
+            match report {
+                [report_id, leds, ..]
+                    if is_valid_report_id(self.report_descriptor, *report_id).unwrap_or(false) =>
+                {
+                    *leds
+                }
+                _ => return Err(UsbError::ParseError),
+            }
+        } else {
+            *report.first().ok_or(UsbError::ParseError)?
+        };
+        Ok(leds.into())
+    }
+
+    /// Tries to read and unpack an output report, from either the OUT endpoint or an
+    /// incoming SET_REPORT(Output) request. The report is for a descriptor without
+    /// report IDs.
+    ///
+    /// WouldBlock is returned if neither has a pending report. Other pending
+    /// SET_REPORT reports are left for pull_raw_report.
+    pub fn pull_output<OR: AsOutputReport>(&mut self) -> Result<OR> {
+        let mut buf = [0u8; CONTROL_BUF_LEN];
+        if self.out_ep.is_some() {
+            match self.pull_raw_output(&mut buf) {
+                Ok(len) => return OR::from_output_report(&buf[..len]),
+                Err(UsbError::WouldBlock) => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        match &self.set_report_buf {
+            Some(report) if report.info.report_type == ReportType::Output => {
+                let info = self.pull_raw_report(&mut buf)?;
+                OR::from_output_report(&buf[..info.len])
+            }
+            _ => Err(UsbError::WouldBlock),
+        }
+    }
+
+    /// Serializes a feature report, and sets it as the response to GET_REPORT(Feature)
+    /// requests (see set_get_report_response), returning its length. The report is for a
+    /// descriptor without report IDs.
+    ///
+    /// An Unsupported error is returned if GET_REPORT_RESPONSES is 0, as it is unless enabled
+    /// with with_storage().
+    pub fn push_feature<FR: AsFeatureReport>(&mut self, r: &FR) -> Result<usize> {
+        if GET_REPORT_RESPONSES == 0 {
+            return Err(UsbError::Unsupported);
+        }
+        let mut buf = [0; CONTROL_BUF_LEN];
+        let len = r.serialize_feature_into(&mut buf)?;
+        self.set_get_report_response(ReportType::Feature, 0, &buf[..len])?;
+        Ok(len)
+    }
+
+    /// Tries to read the feature report of an incoming SET_REPORT(Feature) request.
+    ///
+    /// WouldBlock is returned if there is no pending feature report. Other pending
+    /// SET_REPORT reports are left for pull_raw_report.
+    pub fn pull_feature<FR: AsFeatureReport>(&mut self) -> Result<FR> {
+        match &self.set_report_buf {
+            Some(report) if report.info.report_type == ReportType::Feature => {
+                let mut buf = [0; CONTROL_BUF_LEN];
+                let info = self.pull_raw_report(&mut buf)?;
+                FR::try_from_feature(&buf[..info.len])
+            }
+            _ => Err(UsbError::WouldBlock),
+        }
+    }
+
+    /// Returns the last GET_REPORT(Input) or GET_REPORT(Feature) request received from the
+    /// host, if any, and clears it. The length is the number of bytes the host asked for.
+    ///
+    /// This does not defer the response: usb-device rejects any control request left
+    /// unanswered once the classes have been polled, so GET_REPORT is answered with the
+    /// report given to set_get_report_response() if it has the requested type and ID, and
+    /// STALLed otherwise. Hosts do not usually retry a STALLed GET_REPORT, so responses should
+    /// be set ahead of time; this only tells the device which reports the host asks for.
+    pub fn take_get_report_request(&mut self) -> Option<ReportInfo> {
+        self.get_report_request.take()
+    }
+
+    /// Sets the report served to GET_REPORT requests for the given report type and ID, until
+    /// it is replaced by another report of the same type and ID. If report IDs are used, the
+    /// report ID must be present before the contents of the report.
+    ///
+    /// Reports of up to GET_REPORT_RESPONSES distinct types or IDs are held at once (see
+    /// HIDClass). A BufferOverflow error is returned if the report is longer than the control
+    /// buffer (see control_buffer_len), or if reports of as many other types or IDs are
+    /// already held, see clear_get_report_responses. An Unsupported error is returned if
+    /// GET_REPORT_RESPONSES is 0, as it is unless enabled with with_storage().
+    pub fn set_get_report_response(
+        &mut self,
+        report_type: ReportType,
+        report_id: u8,
+        data: &[u8],
+    ) -> Result<()> {
+        if GET_REPORT_RESPONSES == 0 {
+            return Err(UsbError::Unsupported);
+        }
+        if data.len() > CONTROL_BUF_LEN {
+            return Err(UsbError::BufferOverflow);
+        }
+        let mut buf = [0; CONTROL_BUF_LEN];
+        buf[..data.len()].copy_from_slice(data);
+        let report = Report {
+            info: ReportInfo {
+                report_type,
+                report_id,
+                len: data.len(),
+            },
+            buf,
+        };
+        let held = self
+            .get_report_responses
+            .iter_mut()
+            .find(|r| r.info.report_type == report_type && r.info.report_id == report_id);
+        match held {
+            Some(held) => *held = report,
+            None => self
+                .get_report_responses
+                .push(report)
+                .map_err(|_| UsbError::BufferOverflow)?,
+        }
+        Ok(())
+    }
+
+    /// Discards the reports set with set_get_report_response, and the last output report
+    /// received, so GET_REPORT requests are rejected until new ones are set.
+    pub fn clear_get_report_responses(&mut self) {
+        self.get_report_responses.clear();
+        self.last_output.set(None);
+    }
+
+    /// Discards any pending SET_REPORT report without reading it.
+    pub fn clear_set_report(&mut self) {
+        self.set_report_buf = None;
+    }
+
     /// Retrieves the currently set device protocol
     /// This is equivalent to the USB HID GET_PROTOCOL request
     /// See (7.2.5): <https://www.usb.org/sites/default/files/hid1_11.pdf>
@@ -533,13 +1209,34 @@ impl<B: UsbBus> HIDClass<'_, B> {
     }
 }
 
-impl<B: UsbBus> UsbClass<B> for HIDClass<'_, B> {
+impl<
+        B: UsbBus,
+        const INPUT_QUEUE_LEN: usize,
+        const GET_REPORT_RESPONSES: usize,
+        const LAST_INPUT_LEN: usize,
+    > UsbClass<B> for HIDClass<'_, B, INPUT_QUEUE_LEN, GET_REPORT_RESPONSES, LAST_INPUT_LEN>
+{
+    fn reset(&mut self) {
+        self.suspended = false;
+        self.last_input.set(None);
+        self.input_queue.clear();
+        self.get_report_request = None;
+        // Devices return to report protocol after a reset, see (7.2.6) of the HID spec.
+        self.protocol = determine_protocol_setting(&self.settings);
+    }
+
+    fn poll(&mut self) {
+        self.flush_input_queue();
+    }
+
     fn get_configuration_descriptors(&self, writer: &mut DescriptorWriter) -> Result<()> {
         writer.interface(
             self.if_num,
             USB_CLASS_HID,
             self.settings.subclass as u8,
-            self.settings.protocol as u8,
+            self.settings
+                .interface_protocol
+                .unwrap_or(self.settings.protocol as u8),
         )?;
 
         // HID descriptor
@@ -561,7 +1258,9 @@ impl<B: UsbBus> UsbClass<B> for HIDClass<'_, B> {
         )?;
 
         if let Some(ep) = &self.out_ep {
-            writer.endpoint(ep)?;
+            if !self.settings.hide_out_endpoint {
+                writer.endpoint(ep)?;
+            }
         }
         if let Some(ep) = &self.in_ep {
             writer.endpoint(ep)?;
@@ -608,13 +1307,37 @@ impl<B: UsbBus> UsbClass<B> for HIDClass<'_, B> {
                 }
             }
             (control::RequestType::Class, HID_REQ_GET_REPORT) => {
-                // To support GET_REPORT correctly each request must be serviced immediately.
-                // This complicates the current API and may require a standing copy of each
-                // of the possible IN reports (as well as any FEATURE reports as well).
-                // For most projects, GET_REPORT won't be necessary so until a project comes along
-                // with a need for it, I think it's safe to leave unsupported.
+                let report_type = ((req.value >> 8) as u8).into();
+                let report_id = (req.value & 0xFF) as u8;
+
+                if matches!(report_type, ReportType::Input | ReportType::Feature)
+                    && is_valid_report_id(self.report_descriptor, report_id).unwrap_or(true)
+                {
+                    self.get_report_request = Some(ReportInfo {
+                        report_type,
+                        report_id,
+                        len: req.length as usize,
+                    });
+                }
+
+                // Each request must be serviced immediately, usb-device STALLs any control
+                // request we leave pending, so reports are answered from the standing copy set
+                // with set_get_report_response.
                 // See: https://www.usb.org/sites/default/files/documents/hid1_11.pdf 7.2.1
-                xfer.reject().ok(); // Not supported for now
+                // Output reports are answered with the last one the host sent us.
+                let report = self
+                    .get_report_responses
+                    .iter()
+                    .chain(self.last_output.get_mut().as_ref())
+                    .find(|r| r.info.report_type == report_type && r.info.report_id == report_id);
+                match report {
+                    Some(report) => {
+                        xfer.accept_with(&report.buf[..report.info.len]).ok();
+                    }
+                    _ => {
+                        xfer.reject().ok();
+                    }
+                }
             }
             (control::RequestType::Class, HID_REQ_GET_IDLE) => {
                 // XXX (HaaTa): As a note for future readers
@@ -678,23 +1401,30 @@ impl<B: UsbBus> UsbClass<B> for HIDClass<'_, B> {
                 let report_id = (req.value & 0xFF) as u8;
                 let len = req.length as usize;
 
-                // Validate that the incoming data isn't too large for the buffer
-                if len > CONTROL_BUF_LEN {
+                // Validate that the incoming data isn't too large for the buffer, and that
+                // the descriptor describes the report. Descriptors which can't be parsed
+                // don't restrict the report ID.
+                let known_id =
+                    is_valid_report_id(self.report_descriptor, report_id).unwrap_or(true);
+                if len > CONTROL_BUF_LEN || !known_id {
                     self.set_report_buf = None;
                     xfer.reject().ok();
                 } else {
                     let mut buf: [u8; CONTROL_BUF_LEN] = [0; CONTROL_BUF_LEN];
                     buf[..len].copy_from_slice(&xfer.data()[..len]);
+                    let info = ReportInfo {
+                        report_type,
+                        report_id,
+                        len,
+                    };
+
+                    // Output reports are kept to answer GET_REPORT(Output).
+                    if report_type == ReportType::Output {
+                        self.remember_output(report_id, &buf[..len]);
+                    }
 
                     // Overwrite previous buffer even if unused
-                    self.set_report_buf = Some(Report {
-                        info: ReportInfo {
-                            report_type,
-                            report_id,
-                            len,
-                        },
-                        buf,
-                    });
+                    self.set_report_buf = Some(Report { info, buf });
                     xfer.accept().ok();
                 }
             }
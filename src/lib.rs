@@ -5,15 +5,27 @@
 //! and communicating with a host that implements USB HID.
 #![no_std]
 
+#[cfg(any(test, feature = "std"))]
+extern crate std;
+
 pub use usb_device::{Result, UsbError};
 pub mod descriptor;
 pub mod hid_class;
 
+#[cfg(test)]
+#[allow(dead_code)]
+mod test_bus;
+
 #[cfg(test)]
 #[allow(unused_imports)]
 mod tests {
     use crate::descriptor::generator_prelude::*;
-    use crate::descriptor::{KeyboardReport, MouseReport, SystemControlReport};
+    use crate::descriptor::{
+        CtapReport, GazeReport, HighResScrollMouseReport, KeyboardLayout, KeyboardReport,
+        KeyboardUsage, KeyboardWithMediaReport, KeypadReport, MediaKey, MediaKeyboardReport,
+        MouseReport, PowerDeviceReport, StylusReport, SystemControlReport,
+    };
+    use crate::hid_class::ReportType;
 
     // This should generate this descriptor:
     // 0x06, 0x00, 0xFF,  // Usage Page (Vendor Defined 0xFF00)
@@ -53,12 +65,12 @@ mod tests {
     // 0x06, 0x00, 0xFF,                // Usage Page (Vendor Defined 0xFF00)
     // 0x09, 0x01,                      // Usage (0x01)
     // 0xA1, 0x01,                      // Collection (Application)
-    // 0x17, 0x81, 0xFF, 0xFF, 0xFF,    //   Logical Minimum (-128)
+    // 0x15, 0x81,                      //   Logical Minimum (-128)
     // 0x25, 0x7F,                      //   Logical Maximum (127)
     // 0x75, 0x08,                      //   Report Size (8)
     // 0x95, 0x01,                      //   Report Count (1)
     // 0x81, 0x02,                      //   Input (Data,Var,Abs,No Wrap,Linear,Preferred State,No Null Position)
-    // 0x17, 0x01, 0x80, 0xFF, 0xFF,    //   Logical Minimum (-32768)
+    // 0x16, 0x01, 0x80,                //   Logical Minimum (-32768)
     // 0x26, 0xFF, 0x7F,                //   Logical Maximum (32767)
     // 0x75, 0x10,                      //   Report Size (16)
     // 0x91, 0x02,                      //   Output (Data,Var,Abs,No Wrap,Linear,Preferred State,No Null Position,Non-volatile)
@@ -78,11 +90,10 @@ mod tests {
     #[test]
     fn test_custom_signed() {
         let expected = &[
-            6u8, 0u8, 255u8, 9u8, 1u8, 161u8, 1u8, 23u8, 129u8, 255u8, 255u8, 255u8, 37u8, 127u8,
-            117u8, 8u8, 149u8, 1u8, 129u8, 2u8, 23u8, 1u8, 128u8, 255u8, 255u8, 38u8, 255u8, 127u8,
-            117u8, 16u8, 145u8, 2u8, 192u8,
+            6u8, 0u8, 255u8, 9u8, 1u8, 161u8, 1u8, 21u8, 129u8, 37u8, 127u8, 117u8, 8u8, 149u8,
+            1u8, 129u8, 2u8, 22u8, 1u8, 128u8, 38u8, 255u8, 127u8, 117u8, 16u8, 145u8, 2u8, 192u8,
         ];
-        assert_eq!(CustomUnarySignedFrame::desc()[0..32], expected[0..32]);
+        assert_eq!(CustomUnarySignedFrame::desc(), expected);
     }
 
     #[gen_hid_descriptor(
@@ -107,6 +118,49 @@ mod tests {
         assert_eq!(CustomMultiReport::desc(), expected);
     }
 
+    #[test]
+    fn test_custom_reports_push() {
+        use crate::hid_class::HIDClass;
+        use crate::test_bus::*;
+
+        let alloc = test_alloc();
+        let hid = HIDClass::new_ep_in(&alloc, CustomMultiReport::desc(), 10);
+        let dev = test_device(&alloc);
+
+        let report = CustomMultiReport { f1: 0x05, f2: 0x09 };
+        assert_eq!(CustomMultiReportId1::REPORT_ID, 1);
+        assert_eq!(CustomMultiReportId2::MAX_INPUT_LEN, 2);
+
+        assert_eq!(hid.push_input(&CustomMultiReportId1(&report)), Ok(2));
+        assert_eq!(dev.bus().host_read(1), Some(std::vec![1, 0x05]));
+        assert_eq!(hid.push_input(&CustomMultiReportId2(&report)), Ok(2));
+        assert_eq!(dev.bus().host_read(1), Some(std::vec![2, 0x09]));
+    }
+
+    #[test]
+    fn test_set_report_unknown_id() {
+        use crate::hid_class::HIDClass;
+        use crate::test_bus::*;
+        use usb_device::UsbError;
+
+        let alloc = test_alloc();
+        let mut hid = HIDClass::new_control_only(&alloc, CustomMultiReport::desc());
+        let mut dev = test_device(&alloc);
+
+        let mut buf = [0u8; 8];
+        assert_eq!(
+            set_report(&mut dev, &mut hid, ReportType::Feature, 3, &[0x01]),
+            Err(UsbError::InvalidState)
+        );
+        assert!(matches!(
+            hid.pull_raw_report(&mut buf),
+            Err(UsbError::WouldBlock)
+        ));
+
+        set_report(&mut dev, &mut hid, ReportType::Feature, 2, &[0x01]).unwrap();
+        assert_eq!(hid.pull_raw_report(&mut buf).unwrap().report_id, 2);
+    }
+
     // This should generate the following descriptor:
     // 0x06, 0x00, 0xFF,  // Usage Page (Vendor Defined 0xFF00)
     // 0x09, 0x01,        // Usage (0x01)
@@ -135,6 +189,35 @@ mod tests {
         assert_eq!(CustomArray::desc(), expected);
     }
 
+    // This should generate the following descriptor:
+    // 0x06, 0x00, 0xFF,  // Usage Page (Vendor Defined 0xFF00)
+    // 0x09, 0x01,        // Usage (0x01)
+    // 0xA1, 0x01,        // Collection (Application)
+    // 0x15, 0x00,        //   Logical Minimum (0)
+    // 0x26, 0xFF, 0x00,  //   Logical Maximum (255)
+    // 0x75, 0x08,        //   Report Size (8)
+    // 0x95, 0x04,        //   Report Count (4)
+    // 0x82, 0x02, 0x01,  //   Input (Data,Var,Abs,No Wrap,Linear,Preferred State,No Null Position,Buffered Bytes)
+    // 0xC0,              // End Collection
+    #[gen_hid_descriptor(
+        (collection = APPLICATION, usage_page = VENDOR_DEFINED_START, usage = 0x01) = {
+            #[item_settings data,variable,absolute,buffered_bytes] buff=input;
+        }
+    )]
+    #[allow(dead_code)]
+    struct CustomBufferedBytes {
+        buff: [u8; 4],
+    }
+
+    #[test]
+    fn test_buffered_bytes() {
+        let expected: &[u8] = &[
+            0x06, 0x00, 0xFF, 0x09, 0x01, 0xA1, 0x01, 0x15, 0x00, 0x26, 0xFF, 0x00, 0x75, 0x08,
+            0x95, 0x04, 0x82, 0x02, 0x01, 0xC0,
+        ];
+        assert_eq!(CustomBufferedBytes::desc(), expected);
+    }
+
     #[gen_hid_descriptor(
         (collection = APPLICATION, usage_page = VENDOR_DEFINED_START, usage = 0x01) = {
             (usage_min = BUTTON_1, usage_max = BUTTON_3) = {
@@ -197,14 +280,216 @@ mod tests {
         assert_eq!(CustomPackedBits::desc(), expected);
     }
 
+    // This should generate the following descriptor:
+    // 0x05, 0x01,        // Usage Page (Generic Desktop Ctrls)
+    // 0x09, 0x04,        // Usage (Joystick)
+    // 0xA1, 0x01,        // Collection (Application)
+    // 0x09, 0x30,        //   Usage (X)
+    // 0x39, 0x01,        //   Designator Index (1)
+    // 0x15, 0x00,        //   Logical Minimum (0)
+    // 0x26, 0xFF, 0x00,  //   Logical Maximum (255)
+    // 0x75, 0x08,        //   Report Size (8)
+    // 0x95, 0x01,        //   Report Count (1)
+    // 0x81, 0x02,        //   Input (Data,Var,Abs,No Wrap,Linear,Preferred State,No Null Position)
+    // 0x09, 0x31,        //   Usage (Y)
+    // 0x81, 0x02,        //   Input (Data,Var,Abs,No Wrap,Linear,Preferred State,No Null Position)
+    // 0xC0,              // End Collection
+    #[gen_hid_descriptor(
+        (collection = APPLICATION, usage_page = GENERIC_DESKTOP, usage = JOYSTICK) = {
+            #[usage 0x30] #[designator 1] x=input;
+            #[usage Y] y=input;
+        }
+    )]
+    #[allow(dead_code)]
+    struct CustomFieldLocals {
+        x: u8,
+        y: u8,
+    }
+
+    #[test]
+    fn test_custom_field_locals() {
+        let expected: &[u8] = &[
+            0x05, 0x01, 0x09, 0x04, 0xA1, 0x01, 0x09, 0x30, 0x39, 0x01, 0x15, 0x00, 0x26, 0xFF,
+            0x00, 0x75, 0x08, 0x95, 0x01, 0x81, 0x02, 0x09, 0x31, 0x81, 0x02, 0xC0,
+        ];
+        assert_eq!(CustomFieldLocals::desc(), expected);
+    }
+
+    // This should generate the following descriptor:
+    // 0x06, 0x00, 0xFF,  // Usage Page (Vendor Defined 0xFF00)
+    // 0x09, 0x01,        // Usage (0x01)
+    // 0xA1, 0x01,        // Collection (Application)
+    // 0x15, 0x00,        //   Logical Minimum (0)
+    // 0x26, 0xFF, 0x00,  //   Logical Maximum (255)
+    // 0x75, 0x08,        //   Report Size (8)
+    // 0x95, 0x01,        //   Report Count (1)
+    // 0x81, 0x02,        //   Input (Data,Var,Abs,No Wrap,Linear,Preferred State,No Null Position)
+    // 0x15, 0x80,        //   Logical Minimum (-128)
+    // 0x25, 0x7F,        //   Logical Maximum (127)
+    // 0x81, 0x02,        //   Input (Data,Var,Abs,No Wrap,Linear,Preferred State,No Null Position)
+    // 0xC0,              // End Collection
+    #[gen_hid_descriptor(
+        (collection = APPLICATION, usage_page = VENDOR_DEFINED_START, usage = 0x01) = {
+            #[full_range] f1=input;
+            #[full_range] f2=input;
+        }
+    )]
+    #[allow(dead_code)]
+    struct CustomFullRange {
+        f1: u8,
+        f2: i8,
+    }
+
+    #[test]
+    fn test_full_range() {
+        let expected: &[u8] = &[
+            0x06, 0x00, 0xFF, 0x09, 0x01, 0xA1, 0x01, 0x15, 0x00, 0x26, 0xFF, 0x00, 0x75, 0x08,
+            0x95, 0x01, 0x81, 0x02, 0x15, 0x80, 0x25, 0x7F, 0x81, 0x02, 0xC0,
+        ];
+        assert_eq!(CustomFullRange::desc(), expected);
+    }
+
+    // This should generate the following descriptor:
+    // 0x05, 0x01,        // Usage Page (Generic Desktop Ctrls)
+    // 0x09, 0x02,        // Usage (Mouse)
+    // 0xA1, 0x01,        // Collection (Application)
+    // 0x09, 0x30,        //   Usage (X)
+    // 0x15, 0x81,                   // Logical Minimum (-127)
+    // 0x25, 0x7F,        //   Logical Maximum (127)
+    // 0x75, 0x08,        //   Report Size (8)
+    // 0x95, 0x01,        //   Report Count (1)
+    // 0x81, 0x06,        //   Input (Data,Var,Rel,No Wrap,Linear,Preferred State,No Null Position)
+    // 0x05, 0x0C,        //   Usage Page (Consumer)
+    // 0x0A, 0x38, 0x02,  //   Usage (AC Pan)
+    // 0x81, 0x06,        //   Input (Data,Var,Rel,No Wrap,Linear,Preferred State,No Null Position)
+    // 0x05, 0x01,        //   Usage Page (Generic Desktop Ctrls)
+    // 0x09, 0x38,        //   Usage (Wheel)
+    // 0x81, 0x06,        //   Input (Data,Var,Rel,No Wrap,Linear,Preferred State,No Null Position)
+    // 0xC0,              // End Collection
+    #[gen_hid_descriptor(
+        (collection = APPLICATION, usage_page = GENERIC_DESKTOP, usage = MOUSE) = {
+            #[usage X] #[item_settings data,variable,relative] x=input;
+            #[usage_page CONSUMER] #[usage AC_PAN] #[item_settings data,variable,relative] pan=input;
+            #[usage WHEEL] #[item_settings data,variable,relative] wheel=input;
+        }
+    )]
+    #[allow(dead_code)]
+    struct CustomItemUsagePage {
+        x: i8,
+        pan: i8,
+        wheel: i8,
+    }
+
+    #[test]
+    fn test_item_usage_page() {
+        let expected: &[u8] = &[
+            0x05, 0x01, 0x09, 0x02, 0xA1, 0x01, 0x09, 0x30, 0x15, 0x81, 0x25, 0x7F, 0x75, 0x08,
+            0x95, 0x01, 0x81, 0x06, 0x05, 0x0C, 0x0A, 0x38, 0x02, 0x81, 0x06, 0x05, 0x01, 0x09,
+            0x38, 0x81, 0x06, 0xC0,
+        ];
+        assert_eq!(CustomItemUsagePage::desc(), expected);
+    }
+
+    // This should generate the following descriptor:
+    // 0x06, 0x00, 0xFF,  // Usage Page (Vendor Defined 0xFF00)
+    // 0x09, 0x01,        // Usage (0x01)
+    // 0xA1, 0x01,        // Collection (Application)
+    // 0xA1, 0x02,        //   Collection (Logical)
+    // 0x15, 0x00,        //     Logical Minimum (0)
+    // 0x26, 0xFF, 0x00,  //     Logical Maximum (255)
+    // 0x75, 0x08,        //     Report Size (8)
+    // 0x95, 0x01,        //     Report Count (1)
+    // 0x81, 0x02,        //     Input (Data,Var,Abs,No Wrap,Linear,Preferred State,No Null Position)
+    // 0xC0,              //   End Collection
+    // 0xA1, 0x03,        //   Collection (Report)
+    // 0x81, 0x02,        //     Input (Data,Var,Abs,No Wrap,Linear,Preferred State,No Null Position)
+    // 0xC0,              //   End Collection
+    // 0xA1, 0x00,        //   Collection (Physical)
+    // 0x81, 0x02,        //     Input (Data,Var,Abs,No Wrap,Linear,Preferred State,No Null Position)
+    // 0xC0,              //   End Collection
+    // 0xC0,              // End Collection
+    #[gen_hid_descriptor(
+        (collection = APPLICATION, usage_page = VENDOR_DEFINED_START, usage = 0x01) = {
+            (collection = LOGICAL) = {
+                f1=input;
+            };
+            (collection = REPORT) = {
+                f2=input;
+            };
+            (collection = PHYSICAL) = {
+                f3=input;
+            };
+        }
+    )]
+    #[allow(dead_code)]
+    struct CustomCollectionTypes {
+        f1: u8,
+        f2: u8,
+        f3: u8,
+    }
+
+    #[test]
+    fn test_collection_types() {
+        let expected: &[u8] = &[
+            0x06, 0x00, 0xFF, 0x09, 0x01, 0xA1, 0x01, 0xA1, 0x02, 0x15, 0x00, 0x26, 0xFF, 0x00,
+            0x75, 0x08, 0x95, 0x01, 0x81, 0x02, 0xC0, 0xA1, 0x03, 0x81, 0x02, 0xC0, 0xA1, 0x00,
+            0x81, 0x02, 0xC0, 0xC0,
+        ];
+        assert_eq!(CustomCollectionTypes::desc(), expected);
+    }
+
+    #[gen_hid_descriptor(
+        (collection = APPLICATION, usage_page = VENDOR_DEFINED_START, usage = 0x01) = {
+            #[item_settings data,array,absolute] f1=input;
+            #[item_settings data,array,absolute] f2=input;
+        }
+    )]
+    #[allow(dead_code)]
+    struct CustomLongForm {
+        f1: u8,
+        f2: u8,
+    }
+
+    #[gen_hid_descriptor(
+        (collection = APPLICATION, usage_page = VENDOR_DEFINED_START, usage = 0x01) = {
+            #[item_settings data,array,absolute] f1=input;
+            #[quirks long_form] #[item_settings data,array,absolute] f2=input;
+        }
+    )]
+    #[quirks(allow_short)]
+    #[allow(dead_code)]
+    struct CustomShortForm {
+        f1: u8,
+        f2: u8,
+    }
+
+    #[test]
+    fn test_descriptor_quirks() {
+        let long: &[u8] = &[
+            0x06, 0x00, 0xFF, 0x09, 0x01, 0xA1, 0x01, 0x15, 0x00, 0x26, 0xFF, 0x00, 0x75, 0x08,
+            0x95, 0x01, 0x81, 0x00, 0x81, 0x00, 0xC0,
+        ];
+        assert_eq!(CustomLongForm::desc(), long);
+
+        // Input (Data,Array,Abs) is emitted without data bytes for f1 only.
+        let short: &[u8] = &[
+            0x06, 0x00, 0xFF, 0x09, 0x01, 0xA1, 0x01, 0x15, 0x00, 0x26, 0xFF, 0x00, 0x75, 0x08,
+            0x95, 0x01, 0x80, 0x81, 0x00, 0xC0,
+        ];
+        assert_eq!(CustomShortForm::desc(), short);
+        assert_eq!(
+            CustomShortForm::desc().len(),
+            CustomLongForm::desc().len() - 1
+        );
+    }
+
     #[test]
     fn test_mouse_descriptor() {
         let expected = &[
             5u8, 1u8, 9u8, 2u8, 161u8, 1u8, 9u8, 1u8, 161u8, 0u8, 5u8, 9u8, 25u8, 1u8, 41u8, 8u8,
-            21u8, 0u8, 37u8, 1u8, 117u8, 1u8, 149u8, 8u8, 129u8, 2u8, 5u8, 1u8, 9u8, 48u8, 23u8,
-            129u8, 255u8, 255u8, 255u8, 37u8, 127u8, 117u8, 8u8, 149u8, 1u8, 129u8, 6u8, 9u8, 49u8,
-            129u8, 6u8, 9u8, 56u8, 129u8, 6u8, 5u8, 12u8, 10u8, 56u8, 2u8, 129u8, 6u8, 192u8,
-            192u8,
+            21u8, 0u8, 37u8, 1u8, 117u8, 1u8, 149u8, 8u8, 129u8, 2u8, 5u8, 1u8, 9u8, 48u8, 21u8,
+            129u8, 37u8, 127u8, 117u8, 8u8, 149u8, 1u8, 129u8, 6u8, 9u8, 49u8, 129u8, 6u8, 9u8,
+            56u8, 129u8, 6u8, 5u8, 12u8, 10u8, 56u8, 2u8, 129u8, 6u8, 192u8, 192u8,
         ];
         assert_eq!(MouseReport::desc()[0..32], expected[0..32]);
     }
@@ -250,6 +535,149 @@ mod tests {
         assert_eq!(KeyboardReport::desc(), expected);
     }
 
+    #[test]
+    fn test_keyboard_from_keys() {
+        use usb_device::UsbError;
+
+        let report = KeyboardReport::from_keys(&[
+            KeyboardUsage::KeyboardLeftShift,
+            KeyboardUsage::KeyboardAa,
+            KeyboardUsage::KeyboardBb,
+        ])
+        .unwrap();
+        assert_eq!({ report.modifier }, 0x02);
+        assert_eq!({ report.keycodes }, [0x04, 0x05, 0, 0, 0, 0]);
+
+        let report = KeyboardReport::from_keys(&[
+            KeyboardUsage::KeyboardLeftControl,
+            KeyboardUsage::KeyboardRightGUI,
+        ])
+        .unwrap();
+        assert_eq!({ report.modifier }, 0x81);
+        assert_eq!({ report.keycodes }, [0; 6]);
+
+        assert!(matches!(
+            KeyboardReport::from_keys(&[KeyboardUsage::KeyboardAa; 7]),
+            Err(UsbError::BufferOverflow)
+        ));
+    }
+
+    #[test]
+    fn test_keypad_descriptor() {
+        let expected = &[
+            0x05, 0x01, // Usage Page (Generic Desktop)
+            0x09, 0x07, // Usage (Keypad)
+            0xa1, 0x01, // Collection (Application)
+            0x05, 0x07, // Usage Page (Key Codes)
+            0x19, 0x53, // Usage Minimum (Keypad Num Lock)
+            0x29, 0x67, // Usage Maximum (Keypad =)
+            0x15, 0x53, // Logical Minimum (83)
+            0x25, 0x67, // Logical Maximum (103)
+            0x75, 0x08, // Report Size (8)
+            0x95, 0x06, // Report Count (6)
+            0x81, 0x00, // Input (Data, Array, Absolute)
+            0xc0, // End Collection
+        ];
+        assert_eq!(KeypadReport::desc(), expected);
+    }
+
+    #[test]
+    fn test_keypad_serialize() {
+        let mut report = KeypadReport::default();
+        report.keycodes[0] = KeyboardUsage::Keypad5 as u8;
+        report.keycodes[1] = KeyboardUsage::KeypadEnter as u8;
+        assert_eq!(report.to_array(), [0x5D, 0x58, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_power_device_descriptor() {
+        let expected = &[
+            0x05, 0x84, // Usage Page (Power Device)
+            0x09, 0x24, // Usage (Power Summary)
+            0xa1, 0x01, // Collection (Application)
+            0x05, 0x85, // Usage Page (Battery System)
+            0x09, 0x66, // Usage (Remaining Capacity)
+            0x15, 0x00, // Logical Minimum (0)
+            0x25, 0x64, // Logical Maximum (100)
+            0x75, 0x08, // Report Size (8)
+            0x95, 0x01, // Report Count (1)
+            0x81, 0x02, // Input (Data, Variable, Absolute)
+            0x09, 0x68, // Usage (Run Time To Empty)
+            0x27, 0xFF, 0xFF, 0x00, 0x00, // Logical Maximum (65535)
+            0x66, 0x01, 0x10, // Unit (Seconds)
+            0x75, 0x10, // Report Size (16)
+            0x81, 0x02, // Input (Data, Variable, Absolute)
+            0x05, 0x84, // Usage Page (Power Device)
+            0x09, 0x30, // Usage (Voltage)
+            0x67, 0x21, 0xD1, 0xF0, 0x00, // Unit (Volts)
+            0x55, 0x04, // Unit Exponent (4: millivolts)
+            0x81, 0x02, // Input (Data, Variable, Absolute)
+            0x09, 0x60, // Usage (Present)
+            0x25, 0x01, // Logical Maximum (1)
+            0x65, 0x00, // Unit (None)
+            0x55, 0x00, // Unit Exponent (0)
+            0x75, 0x01, // Report Size (1)
+            0x81, 0x02, // Input (Data, Variable, Absolute)
+            0x95, 0x07, // Report Count (7)
+            0x81, 0x03, // Input (Constant, Variable, Absolute)
+            0xc0, // End Collection
+        ];
+        assert_eq!(PowerDeviceReport::desc(), expected);
+    }
+
+    #[test]
+    fn test_media_keyboard_descriptor() {
+        let expected = &[
+            0x05, 0x0C, // Usage Page (Consumer)
+            0x09, 0x01, // Usage (Consumer Control)
+            0xA1, 0x01, // Collection (Application)
+            0x05, 0x0C, // Usage Page (Consumer)
+            0x19, 0x00, // Usage Minimum (Unassigned)
+            0x2A, 0x14, 0x05, // Usage Maximum (0x514)
+            0x15, 0x00, // Logical Minimum (0)
+            0x27, 0xFF, 0xFF, 0x00, 0x00, // Logical Maximum (65535)
+            0x75, 0x10, // Report Size (16)
+            0x95, 0x01, // Report Count (1)
+            0x81,
+            0x00, // Input (Data,Array,Abs,No Wrap,Linear,Preferred State,No Null Position)
+            0xC0, // End Collection
+        ];
+        assert_eq!(MediaKeyboardReport::desc(), expected);
+
+        // Releasing all keys sends the Unassigned usage.
+        let release = MediaKeyboardReport {
+            usage_id: MediaKey::Zero.into(),
+        };
+        assert_eq!(release.to_array(), [0x00, 0x00]);
+        let press = MediaKeyboardReport {
+            usage_id: MediaKey::PlayPause.into(),
+        };
+        assert_eq!(press.to_array(), [0xCD, 0x00]);
+    }
+
+    #[gen_hid_descriptor(
+        (collection = APPLICATION, usage_page = CONSUMER, usage = CONSUMER_CONTROL) = {
+            (usage_page = CONSUMER, usage_min = 0x00, usage_max = 0x514) = {
+                #[item_settings data,array,absolute,null] usage_id=input;
+            };
+        }
+    )]
+    #[allow(dead_code)]
+    struct CustomNullableMediaKeyboard {
+        usage_id: u16,
+    }
+
+    #[test]
+    fn test_media_keyboard_null_state() {
+        let desc = CustomNullableMediaKeyboard::desc();
+        // Input (Data,Array,Abs,No Wrap,Linear,Preferred State,Null State)
+        assert_eq!(desc[desc.len() - 3..], [0x81, 0x40, 0xC0]);
+        assert_eq!(
+            desc[..desc.len() - 3],
+            MediaKeyboardReport::desc()[..desc.len() - 3]
+        );
+    }
+
     #[test]
     fn test_system_control_descriptor() {
         let expected = &[
@@ -268,4 +696,3595 @@ mod tests {
         ];
         assert_eq!(SystemControlReport::desc(), expected);
     }
+
+    #[test]
+    fn test_volume_knob_descriptor() {
+        use crate::descriptor::VolumeKnobReport;
+
+        let expected = &[
+            0x05, 0x0C, // Usage Page (Consumer)
+            0x09, 0x01, // Usage (Consumer Control)
+            0xA1, 0x01, // Collection (Application)
+            0x09, 0xE2, //   Usage (Mute)
+            0x09, 0xE9, //   Usage (Volume Increment)
+            0x09, 0xEA, //   Usage (Volume Decrement)
+            0x15, 0x00, //   Logical Minimum (0)
+            0x25, 0x01, //   Logical Maximum (1)
+            0x75, 0x01, //   Report Size (1)
+            0x95, 0x03, //   Report Count (3)
+            0x81, 0x02, //   Input (Data,Var,Abs)
+            0x95, 0x05, //   Report Count (5)
+            0x81, 0x03, //   Input (Const,Var,Abs)
+            0xC0, // End Collection
+        ];
+        assert_eq!(VolumeKnobReport::desc(), expected);
+
+        // Two detents clockwise, each a press and a release of Volume Increment.
+        let mut buf = [0u8; 1];
+        let mut sent = std::vec::Vec::new();
+        for turn in [1i8, 0, 1, 0] {
+            assert_eq!(
+                VolumeKnobReport::new(false, turn).serialize_into(&mut buf),
+                Ok(1)
+            );
+            sent.push(buf[0]);
+        }
+        assert_eq!(sent, [0x02, 0x00, 0x02, 0x00]);
+        let report = VolumeKnobReport::new(true, -3);
+        assert!(report.controls_bit(0) && report.controls_bit(2));
+        assert_eq!(report.to_array(), [0x05]);
+        assert_eq!(VolumeKnobReport::default().to_array(), [0x00]);
+    }
+
+    // This should generate the following descriptor:
+    // 0x06, 0x00, 0xFF,  // Usage Page (Vendor Defined 0xFF00)
+    // 0x09, 0x01,        // Usage (0x01)
+    // 0xA1, 0x01,        // Collection (Application)
+    // 0x09, 0x02,        //   Usage (0x02)
+    // 0xA1, 0x02,        //   Collection (Logical)
+    // 0x09, 0x03,        //     Usage (0x03)
+    // 0xA1, 0x00,        //     Collection (Physical)
+    // 0x15, 0x01,        //       Logical Minimum (1)
+    // 0x26, 0xFF, 0x00,  //       Logical Maximum (255)
+    // 0x75, 0x08,        //       Report Size (8)
+    // 0x95, 0x01,        //       Report Count (1)
+    // 0x81, 0x02,        //       Input (Data,Var,Abs,No Wrap,Linear,Preferred State,No Null Position)
+    // 0xC0,              //     End Collection
+    // 0x15, 0x00,        //     Logical Minimum (0)
+    // 0x81, 0x02,        //     Input (Data,Var,Abs,No Wrap,Linear,Preferred State,No Null Position)
+    // 0xC0,              //   End Collection
+    // 0x81, 0x02,        //   Input (Data,Var,Abs,No Wrap,Linear,Preferred State,No Null Position)
+    // 0xC0,              // End Collection
+    #[gen_hid_descriptor(
+        (collection = APPLICATION, usage_page = VENDOR_DEFINED_START, usage = 0x01) = {
+            (collection = LOGICAL, usage = 0x02) = {
+                (collection = PHYSICAL, usage = 0x03) = {
+                    (logical_min = 1,) = {
+                        f1=input;
+                    };
+                };
+                f2=input;
+            };
+            f3=input;
+        }
+    )]
+    #[allow(dead_code)]
+    struct CustomNestedCollections {
+        f1: u8,
+        f2: u8,
+        f3: u8,
+    }
+
+    #[test]
+    fn test_nested_collections() {
+        let expected: &[u8] = &[
+            0x06, 0x00, 0xFF, 0x09, 0x01, 0xA1, 0x01, 0x09, 0x02, 0xA1, 0x02, 0x09, 0x03, 0xA1,
+            0x00, 0x15, 0x01, 0x26, 0xFF, 0x00, 0x75, 0x08, 0x95, 0x01, 0x81, 0x02, 0xC0, 0x15,
+            0x00, 0x81, 0x02, 0xC0, 0x81, 0x02, 0xC0,
+        ];
+        assert_eq!(CustomNestedCollections::desc(), expected);
+    }
+
+    #[test]
+    fn test_to_array() {
+        let report = MouseReport {
+            buttons: 0x05,
+            x: -3,
+            y: 7,
+            wheel: -1,
+            pan: 1,
+        };
+        let mut expected = [0u8; 64];
+        let size = ssmarshal::serialize(&mut expected, &report).unwrap();
+
+        assert_eq!(MouseReport::MAX_INPUT_LEN, 5);
+        assert_eq!(size, MouseReport::MAX_INPUT_LEN);
+        assert_eq!(report.to_array(), expected[..size]);
+    }
+
+    #[test]
+    fn test_get_report_output() {
+        use crate::hid_class::HIDClass;
+        use crate::test_bus::*;
+
+        let alloc = test_alloc();
+        let mut hid = HIDClass::new(&alloc, KeyboardReport::desc(), 10);
+        let mut dev = test_device(&alloc);
+
+        // Nothing has been received yet, so there is nothing to report.
+        assert!(get_report(&mut dev, &mut hid, ReportType::Output, 0, 1).is_err());
+
+        // Set the keyboard LEDs, then read them back.
+        set_report(&mut dev, &mut hid, ReportType::Output, 0, &[0x03]).unwrap();
+        let data = get_report(&mut dev, &mut hid, ReportType::Output, 0, 1).unwrap();
+        assert_eq!(data, [0x03]);
+
+        // Reading back the report does not depend on the SET_REPORT buffer being pending.
+        let mut buf = [0u8; 8];
+        hid.pull_raw_report(&mut buf).unwrap();
+        let data = get_report(&mut dev, &mut hid, ReportType::Output, 0, 1).unwrap();
+        assert_eq!(data, [0x03]);
+
+        // Only output reports are answered.
+        assert!(get_report(&mut dev, &mut hid, ReportType::Input, 0, 1).is_err());
+        assert!(get_report(&mut dev, &mut hid, ReportType::Output, 1, 1).is_err());
+
+        // Reports read from the OUT endpoint are answered as well.
+        dev.bus().host_write(1, &[0x05], false);
+        assert_eq!(hid.pull_raw_output(&mut buf), Ok(1));
+        let data = get_report(&mut dev, &mut hid, ReportType::Output, 0, 1).unwrap();
+        assert_eq!(data, [0x05]);
+
+        hid.clear_get_report_responses();
+        assert!(get_report(&mut dev, &mut hid, ReportType::Output, 0, 1).is_err());
+    }
+
+    #[test]
+    fn test_report_descriptor_accessor() {
+        use crate::hid_class::HIDClass;
+        use crate::test_bus::test_alloc;
+
+        let alloc = test_alloc();
+        let hid = HIDClass::new(&alloc, MouseReport::desc(), 10);
+        assert_eq!(hid.report_descriptor(), MouseReport::desc());
+    }
+
+    #[test]
+    fn test_clear_set_report() {
+        use crate::hid_class::HIDClass;
+        use crate::test_bus::*;
+        use usb_device::UsbError;
+
+        let alloc = test_alloc();
+        let mut hid = HIDClass::new(&alloc, KeyboardReport::desc(), 10);
+        let mut dev = test_device(&alloc);
+
+        set_report(&mut dev, &mut hid, ReportType::Output, 0, &[0x01]).unwrap();
+        hid.clear_set_report();
+
+        let mut buf = [0u8; 8];
+        assert!(matches!(
+            hid.pull_raw_report(&mut buf),
+            Err(UsbError::WouldBlock)
+        ));
+    }
+
+    #[test]
+    fn test_poll_interval() {
+        use crate::hid_class::{HIDClass, HidClassSettings, PollInterval};
+        use crate::test_bus::{test_alloc, test_device};
+        use usb_device::endpoint::EndpointAddress;
+
+        assert_eq!(PollInterval::FullSpeedMs(10).b_interval(), 10);
+        assert_eq!(PollInterval::HighSpeedMicroframes(1).b_interval(), 1);
+        assert_eq!(PollInterval::HighSpeedMicroframes(8).b_interval(), 4);
+        assert_eq!(PollInterval::HighSpeedMicroframes(10).b_interval(), 4);
+        assert_eq!(PollInterval::HighSpeedMicroframes(255).b_interval(), 8);
+
+        let alloc = test_alloc();
+        let _hid = HIDClass::new_with_settings(
+            &alloc,
+            MouseReport::desc(),
+            10,
+            HidClassSettings::default().with_poll_interval(PollInterval::HighSpeedMicroframes(2)),
+        );
+        let _hid_default = HIDClass::new_ep_in(&alloc, MouseReport::desc(), 10);
+
+        let dev = test_device(&alloc);
+        assert_eq!(dev.bus().endpoint(EndpointAddress::from(0x81)).interval, 2);
+        assert_eq!(dev.bus().endpoint(EndpointAddress::from(0x82)).interval, 10);
+    }
+
+    #[test]
+    #[should_panic(expected = "poll_ms must be at least 1")]
+    fn test_poll_ms_zero() {
+        use crate::hid_class::HIDClass;
+        use crate::test_bus::test_alloc;
+
+        let alloc = test_alloc();
+        let _hid = HIDClass::new(&alloc, MouseReport::desc(), 0);
+    }
+
+    #[test]
+    fn test_max_packet_size() {
+        use crate::hid_class::{HIDClass, HidClassSettings};
+        use crate::test_bus::{test_alloc, test_device};
+        use usb_device::endpoint::EndpointAddress;
+        use usb_device::UsbError;
+
+        let alloc = test_alloc();
+        let hid = HIDClass::new_with_settings(
+            &alloc,
+            CtapReport::desc(),
+            10,
+            HidClassSettings::default().with_max_packet_size(16),
+        );
+        let dev = test_device(&alloc);
+        assert_eq!(
+            dev.bus()
+                .endpoint(EndpointAddress::from(0x01))
+                .max_packet_size,
+            16
+        );
+        assert_eq!(
+            dev.bus()
+                .endpoint(EndpointAddress::from(0x81))
+                .max_packet_size,
+            16
+        );
+
+        assert_eq!(hid.push_raw_input(&[0u8; 16]), Ok(16));
+        dev.bus().host_read(1);
+        assert_eq!(
+            hid.push_raw_input(&[0u8; 17]),
+            Err(UsbError::BufferOverflow)
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_max_packet_size_exceeds_buffer() {
+        use crate::hid_class::{HIDClass, HidClassSettings};
+        use crate::test_bus::test_alloc;
+
+        let alloc = test_alloc();
+        let _hid = HIDClass::new_with_settings(
+            &alloc,
+            CtapReport::desc(),
+            10,
+            HidClassSettings::default().with_max_packet_size(128),
+        );
+    }
+
+    #[test]
+    fn test_pull_keyboard_leds() {
+        use crate::descriptor::KeyboardLedsReport;
+        use crate::hid_class::HIDClass;
+        use crate::test_bus::*;
+        use usb_device::UsbError;
+
+        let leds = KeyboardLedsReport::from(0x03);
+        assert!(leds.num_lock && leds.caps_lock);
+        assert!(!leds.scroll_lock && !leds.compose && !leds.kana);
+        assert_eq!(u8::from(leds), 0x03);
+
+        let alloc = test_alloc();
+        let mut hid = HIDClass::new(&alloc, KeyboardReport::desc(), 10);
+        let mut dev = test_device(&alloc);
+        assert!(matches!(
+            hid.pull_keyboard_leds(),
+            Err(UsbError::WouldBlock)
+        ));
+
+        // Via the OUT endpoint.
+        dev.bus().host_write(1, &[0x04], false);
+        assert_eq!(
+            hid.pull_keyboard_leds().unwrap(),
+            KeyboardLedsReport {
+                scroll_lock: true,
+                ..Default::default()
+            }
+        );
+
+        // Via SET_REPORT.
+        set_report(&mut dev, &mut hid, ReportType::Output, 0, &[0x03]).unwrap();
+        assert_eq!(hid.pull_keyboard_leds().unwrap(), leds);
+        assert!(matches!(
+            hid.pull_keyboard_leds(),
+            Err(UsbError::WouldBlock)
+        ));
+    }
+
+    #[test]
+    fn test_pull_keyboard_leds_report_id() {
+        use crate::descriptor::{KeyboardLedsReport, KeyboardWithMediaReport};
+        use crate::hid_class::HIDClass;
+        use crate::test_bus::*;
+        use usb_device::UsbError;
+
+        let alloc = test_alloc();
+        let mut hid = HIDClass::new(&alloc, KeyboardWithMediaReport::desc(), 10);
+        let mut dev = test_device(&alloc);
+        let leds = KeyboardLedsReport::from(0x03);
+
+        // The LED byte follows the report ID, from the OUT endpoint and SET_REPORT alike.
+        dev.bus().host_write(1, &[0x01, 0x03], false);
+        assert_eq!(hid.pull_keyboard_leds(), Ok(leds));
+        set_report(&mut dev, &mut hid, ReportType::Output, 1, &[0x01, 0x03]).unwrap();
+        assert_eq!(hid.pull_keyboard_leds(), Ok(leds));
+
+        // Undeclared report IDs, and reports without an LED byte, are rejected.
+        dev.bus().host_write(1, &[0x07, 0x03], false);
+        assert_eq!(hid.pull_keyboard_leds(), Err(UsbError::ParseError));
+        dev.bus().host_write(1, &[0x01], false);
+        assert_eq!(hid.pull_keyboard_leds(), Err(UsbError::ParseError));
+    }
+
+    #[test]
+    fn test_pull_output() {
+        use crate::hid_class::HIDClass;
+        use crate::test_bus::*;
+        use usb_device::UsbError;
+
+        let report = KeyboardReport::from_output_report(&[0x05]).unwrap();
+        assert_eq!(report.leds, 0x05);
+        assert_eq!(report.keycodes, [0; 6]);
+        assert_eq!(
+            KeyboardReport::from_output_report(&[0x05, 0x00]),
+            Err(UsbError::ParseError)
+        );
+
+        let alloc = test_alloc();
+        let mut hid = HIDClass::new(&alloc, CustomCalibration::desc(), 10);
+        let mut dev = test_device(&alloc);
+        assert_eq!(
+            hid.pull_output::<CustomCalibration>(),
+            Err(UsbError::WouldBlock)
+        );
+
+        // Via the OUT endpoint: only the output field is set.
+        dev.bus().host_write(1, &[0x02], false);
+        let device: CustomCalibration = hid.pull_output().unwrap();
+        assert_eq!(device.mode, 0x02);
+        assert_eq!(({ device.reading }, { device.calibration }), (0, [0; 3]));
+
+        // Via SET_REPORT, leaving feature reports for pull_feature.
+        set_report(&mut dev, &mut hid, ReportType::Output, 0, &[0x03]).unwrap();
+        assert_eq!(hid.pull_output::<CustomCalibration>().unwrap().mode, 0x03);
+        set_report(&mut dev, &mut hid, ReportType::Feature, 0, &[0; 10]).unwrap();
+        assert_eq!(
+            hid.pull_output::<CustomCalibration>(),
+            Err(UsbError::WouldBlock)
+        );
+        assert!(hid.pull_feature::<CustomCalibration>().is_ok());
+    }
+
+    #[test]
+    fn test_control_only() {
+        use crate::hid_class::HIDClass;
+        use crate::test_bus::*;
+        use usb_device::UsbError;
+
+        let alloc = test_alloc();
+        let mut hid = HIDClass::new_control_only(&alloc, KeyboardReport::desc());
+        let mut dev = test_device(&alloc);
+
+        let config = get_configuration_descriptor(&mut dev, &mut hid).unwrap();
+        let descriptors = split_descriptors(&config);
+        let (_, interface) = descriptors
+            .iter()
+            .find(|(typ, _)| *typ == DESC_TYPE_INTERFACE)
+            .unwrap();
+        assert_eq!(interface[4], 0); // bNumEndpoints
+        assert!(!descriptors
+            .iter()
+            .any(|(typ, _)| *typ == DESC_TYPE_ENDPOINT));
+
+        // Reports are still received through SET_REPORT.
+        assert_eq!(
+            hid.push_raw_input(&[0u8; 8]),
+            Err(UsbError::InvalidEndpoint)
+        );
+        set_report(&mut dev, &mut hid, ReportType::Output, 0, &[0x02]).unwrap();
+        let mut buf = [0u8; 8];
+        assert_eq!(hid.pull_raw_report(&mut buf).unwrap().len, 1);
+        assert_eq!(buf[0], 0x02);
+    }
+
+    #[gen_hid_descriptor(
+        (collection = APPLICATION, usage_page = VENDOR_DEFINED_START, usage = 0x01) = {
+            f1=input;
+            f2=output;
+            f3=output;
+        }
+    )]
+    #[allow(dead_code)]
+    struct CustomOutputReport {
+        f1: u8,
+        f2: i16,
+        f3: [u8; 2],
+    }
+
+    #[test]
+    fn test_output_try_from() {
+        use crate::descriptor::KeyboardLedsReport;
+        use usb_device::UsbError;
+
+        let report = CustomOutputReport::try_from(&[0xFE, 0xFF, 0x01, 0x02][..]).unwrap();
+        assert_eq!(
+            report,
+            CustomOutputReport {
+                f1: 0,
+                f2: -2,
+                f3: [0x01, 0x02],
+            }
+        );
+        assert_eq!(
+            CustomOutputReport::try_from(&[0x01, 0x02][..]),
+            Err(UsbError::ParseError)
+        );
+
+        let report = KeyboardReport::try_from(&[0x03][..]).unwrap();
+        assert_eq!({ report.leds }, 0x03);
+        let leds = KeyboardLedsReport::try_from(&[0x03][..]).unwrap();
+        assert!(leds.num_lock && leds.caps_lock);
+    }
+
+    #[test]
+    fn test_interface_protocol() {
+        use crate::hid_class::{HIDClass, HidClassSettings};
+        use crate::test_bus::*;
+
+        let alloc = test_alloc();
+        let mut hid = HIDClass::new_with_settings(
+            &alloc,
+            CtapReport::desc(),
+            10,
+            HidClassSettings::default().with_interface_protocol(0x42),
+        );
+        let mut dev = test_device(&alloc);
+
+        let config = get_configuration_descriptor(&mut dev, &mut hid).unwrap();
+        let descriptors = split_descriptors(&config);
+        let (_, interface) = descriptors
+            .iter()
+            .find(|(typ, _)| *typ == DESC_TYPE_INTERFACE)
+            .unwrap();
+        assert_eq!(interface[7], 0x42); // bInterfaceProtocol
+    }
+
+    #[test]
+    fn test_suspended() {
+        use crate::hid_class::HIDClass;
+        use crate::test_bus::{test_alloc, test_device};
+        use usb_device::UsbError;
+
+        let alloc = test_alloc();
+        let mut hid = HIDClass::new_ep_in(&alloc, MouseReport::desc(), 10);
+        let dev = test_device(&alloc);
+        let report = MouseReport {
+            buttons: 1,
+            x: 0,
+            y: 0,
+            wheel: 0,
+            pan: 0,
+        };
+
+        hid.set_suspended(true);
+        assert!(hid.is_suspended());
+        assert_eq!(hid.push_input(&report), Err(UsbError::InvalidState));
+        assert_eq!(hid.push_raw_input(&[1]), Err(UsbError::InvalidState));
+        assert_eq!(dev.bus().host_read(1), None);
+
+        hid.set_suspended(false);
+        assert_eq!(hid.push_input(&report), Ok(5));
+        assert_eq!(dev.bus().host_read(1), Some(std::vec![1, 0, 0, 0, 0]));
+    }
+
+    #[test]
+    fn test_const_descriptors() {
+        const DESCRIPTORS: [&[u8]; 2] = [&MouseReport::DESC, &KeyboardReport::DESC];
+
+        assert_eq!(DESCRIPTORS[0], MouseReport::desc());
+        assert_eq!(DESCRIPTORS[1], KeyboardReport::desc());
+        assert_eq!(KeyboardReport::DESC_LEN, KeyboardReport::desc().len());
+    }
+
+    #[test]
+    fn test_join_descriptors() {
+        use crate::descriptor::{join, total_len};
+
+        const DESCRIPTORS: [&[u8]; 2] = [&MouseReport::DESC, &KeyboardReport::DESC];
+        const LEN: usize = total_len(&DESCRIPTORS);
+        const JOINED: [u8; LEN] = join(&DESCRIPTORS);
+
+        assert_eq!(LEN, MouseReport::DESC_LEN + KeyboardReport::DESC_LEN);
+        assert_eq!(JOINED[..MouseReport::DESC_LEN], *MouseReport::desc());
+        assert_eq!(JOINED[MouseReport::DESC_LEN..], *KeyboardReport::desc());
+    }
+
+    #[gen_hid_descriptor(
+        (collection = APPLICATION, usage_page = VENDOR_DEFINED_START, usage = 0x01) = {
+            #[item_settings data,variable,relative] f_in=input;
+            #[item_settings data,variable,relative] f_out=output;
+        }
+    )]
+    #[allow(dead_code)]
+    struct CustomSignedArrays {
+        f_in: [i8; 4],
+        f_out: [i8; 4],
+    }
+
+    #[test]
+    fn test_signed_array_round_trip() {
+        let values = [-1, -128, 127, 0];
+        let report = CustomSignedArrays {
+            f_in: values,
+            f_out: [0; 4],
+        };
+        let bytes = report.to_array();
+        assert_eq!(bytes, [0xFF, 0x80, 0x7F, 0x00]);
+
+        let report = CustomSignedArrays::try_from(&bytes[..]).unwrap();
+        assert_eq!({ report.f_out }, values);
+
+        // The logical range of the field is signed.
+        let desc = CustomSignedArrays::desc();
+        assert_eq!(desc[7..9], [0x15, 0x81]);
+    }
+
+    // This should generate the following descriptor:
+    // 0x05, 0x0C,                    // Usage Page (Consumer)
+    // 0x09, 0x01,                    // Usage (Consumer Control)
+    // 0xA1, 0x01,                    // Collection (Application)
+    // 0x1B, 0x00, 0x00, 0x0C, 0x00,  //   Usage Minimum (Consumer: 0x00)
+    // 0x2B, 0x14, 0x05, 0x0C, 0x00,  //   Usage Maximum (Consumer: 0x514)
+    // 0x15, 0x00,                    //   Logical Minimum (0)
+    // 0x27, 0xFF, 0xFF, 0x00, 0x00,  //   Logical Maximum (65535)
+    // 0x75, 0x10,                    //   Report Size (16)
+    // 0x95, 0x01,                    //   Report Count (1)
+    // 0x81, 0x00,                    //   Input (Data,Array,Abs,No Wrap,Linear,Preferred State,No Null Position)
+    // 0x2B, 0x01, 0x00, 0xFF, 0xFF,  //   Usage Maximum (Vendor 0xFFFF: 0x01)
+    // 0x81, 0x00,                    //   Input (Data,Array,Abs,No Wrap,Linear,Preferred State,No Null Position)
+    // 0xC0,                          // End Collection
+    #[gen_hid_descriptor(
+        (collection = APPLICATION, usage_page = CONSUMER, usage = CONSUMER_CONTROL) = {
+            (usage_min = 0x000C0000, usage_max = 0x000C0514) = {
+                #[item_settings data,array,absolute] f1=input;
+            };
+            (usage_max = 0xFFFF0001,) = {
+                #[item_settings data,array,absolute] f2=input;
+            };
+        }
+    )]
+    #[allow(dead_code)]
+    struct CustomExtendedUsageMax {
+        f1: u16,
+        f2: u16,
+    }
+
+    #[test]
+    fn test_extended_usage_max() {
+        let expected: &[u8] = &[
+            0x05, 0x0C, 0x09, 0x01, 0xA1, 0x01, 0x1B, 0x00, 0x00, 0x0C, 0x00, 0x2B, 0x14, 0x05,
+            0x0C, 0x00, 0x15, 0x00, 0x27, 0xFF, 0xFF, 0x00, 0x00, 0x75, 0x10, 0x95, 0x01, 0x81,
+            0x00, 0x2B, 0x01, 0x00, 0xFF, 0xFF, 0x81, 0x00, 0xC0,
+        ];
+        assert_eq!(CustomExtendedUsageMax::desc(), expected);
+    }
+
+    // This should generate the following descriptor:
+    // 0x06, 0x00, 0xFF,              // Usage Page (Vendor Defined 0xFF00)
+    // 0x09, 0x01,                    // Usage (0x01)
+    // 0xA1, 0x01,                    // Collection (Application)
+    // 0x15, 0xC0,                    //   Logical Minimum (-64)
+    // 0x26, 0xBF, 0x00,              //   Logical Maximum (191)
+    // 0x75, 0x10,                    //   Report Size (16)
+    // 0x95, 0x01,                    //   Report Count (1)
+    // 0x81, 0x02,                    //   Input (Data,Var,Abs,No Wrap,Linear,Preferred State,No Null Position)
+    // 0xC0,                          // End Collection
+    #[gen_hid_descriptor(
+        (collection = APPLICATION, usage_page = VENDOR_DEFINED_START, usage = 0x01) = {
+            #[logical_min -64] #[logical_max 191] throttle=input;
+        }
+    )]
+    #[allow(dead_code)]
+    struct CustomAsymmetricRange {
+        throttle: i16,
+    }
+
+    #[test]
+    fn test_item_logical_range() {
+        let expected: &[u8] = &[
+            0x06, 0x00, 0xFF, 0x09, 0x01, 0xA1, 0x01, 0x15, 0xC0, 0x26, 0xBF, 0x00, 0x75, 0x10,
+            0x95, 0x01, 0x81, 0x02, 0xC0,
+        ];
+        assert_eq!(CustomAsymmetricRange::desc(), expected);
+
+        let report = CustomAsymmetricRange { throttle: -64 };
+        assert_eq!(report.to_array(), [0xC0, 0xFF]);
+    }
+
+    #[gen_hid_descriptor(
+        (collection = APPLICATION, usage_page = VENDOR_DEFINED_START, usage = 0x01) = {
+            in1=input;
+            out1=output;
+            in2=input;
+            out2=output;
+        }
+    )]
+    #[allow(dead_code)]
+    struct CustomInterleavedReport {
+        in1: u8,
+        out1: u8,
+        in2: u16,
+        out2: u8,
+    }
+
+    #[test]
+    fn test_interleaved_fields() {
+        // The wire format of each report is defined by the order of its items in the
+        // descriptor, not by the layout of the struct: the input report holds only in1 and
+        // in2, and the output report only out1 and out2.
+        let report = CustomInterleavedReport {
+            in1: 0x11,
+            out1: 0x22,
+            in2: 0x4433,
+            out2: 0x55,
+        };
+        assert_eq!(CustomInterleavedReport::MAX_INPUT_LEN, 3);
+        assert_eq!(report.to_array(), [0x11, 0x33, 0x44]);
+
+        let report = CustomInterleavedReport::try_from(&[0x22, 0x55][..]).unwrap();
+        assert_eq!(
+            report,
+            CustomInterleavedReport {
+                in1: 0,
+                out1: 0x22,
+                in2: 0,
+                out2: 0x55,
+            }
+        );
+    }
+
+    #[test]
+    fn test_copy_descriptor() {
+        use usb_device::UsbError;
+
+        let mut buf = [0u8; 128];
+        assert_eq!(
+            MouseReport::copy_to(&mut buf[..MouseReport::desc().len() - 1]),
+            Err(UsbError::BufferOverflow)
+        );
+        let len = MouseReport::copy_to(&mut buf).unwrap();
+        assert_eq!(&buf[..len], MouseReport::desc());
+    }
+
+    #[test]
+    fn test_stylus_descriptor() {
+        let expected = &[
+            0x05, 0x0d, // Usage Page (Digitizer)
+            0x09, 0x02, // Usage (Pen)
+            0xa1, 0x01, // Collection (Application)
+            0x09, 0x20, // Usage (Stylus)
+            0xa1, 0x00, // Collection (Physical)
+            0x09, 0x42, // Usage (Tip Switch)
+            0x09, 0x44, // Usage (Barrel Switch)
+            0x09, 0x45, // Usage (Eraser)
+            0x09, 0x32, // Usage (In Range)
+            0x15, 0x00, // Logical Minimum (0)
+            0x25, 0x01, // Logical Maximum (1)
+            0x75, 0x01, // Report Size (1)
+            0x95, 0x04, // Report Count (4)
+            0x81, 0x02, // Input (Data, Variable, Absolute)
+            0x81, 0x03, // Input (Constant, Variable, Absolute)
+            0x09, 0x30, // Usage (Tip Pressure)
+            0x27, 0xff, 0xff, 0x00, 0x00, // Logical Maximum (65535)
+            0x75, 0x10, // Report Size (16)
+            0x95, 0x01, // Report Count (1)
+            0x81, 0x02, // Input (Data, Variable, Absolute)
+            0x09, 0x3d, // Usage (X Tilt)
+            0x15, 0xa6, // Logical Minimum (-90)
+            0x25, 0x5a, // Logical Maximum (90)
+            0x75, 0x08, // Report Size (8)
+            0x81, 0x02, // Input (Data, Variable, Absolute)
+            0x09, 0x3e, // Usage (Y Tilt)
+            0x81, 0x02, // Input (Data, Variable, Absolute)
+            0x05, 0x01, // Usage Page (Generic Desktop)
+            0x09, 0x30, // Usage (X)
+            0x15, 0x00, // Logical Minimum (0)
+            0x27, 0xff, 0xff, 0x00, 0x00, // Logical Maximum (65535)
+            0x75, 0x10, // Report Size (16)
+            0x81, 0x02, // Input (Data, Variable, Absolute)
+            0x09, 0x31, // Usage (Y)
+            0x81, 0x02, // Input (Data, Variable, Absolute)
+            0xc0, // End Collection
+            0xc0, // End Collection
+        ];
+        assert_eq!(StylusReport::desc(), expected);
+    }
+
+    #[test]
+    fn test_stylus_serialize() {
+        let report = StylusReport {
+            buttons: 0b1001,
+            tip_pressure: 0x1234,
+            x_tilt: -30,
+            y_tilt: 45,
+            x: 0x0100,
+            y: 0x0200,
+        };
+        assert_eq!(
+            report.to_array(),
+            [0x09, 0x34, 0x12, 0xE2, 0x2D, 0x00, 0x01, 0x00, 0x02]
+        );
+    }
+
+    #[gen_hid_descriptor(
+        (collection = APPLICATION, usage_page = GENERIC_DESKTOP, usage = KEYBOARD) = {
+            #[usage_page KEYBOARD] #[usage_min 0x00] #[usage_max 0xDD] #[item_settings data,array,absolute] keycodes=input;
+        }
+    )]
+    #[allow(dead_code)]
+    struct CustomItemUsageRange {
+        keycodes: [u8; 6],
+    }
+
+    #[test]
+    fn test_item_usage_range() {
+        let expected: &[u8] = &[
+            0x05, 0x01, // Usage Page (Generic Desktop)
+            0x09, 0x06, // Usage (Keyboard)
+            0xa1, 0x01, // Collection (Application)
+            0x05, 0x07, // Usage Page (Key Codes)
+            0x19, 0x00, // Usage Minimum (0)
+            0x29, 0xdd, // Usage Maximum (221)
+            0x15, 0x00, // Logical Minimum (0)
+            0x26, 0xff, 0x00, // Logical Maximum (255)
+            0x75, 0x08, // Report Size (8)
+            0x95, 0x06, // Report Count (6)
+            0x81, 0x00, // Input (Data, Array, Absolute)
+            0x05, 0x01, // Usage Page (Generic Desktop)
+            0xc0, // End Collection
+        ];
+        assert_eq!(CustomItemUsageRange::desc(), expected);
+    }
+
+    #[test]
+    fn test_control_buffer_len() {
+        use crate::hid_class::HIDClass;
+        use crate::test_bus::*;
+
+        let alloc = test_alloc();
+        let mut hid = HIDClass::new(&alloc, MouseReport::desc(), 10);
+        let mut dev = test_device(&alloc);
+        let expected = if cfg!(feature = "control-buffer-256") {
+            256
+        } else {
+            128
+        };
+        assert_eq!(hid.control_buffer_len(), expected);
+
+        let data = std::vec![0xAB; hid.control_buffer_len()];
+        set_report(&mut dev, &mut hid, ReportType::Feature, 0, &data).unwrap();
+        let mut buf = [0u8; 256];
+        assert_eq!(hid.pull_raw_report(&mut buf).unwrap().len, expected);
+    }
+
+    #[gen_hid_descriptor(
+        (collection = APPLICATION, usage_page = VENDOR_DEFINED_START, usage = 0x01) = {
+            #[packed_bits 8] buttons=input;
+            x=input;
+        }
+    )]
+    #[allow(dead_code)]
+    struct CustomBitPadding {
+        buttons: u32,
+        x: u8,
+    }
+
+    #[gen_hid_descriptor(
+        (collection = APPLICATION, usage_page = VENDOR_DEFINED_START, usage = 0x01) = {
+            #[packed_bits 8] #[pad_bytes 3] buttons=input;
+            x=input;
+        }
+    )]
+    #[allow(dead_code)]
+    struct CustomBytePadding {
+        buttons: u32,
+        x: u8,
+    }
+
+    #[test]
+    fn test_pad_bytes() {
+        let header: &[u8] = &[
+            0x06, 0x00, 0xff, // Usage Page (Vendor Defined 0xFF00)
+            0x09, 0x01, // Usage (0x01)
+            0xa1, 0x01, // Collection (Application)
+            0x15, 0x00, // Logical Minimum (0)
+            0x25, 0x01, // Logical Maximum (1)
+            0x75, 0x01, // Report Size (1)
+            0x95, 0x08, // Report Count (8)
+            0x81, 0x02, // Input (Data, Variable, Absolute)
+        ];
+        let bits: &[u8] = &[
+            0x95, 0x18, // Report Count (24)
+            0x81, 0x03, // Input (Constant, Variable, Absolute)
+            0x26, 0xff, 0x00, // Logical Maximum (255)
+            0x75, 0x08, // Report Size (8)
+            0x95, 0x01, // Report Count (1)
+            0x81, 0x02, // Input (Data, Variable, Absolute)
+            0xc0, // End Collection
+        ];
+        let bytes: &[u8] = &[
+            0x75, 0x08, // Report Size (8)
+            0x95, 0x03, // Report Count (3)
+            0x81, 0x03, // Input (Constant, Variable, Absolute)
+            0x26, 0xff, 0x00, // Logical Maximum (255)
+            0x95, 0x01, // Report Count (1)
+            0x81, 0x02, // Input (Data, Variable, Absolute)
+            0xc0, // End Collection
+        ];
+        assert_eq!(CustomBitPadding::desc(), [header, bits].concat());
+        assert_eq!(CustomBytePadding::desc(), [header, bytes].concat());
+
+        let report = CustomBytePadding {
+            buttons: 0x05,
+            x: 0x7F,
+        };
+        assert_eq!(report.to_array(), [0x05, 0x00, 0x00, 0x00, 0x7F]);
+    }
+
+    #[gen_hid_descriptor(
+        (collection = APPLICATION, usage_page = GENERIC_DESKTOP, usage = 0x00) = {
+            (collection = LOGICAL, usage_page = GENERIC_DESKTOP, usage = X) = {
+                a=input;
+            };
+            (usage_page = GENERIC_DESKTOP, usage = Y) = {
+                b=input;
+            };
+            (collection = LOGICAL, usage_page = GENERIC_DESKTOP, usage = Z) = {
+                c=output;
+            };
+        }
+    )]
+    #[allow(dead_code)]
+    struct CustomUniformGlobals {
+        a: u8,
+        b: u8,
+        c: u8,
+    }
+
+    #[test]
+    fn test_globals_deduplicated_across_groups() {
+        // Logical Minimum/Maximum, Report Size and Report Count are only emitted when they
+        // change, regardless of group boundaries. A usage page named in a group spec is always
+        // emitted.
+        let expected: &[u8] = &[
+            0x05, 0x01, // Usage Page (Generic Desktop)
+            0x09, 0x00, // Usage (Undefined)
+            0xa1, 0x01, // Collection (Application)
+            0x05, 0x01, // Usage Page (Generic Desktop)
+            0x09, 0x30, // Usage (X)
+            0xa1, 0x02, // Collection (Logical)
+            0x15, 0x00, // Logical Minimum (0)
+            0x26, 0xff, 0x00, // Logical Maximum (255)
+            0x75, 0x08, // Report Size (8)
+            0x95, 0x01, // Report Count (1)
+            0x81, 0x02, // Input (Data, Variable, Absolute)
+            0xc0, // End Collection
+            0x05, 0x01, // Usage Page (Generic Desktop)
+            0x09, 0x31, // Usage (Y)
+            0x81, 0x02, // Input (Data, Variable, Absolute)
+            0x05, 0x01, // Usage Page (Generic Desktop)
+            0x09, 0x32, // Usage (Z)
+            0xa1, 0x02, // Collection (Logical)
+            0x91, 0x02, // Output (Data, Variable, Absolute)
+            0xc0, // End Collection
+            0xc0, // End Collection
+        ];
+        assert_eq!(CustomUniformGlobals::desc(), expected);
+    }
+
+    #[gen_hid_descriptor(
+        (collection = APPLICATION, usage_page = VENDOR_DEFINED_START, usage = 0x01) = {
+            (usage = 0x01,) = {
+                #[logical_max 4] count=input;
+            };
+            (usage = 0x02,) = {
+                samples=input;
+            };
+        }
+    )]
+    #[allow(dead_code)]
+    struct CustomCountedSamples {
+        count: u8,
+        samples: [u8; 4],
+    }
+
+    #[test]
+    fn test_counted_array() {
+        let expected: &[u8] = &[
+            0x06, 0x00, 0xff, // Usage Page (Vendor Defined 0xFF00)
+            0x09, 0x01, // Usage (0x01)
+            0xa1, 0x01, // Collection (Application)
+            0x09, 0x01, // Usage (0x01)
+            0x15, 0x00, // Logical Minimum (0)
+            0x25, 0x04, // Logical Maximum (4)
+            0x75, 0x08, // Report Size (8)
+            0x95, 0x01, // Report Count (1)
+            0x81, 0x02, // Input (Data, Variable, Absolute)
+            0x09, 0x02, // Usage (0x02)
+            0x26, 0xff, 0x00, // Logical Maximum (255)
+            0x95, 0x04, // Report Count (4)
+            0x81, 0x02, // Input (Data, Variable, Absolute)
+            0xc0, // End Collection
+        ];
+        assert_eq!(CustomCountedSamples::desc(), expected);
+
+        let report = CustomCountedSamples {
+            count: 2,
+            samples: [0x12, 0x34, 0, 0],
+        };
+        assert_eq!(report.to_array(), [0x02, 0x12, 0x34, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn test_in_endpoint_type() {
+        use crate::hid_class::{HIDClass, HidClassSettings};
+        use crate::test_bus::{test_alloc, test_device};
+        use usb_device::endpoint::{
+            EndpointAddress, EndpointType, IsochronousSynchronizationType, IsochronousUsageType,
+        };
+
+        let alloc = test_alloc();
+        let hid = HIDClass::new_with_settings(
+            &alloc,
+            MouseReport::desc(),
+            10,
+            HidClassSettings::default(),
+        );
+        let dev = test_device(&alloc);
+        for addr in [0x01, 0x81] {
+            let ep = dev.bus().endpoint(EndpointAddress::from(addr));
+            assert_eq!(ep.ep_type, Some(EndpointType::Interrupt));
+        }
+        assert_eq!(hid.push_raw_input(&[0x01, 0x02]), Ok(2));
+        assert_eq!(dev.bus().host_read(1), Some(std::vec![0x01, 0x02]));
+
+        let isochronous = EndpointType::Isochronous {
+            synchronization: IsochronousSynchronizationType::Asynchronous,
+            usage: IsochronousUsageType::Data,
+        };
+        let alloc = test_alloc();
+        let _hid = HIDClass::new_ep_in_with_settings(
+            &alloc,
+            MouseReport::desc(),
+            1,
+            HidClassSettings::default().with_in_endpoint_type(isochronous),
+        );
+        let dev = test_device(&alloc);
+        let ep = dev.bus().endpoint(EndpointAddress::from(0x81));
+        assert_eq!(ep.ep_type, Some(isochronous));
+    }
+
+    #[gen_hid_descriptor(
+        (collection = APPLICATION, usage_page = VENDOR_DEFINED_START, usage = 0x01) = {
+            #[packed_bits 12] flags=input;
+        }
+    )]
+    #[allow(dead_code)]
+    struct CustomPackedArray {
+        flags: [u8; 2],
+    }
+
+    #[test]
+    fn test_packed_bits_accessors() {
+        let mut report = MouseReport {
+            buttons: 0,
+            x: 0,
+            y: 0,
+            wheel: 0,
+            pan: 0,
+        };
+        report.set_buttons_bit(0, true);
+        report.set_buttons_bit(2, true);
+        assert_eq!({ report.buttons }, 0b101);
+        assert!(report.buttons_bit(0) && !report.buttons_bit(1) && report.buttons_bit(2));
+        report.set_buttons_bit(0, false);
+        assert_eq!({ report.buttons }, 0b100);
+
+        // Bits beyond the packed bits are ignored.
+        report.set_buttons_bit(8, true);
+        assert_eq!({ report.buttons }, 0b100);
+        assert!(!report.buttons_bit(8));
+
+        let mut report = CustomPackedArray { flags: [0; 2] };
+        report.set_flags_bit(9, true);
+        report.set_flags_bit(12, true);
+        assert_eq!({ report.flags }, [0x00, 0x02]);
+        assert!(report.flags_bit(9));
+        assert!(!report.flags_bit(12));
+    }
+
+    #[gen_hid_descriptor(
+        (collection = APPLICATION, usage_page = CONSUMER, usage = CONSUMER_CONTROL) = {
+            (usage = PLAY_PAUSE, usage = SCAN_NEXT_TRACK, usage = SCAN_PREVIOUS_TRACK) = {
+                #[packed_bits 3] buttons=input;
+            };
+        }
+    )]
+    #[allow(dead_code)]
+    struct CustomMediaRemote {
+        buttons: u8,
+    }
+
+    #[test]
+    fn test_consumer_named_controls() {
+        let expected: &[u8] = &[
+            0x05, 0x0c, // Usage Page (Consumer)
+            0x09, 0x01, // Usage (Consumer Control)
+            0xa1, 0x01, // Collection (Application)
+            0x09, 0xcd, // Usage (Play/Pause)
+            0x09, 0xb5, // Usage (Scan Next Track)
+            0x09, 0xb6, // Usage (Scan Previous Track)
+            0x15, 0x00, // Logical Minimum (0)
+            0x25, 0x01, // Logical Maximum (1)
+            0x75, 0x01, // Report Size (1)
+            0x95, 0x03, // Report Count (3)
+            0x81, 0x02, // Input (Data, Variable, Absolute)
+            0x95, 0x05, // Report Count (5)
+            0x81, 0x03, // Input (Constant, Variable, Absolute)
+            0xc0, // End Collection
+        ];
+        assert_eq!(CustomMediaRemote::desc(), expected);
+
+        let mut report = CustomMediaRemote { buttons: 0 };
+        report.set_buttons_bit(1, true); // Scan Next Track
+        assert_eq!(report.to_array(), [0x02]);
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        use crate::descriptor::fingerprint;
+
+        // Known FNV-1a test vectors.
+        assert_eq!(fingerprint(&[]), 0x811c9dc5);
+        assert_eq!(fingerprint(b"a"), 0xe40c292c);
+
+        // Update this fingerprint only when changing the keyboard descriptor on purpose.
+        assert_eq!(fingerprint(KeyboardReport::desc()), 0x2e209ce4);
+    }
+
+    #[test]
+    fn test_pull_raw_output_short_read() {
+        use crate::hid_class::HIDClass;
+        use crate::test_bus::{test_alloc, test_device};
+        use usb_device::UsbError;
+
+        let alloc = test_alloc();
+        let hid = HIDClass::new_ep_out(&alloc, KeyboardReport::desc(), 10);
+        let dev = test_device(&alloc);
+
+        dev.bus().host_write(1, &[0x01, 0x02], false);
+        let mut buf = [0xFFu8; 8];
+        assert_eq!(hid.pull_raw_output(&mut buf), Ok(2));
+        assert_eq!(buf[..2], [0x01, 0x02]);
+        assert_eq!(hid.pull_raw_output(&mut buf), Err(UsbError::WouldBlock));
+
+        dev.bus().host_write(1, &[0x01, 0x02, 0x03], false);
+        assert_eq!(
+            hid.pull_raw_output(&mut buf[..2]),
+            Err(UsbError::BufferOverflow)
+        );
+    }
+
+    #[test]
+    fn test_gaze_descriptor() {
+        let expected = &[
+            0x05, 0x12, // Usage Page (Eye and Head Trackers)
+            0x09, 0x01, // Usage (Eye Tracker)
+            0xa1, 0x01, // Collection (Application)
+            0x09, 0x24, // Usage (Gaze Point)
+            0xa1, 0x00, // Collection (Physical)
+            0x09, 0x21, // Usage (Position X)
+            0x17, 0x01, 0x00, 0x00, 0x80, // Logical Minimum (-2147483647)
+            0x27, 0xff, 0xff, 0xff, 0x7f, // Logical Maximum (2147483647)
+            0x65, 0x11, // Unit (SI Linear: Centimeter)
+            0x55, 0x0c, // Unit Exponent (-4), ie: micrometers
+            0x75, 0x20, // Report Size (32)
+            0x95, 0x01, // Report Count (1)
+            0x81, 0x02, // Input (Data, Variable, Absolute)
+            0x09, 0x22, // Usage (Position Y)
+            0x81, 0x02, // Input (Data, Variable, Absolute)
+            0xc0, // End Collection
+            0xc0, // End Collection
+        ];
+        assert_eq!(GazeReport::desc(), expected);
+
+        let report = GazeReport { x: 1000, y: -1 };
+        assert_eq!(
+            report.to_array(),
+            [0xE8, 0x03, 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFF]
+        );
+    }
+
+    #[gen_hid_descriptor(
+        byte_order(big),
+        (collection = APPLICATION, usage_page = VENDOR_DEFINED_START, usage = 0x01) = {
+            a=input;
+            b=input;
+            c=input;
+            #[packed_bits 12] flags=input;
+            d=output;
+        }
+    )]
+    #[allow(dead_code)]
+    struct CustomBigEndian {
+        a: u8,
+        b: u16,
+        c: i32,
+        flags: u16,
+        d: u16,
+    }
+
+    #[test]
+    fn test_big_endian() {
+        let report = CustomBigEndian {
+            a: 0x01,
+            b: 0x0203,
+            c: -2,
+            flags: 0x0405,
+            d: 0,
+        };
+        assert_eq!(
+            report.to_array(),
+            [0x01, 0x02, 0x03, 0xFF, 0xFF, 0xFF, 0xFE, 0x05, 0x04]
+        );
+
+        let report = CustomBigEndian::try_from(&[0x12, 0x34][..]).unwrap();
+        assert_eq!({ report.d }, 0x1234);
+    }
+
+    #[test]
+    fn test_push_input_if_changed() {
+        use crate::hid_class::HIDClass;
+        use crate::test_bus::{test_alloc, test_device};
+
+        let alloc = test_alloc();
+        let mut hid: HIDClass<_, 0, 0, 64> =
+            HIDClass::new_ep_in(&alloc, MouseReport::desc(), 10).with_storage();
+        let dev = test_device(&alloc);
+
+        let mut report = MouseReport {
+            buttons: 0x01,
+            x: 0,
+            y: 0,
+            wheel: 0,
+            pan: 0,
+        };
+        assert_eq!(hid.push_input_if_changed(&report), Ok(5));
+        assert_eq!(dev.bus().host_read(1), Some(std::vec![0x01, 0, 0, 0, 0]));
+        assert_eq!(hid.push_input_if_changed(&report), Ok(0));
+        assert_eq!(dev.bus().host_read(1), None);
+
+        report.buttons = 0;
+        assert_eq!(hid.push_input_if_changed(&report), Ok(5));
+        assert_eq!(dev.bus().host_read(1), Some(std::vec![0, 0, 0, 0, 0]));
+    }
+
+    #[test]
+    fn test_last_input() {
+        use crate::hid_class::HIDClass;
+        use crate::test_bus::{test_alloc, test_device};
+
+        let alloc = test_alloc();
+        let mut hid: HIDClass<_, 0, 0, 64> =
+            HIDClass::new_ep_in(&alloc, MouseReport::desc(), 10).with_storage();
+        let dev = test_device(&alloc);
+        assert_eq!(hid.last_input(), None);
+
+        let report = MouseReport {
+            buttons: 0x01,
+            x: 5,
+            y: -5,
+            wheel: 0,
+            pan: 0,
+        };
+        assert_eq!(hid.push_input(&report), Ok(5));
+        assert_eq!(
+            hid.last_input().as_deref(),
+            Some(&[0x01, 0x05, 0xFB, 0, 0][..])
+        );
+        // A report which isn't written leaves the last one in place.
+        assert_eq!(
+            hid.push_raw_input(&[0x02, 0, 0, 0, 0]),
+            Err(UsbError::WouldBlock)
+        );
+        assert_eq!(
+            hid.last_input().as_deref(),
+            Some(&[0x01, 0x05, 0xFB, 0, 0][..])
+        );
+
+        assert_eq!(
+            dev.bus().host_read(1),
+            Some(std::vec![0x01, 0x05, 0xFB, 0, 0])
+        );
+        assert_eq!(hid.push_raw_input(&[0x02, 0, 0, 0, 0]), Ok(5));
+        assert_eq!(hid.last_input().as_deref(), Some(&[0x02, 0, 0, 0, 0][..]));
+        // Identical to the report written by push_raw_input, so it is skipped.
+        let report = MouseReport {
+            buttons: 0x02,
+            x: 0,
+            y: 0,
+            ..report
+        };
+        assert_eq!(hid.push_input_if_changed(&report), Ok(0));
+    }
+
+    #[gen_hid_descriptor(
+        (collection = APPLICATION, usage_page = VENDOR_DEFINED_START, usage = 0x01, report_id = 0x01) = {
+            status=input;
+            #[item_settings data,variable,absolute] counter=input;
+        }
+    )]
+    struct VendorWithId {
+        status: u8,
+        counter: u16,
+    }
+
+    #[test]
+    fn test_vendor_single_report_id() {
+        use crate::hid_class::HIDClass;
+        use crate::test_bus::{test_alloc, test_device};
+
+        assert_eq!(VendorWithId::REPORT_ID, 1);
+        assert_eq!(VendorWithId::MAX_INPUT_LEN, 4);
+
+        let alloc = test_alloc();
+        let hid = HIDClass::new_ep_in(&alloc, VendorWithId::desc(), 10);
+        let dev = test_device(&alloc);
+
+        let report = VendorWithId {
+            status: 0xAA,
+            counter: 0x1234,
+        };
+        assert_eq!(hid.push_input(&report), Ok(4));
+        assert_eq!(
+            dev.bus().host_read(1),
+            Some(std::vec![0x01, 0xAA, 0x34, 0x12])
+        );
+        assert_eq!(report.to_array(), [0x01, 0xAA, 0x34, 0x12]);
+    }
+
+    #[test]
+    fn test_system_control_release() {
+        use crate::descriptor::parser::items;
+        use usbd_hid_descriptors::{GlobalItemKind, ItemType};
+
+        let release = SystemControlReport::release();
+        assert_eq!({ release.usage_id }, 0);
+
+        // The release value must fall outside the logical range of the array item.
+        let min = items(SystemControlReport::desc())
+            .map(|item| item.unwrap())
+            .find(|item| {
+                item.typ == ItemType::Global.into() && item.tag == GlobalItemKind::LogicalMin.into()
+            })
+            .unwrap()
+            .signed_value();
+        assert!((release.usage_id as i32) < min);
+
+        let mut buf = [0xFFu8; 1];
+        assert_eq!(release.serialize_into(&mut buf), Ok(1));
+        assert_eq!(buf, [0]);
+    }
+
+    #[test]
+    fn test_keyboard_layout_usage() {
+        use crate::descriptor::LayoutKey;
+
+        assert_eq!(
+            KeyboardLayout::Jis.usage(LayoutKey::Yen),
+            Some(KeyboardUsage::KeyboardInternational3)
+        );
+        assert_eq!(
+            KeyboardLayout::Jis.usage(LayoutKey::Ro),
+            Some(KeyboardUsage::KeyboardInternational1)
+        );
+        assert_eq!(
+            KeyboardLayout::Korean.usage(LayoutKey::Hanja),
+            Some(KeyboardUsage::KeyboardLANG2)
+        );
+        assert_eq!(KeyboardLayout::Ansi.usage(LayoutKey::Yen), None);
+        assert_eq!(KeyboardLayout::Jis.usage(LayoutKey::IsoBackslash), None);
+
+        let report =
+            KeyboardReport::from_keys(&[KeyboardLayout::Jis.usage(LayoutKey::Henkan).unwrap()])
+                .unwrap();
+        assert_eq!({ report.keycodes }, [0x8A, 0, 0, 0, 0, 0]);
+    }
+
+    // This should generate the following descriptor:
+    // 0x06, 0x00, 0xFF,  // Usage Page (Vendor Defined 0xFF00)
+    // 0x09, 0x01,        // Usage (0x01)
+    // 0xA1, 0x01,        // Collection (Application)
+    // 0x15, 0x00,        //   Logical Minimum (0)
+    // 0x25, 0x64,        //   Logical Maximum (100)
+    // 0x75, 0x08,        //   Report Size (8)
+    // 0x95, 0x01,        //   Report Count (1)
+    // 0x81, 0x02,        //   Input (Data,Var,Abs,No Wrap,Linear,Preferred State,No Null Position)
+    // 0xC0,              // End Collection
+    #[gen_hid_descriptor(
+        (collection = APPLICATION, usage_page = VENDOR_DEFINED_START, usage = 0x01) = {
+            #[percent] battery=input;
+        }
+    )]
+    #[allow(dead_code)]
+    struct CustomPercent {
+        battery: u8,
+    }
+
+    #[test]
+    fn test_percent() {
+        let expected: &[u8] = &[
+            0x06, 0x00, 0xFF, 0x09, 0x01, 0xA1, 0x01, 0x15, 0x00, 0x25, 0x64, 0x75, 0x08, 0x95,
+            0x01, 0x81, 0x02, 0xC0,
+        ];
+        assert_eq!(CustomPercent::desc(), expected);
+    }
+
+    #[test]
+    fn test_collection_usage_scoping() {
+        use crate::descriptor::describe;
+
+        // The application and collection usages precede their collection items, and
+        // each item's usages directly precede its input item.
+        let expected = "\
+Usage Page (Generic Desktop)
+Usage (0x02)
+Collection (Application)
+  Usage (0x01)
+  Collection (Physical)
+    Usage Page (Button)
+    Usage Minimum (0x01)
+    Usage Maximum (0x08)
+    Logical Minimum (0)
+    Logical Maximum (1)
+    Report Size (1)
+    Report Count (8)
+    Input (Data,Var,Abs)
+    Usage Page (Generic Desktop)
+    Usage (0x30)
+    Logical Minimum (-127)
+    Logical Maximum (127)
+    Report Size (8)
+    Report Count (1)
+    Input (Data,Var,Rel)
+    Usage (0x31)
+    Input (Data,Var,Rel)
+    Usage (0x38)
+    Input (Data,Var,Rel)
+    Usage Page (Consumer)
+    Usage (0x238)
+    Input (Data,Var,Rel)
+  End Collection
+End Collection
+";
+        assert_eq!(describe(MouseReport::desc()), expected);
+
+        let expected = "\
+Usage Page (Vendor Defined 0xFF00)
+Usage (0x01)
+Collection (Application)
+  Usage (0x02)
+  Usage Minimum (0x10)
+  Usage Maximum (0x12)
+  Collection (Physical)
+    Logical Minimum (0)
+    Logical Maximum (1)
+    Report Size (1)
+    Report Count (3)
+    Input (Data,Var,Abs)
+    Report Count (5)
+    Input (Const,Var,Abs)
+  End Collection
+End Collection
+";
+        assert_eq!(describe(CustomCollectionUsageRange::desc()), expected);
+    }
+
+    // The usage range of a group which also declares a collection is emitted before the
+    // collection item, like its usage.
+    #[gen_hid_descriptor(
+        (collection = APPLICATION, usage_page = VENDOR_DEFINED_START, usage = 0x01) = {
+            (collection = PHYSICAL, usage = 0x02, usage_min = 0x10, usage_max = 0x12) = {
+                #[packed_bits 3] buttons=input;
+            };
+        }
+    )]
+    #[allow(dead_code)]
+    struct CustomCollectionUsageRange {
+        buttons: u8,
+    }
+
+    #[test]
+    fn test_report_id_consts() {
+        assert_eq!(CustomPackedBits::REPORT_ID, 1);
+        assert_eq!(CustomPackedBits::REPORT_IDS, &[1]);
+        assert_eq!(CustomMultiReport::REPORT_IDS, &[1, 2]);
+    }
+
+    #[gen_hid_descriptor(
+        (collection = APPLICATION, usage_page = VENDOR_DEFINED_START, usage = 0x01) = {
+            f1=input;
+        }
+    )]
+    #[quirks(implicit_logical_min)]
+    #[allow(dead_code)]
+    struct CustomImplicitLogicalMin {
+        f1: u8,
+    }
+
+    #[gen_hid_descriptor(
+        (collection = APPLICATION, usage_page = VENDOR_DEFINED_START, usage = 0x01) = {
+            f1=input;
+        }
+    )]
+    #[allow(dead_code)]
+    struct CustomExplicitLogicalMin {
+        f1: u8,
+    }
+
+    #[test]
+    fn test_implicit_logical_min() {
+        let expected: &[u8] = &[
+            0x06, 0x00, 0xFF, // Usage Page (Vendor Defined 0xFF00)
+            0x09, 0x01, // Usage (0x01)
+            0xA1, 0x01, // Collection (Application)
+            0x26, 0xFF, 0x00, //   Logical Maximum (255)
+            0x75, 0x08, //   Report Size (8)
+            0x95, 0x01, //   Report Count (1)
+            0x81, 0x02, //   Input (Data,Var,Abs)
+            0xC0, // End Collection
+        ];
+        assert_eq!(CustomImplicitLogicalMin::desc(), expected);
+        assert_eq!(
+            CustomImplicitLogicalMin::desc().len() + 2,
+            CustomExplicitLogicalMin::desc().len()
+        );
+    }
+
+    #[test]
+    fn test_set_report_multi_packet() {
+        use crate::hid_class::HIDClass;
+        use crate::test_bus::*;
+        use usb_device::device::{UsbDeviceBuilder, UsbVidPid};
+        use usb_device::UsbError;
+
+        let alloc = test_alloc();
+        let mut hid = HIDClass::new_control_only(&alloc, MouseReport::desc());
+        let mut dev = UsbDeviceBuilder::new(&alloc, UsbVidPid(0x1209, 0x0001))
+            .max_packet_size_0(64)
+            .unwrap()
+            .build();
+
+        // Spans several 64 byte data packets.
+        let len = if cfg!(feature = "control-buffer-256") {
+            200
+        } else {
+            100
+        };
+        let data: std::vec::Vec<u8> = (0..len).map(|i| i as u8).collect();
+        set_report(&mut dev, &mut hid, ReportType::Feature, 0, &data).unwrap();
+        let mut buf = [0u8; 256];
+        let info = hid.pull_raw_report(&mut buf).unwrap();
+        assert_eq!(info.len, len);
+        assert_eq!(&buf[..len], &data[..]);
+
+        let data = std::vec![0u8; hid.control_buffer_len() + 1];
+        assert_eq!(
+            set_report(&mut dev, &mut hid, ReportType::Feature, 0, &data),
+            Err(UsbError::InvalidState)
+        );
+    }
+
+    // This should generate the following descriptor:
+    // 0x05, 0x20,              // Usage Page (Sensor)
+    // 0x09, 0x73,              // Usage (Motion: Accelerometer 3D)
+    // 0xA1, 0x01,              // Collection (Application)
+    // 0x05, 0x01,              //   Usage Page (Generic Desktop)
+    // 0x09, 0x30,              //   Usage (X)
+    // 0x09, 0x31,              //   Usage (Y)
+    // 0x09, 0x32,              //   Usage (Z)
+    // 0x16, 0x01, 0x80,              //   Logical Minimum (-32767)
+    // 0x26, 0xFF, 0x7F,        //   Logical Maximum (32767)
+    // 0x75, 0x10,              //   Report Size (16)
+    // 0x95, 0x03,              //   Report Count (3)
+    // 0x81, 0x02,              //   Input (Data,Var,Abs)
+    // 0xC0,                    // End Collection
+    #[gen_hid_descriptor(
+        (collection = APPLICATION, usage_page = SENSOR, usage = 0x73) = {
+            (usage_page = GENERIC_DESKTOP, usage = X, usage = Y, usage = Z) = {
+                accel=input;
+            };
+        }
+    )]
+    #[allow(dead_code)]
+    struct CustomAccelerometer {
+        accel: [i16; 3],
+    }
+
+    #[test]
+    fn test_array_element_usages() {
+        let expected: &[u8] = &[
+            0x05, 0x20, 0x09, 0x73, 0xA1, 0x01, 0x05, 0x01, 0x09, 0x30, 0x09, 0x31, 0x09, 0x32,
+            0x16, 0x01, 0x80, 0x26, 0xFF, 0x7F, 0x75, 0x10, 0x95, 0x03, 0x81, 0x02, 0xC0,
+        ];
+        assert_eq!(CustomAccelerometer::desc(), expected);
+
+        let report = CustomAccelerometer {
+            accel: [1, -2, 0x0304],
+        };
+        assert_eq!(report.to_array(), [0x01, 0x00, 0xFE, 0xFF, 0x04, 0x03]);
+    }
+
+    #[test]
+    fn test_hide_out_endpoint() {
+        use crate::hid_class::{HIDClass, HidClassSettings};
+        use crate::test_bus::*;
+        use usb_device::endpoint::{EndpointAddress, EndpointType};
+
+        let alloc = test_alloc();
+        let mut hid = HIDClass::new_with_settings(
+            &alloc,
+            KeyboardReport::desc(),
+            10,
+            HidClassSettings::default().with_hide_out_endpoint(true),
+        );
+        let mut dev = test_device(&alloc);
+
+        // The endpoint is still allocated.
+        let ep = dev.bus().endpoint(EndpointAddress::from(0x01));
+        assert_eq!(ep.ep_type, Some(EndpointType::Interrupt));
+
+        let config = get_configuration_descriptor(&mut dev, &mut hid).unwrap();
+        let descriptors = split_descriptors(&config);
+        let (_, interface) = descriptors
+            .iter()
+            .find(|(typ, _)| *typ == DESC_TYPE_INTERFACE)
+            .unwrap();
+        assert_eq!(interface[4], 1); // bNumEndpoints
+        let endpoints: std::vec::Vec<u8> = descriptors
+            .iter()
+            .filter(|(typ, _)| *typ == DESC_TYPE_ENDPOINT)
+            .map(|(_, ep)| ep[2]) // bEndpointAddress
+            .collect();
+        assert_eq!(endpoints, [0x81]);
+    }
+
+    // This should generate the following descriptor:
+    // 0x06, 0x00, 0xFF,  // Usage Page (Vendor Defined 0xFF00)
+    // 0x09, 0x01,        // Usage (0x01)
+    // 0xA1, 0x01,        // Collection (Application)
+    // 0x55, 0x0F,        //   Unit Exponent (-1)
+    // 0x16, 0x01, 0x80,              //   Logical Minimum (-32767)
+    // 0x26, 0xFF, 0x7F,  //   Logical Maximum (32767)
+    // 0x75, 0x10,        //   Report Size (16)
+    // 0x95, 0x01,        //   Report Count (1)
+    // 0x81, 0x02,        //   Input (Data,Var,Abs)
+    // 0x55, 0x02,        //   Unit Exponent (2)
+    // 0x15, 0x00,        //   Logical Minimum (0)
+    // 0x27, 0xFF, 0xFF, 0x00, 0x00,  //   Logical Maximum (65535)
+    // 0x81, 0x02,        //   Input (Data,Var,Abs)
+    // 0x81, 0x02,        //   Input (Data,Var,Abs)
+    // 0xC0,              // End Collection
+    #[gen_hid_descriptor(
+        (collection = APPLICATION, usage_page = VENDOR_DEFINED_START, usage = 0x01) = {
+            #[unit_exponent -1] temperature=input;
+            #[unit_exponent 2] pressure=input;
+            #[unit_exponent 2] pressure_max=input;
+        }
+    )]
+    #[allow(dead_code)]
+    struct CustomUnitExponents {
+        temperature: i16,
+        pressure: u16,
+        pressure_max: u16,
+    }
+
+    #[test]
+    fn test_item_unit_exponent() {
+        let expected: &[u8] = &[
+            0x06, 0x00, 0xFF, 0x09, 0x01, 0xA1, 0x01, 0x55, 0x0F, 0x16, 0x01, 0x80, 0x26, 0xFF,
+            0x7F, 0x75, 0x10, 0x95, 0x01, 0x81, 0x02, 0x55, 0x02, 0x15, 0x00, 0x27, 0xFF, 0xFF,
+            0x00, 0x00, 0x81, 0x02, 0x81, 0x02, 0xC0,
+        ];
+        assert_eq!(CustomUnitExponents::desc(), expected);
+    }
+
+    // This should generate the following descriptor:
+    // 0x06, 0x00, 0xFF,  // Usage Page (Vendor Defined 0xFF00)
+    // 0x09, 0x01,        // Usage (0x01)
+    // 0xA1, 0x01,        // Collection (Application)
+    // 0x15, 0x00,        //   Logical Minimum (0)
+    // 0x26, 0xFF, 0x00,  //   Logical Maximum (255)
+    // 0x75, 0x08,        //   Report Size (8)
+    // 0x95, 0x01,        //   Report Count (1)
+    // 0x81, 0x02,        //   Input (Data,Var,Abs)
+    // 0x27, 0xFF, 0xFF, 0x00, 0x00,  //   Logical Maximum (65535)
+    // 0x75, 0x10,        //   Report Size (16)
+    // 0x81, 0x02,        //   Input (Data,Var,Abs)
+    // 0x75, 0x08,        //   Report Size (8)
+    // 0x95, 0x05,        //   Report Count (5)
+    // 0x81, 0x03,        //   Input (Const,Var,Abs)
+    // 0xC0,              // End Collection
+    #[gen_hid_descriptor(
+        pad_to(8),
+        (collection = APPLICATION, usage_page = VENDOR_DEFINED_START, usage = 0x01) = {
+            status=input;
+            value=input;
+        }
+    )]
+    #[allow(dead_code)]
+    struct CustomPadTo {
+        status: u8,
+        value: u16,
+    }
+
+    #[test]
+    fn test_pad_to() {
+        let expected: &[u8] = &[
+            0x06, 0x00, 0xFF, 0x09, 0x01, 0xA1, 0x01, 0x15, 0x00, 0x26, 0xFF, 0x00, 0x75, 0x08,
+            0x95, 0x01, 0x81, 0x02, 0x27, 0xFF, 0xFF, 0x00, 0x00, 0x75, 0x10, 0x81, 0x02, 0x75,
+            0x08, 0x95, 0x05, 0x81, 0x03, 0xC0,
+        ];
+        assert_eq!(CustomPadTo::desc(), expected);
+        assert_eq!(CustomPadTo::MAX_INPUT_LEN, 8);
+
+        let report = CustomPadTo {
+            status: 0x01,
+            value: 0x0302,
+        };
+        assert_eq!(report.to_array(), [0x01, 0x02, 0x03, 0, 0, 0, 0, 0]);
+
+        let mut buf = [0xFFu8; 7];
+        assert_eq!(
+            report.serialize_into(&mut buf),
+            Err(usb_device::UsbError::BufferOverflow)
+        );
+
+        // Reports assembled with a report ID are padded the same way.
+        let mut buf = [0xFFu8; 10];
+        assert_eq!(report.serialize_with_id(0x04, &mut buf), Ok(9));
+        assert_eq!(buf, [0x04, 0x01, 0x02, 0x03, 0, 0, 0, 0, 0, 0xFF]);
+        assert_eq!(
+            report.serialize_with_id(0x04, &mut buf[..8]),
+            Err(usb_device::UsbError::BufferOverflow)
+        );
+    }
+
+    #[test]
+    fn test_endpoint_descriptors() {
+        use crate::hid_class::{HIDClass, HidClassSettings, PollInterval};
+        use crate::test_bus::*;
+
+        let endpoints = |poll_ms: u8, settings: HidClassSettings| {
+            let alloc = test_alloc();
+            let mut hid =
+                HIDClass::new_with_settings(&alloc, KeyboardReport::desc(), poll_ms, settings);
+            let mut dev = test_device(&alloc);
+            let config = get_configuration_descriptor(&mut dev, &mut hid).unwrap();
+            split_descriptors(&config)
+                .into_iter()
+                .filter(|(typ, _)| *typ == DESC_TYPE_ENDPOINT)
+                .map(|(_, ep)| ep.to_vec())
+                .collect::<std::vec::Vec<_>>()
+        };
+
+        // bLength, bDescriptorType, bEndpointAddress, bmAttributes (interrupt),
+        // wMaxPacketSize, bInterval
+        assert_eq!(
+            endpoints(10, HidClassSettings::default()),
+            [
+                [7, DESC_TYPE_ENDPOINT, 0x01, 0x03, 64, 0, 10],
+                [7, DESC_TYPE_ENDPOINT, 0x81, 0x03, 64, 0, 10],
+            ]
+        );
+
+        let settings = HidClassSettings::default()
+            .with_poll_interval(PollInterval::FullSpeedMs(2))
+            .with_max_packet_size(8);
+        assert_eq!(
+            endpoints(10, settings),
+            [
+                [7, DESC_TYPE_ENDPOINT, 0x01, 0x03, 8, 0, 2],
+                [7, DESC_TYPE_ENDPOINT, 0x81, 0x03, 8, 0, 2],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_keyboard_with_media() {
+        use crate::hid_class::HIDClass;
+        use crate::test_bus::{test_alloc, test_device};
+
+        assert_eq!(KeyboardWithMediaReport::REPORT_IDS, &[1, 2]);
+        let desc = KeyboardWithMediaReport::desc();
+        assert_eq!(
+            crate::descriptor::report_ids::<4>(desc).unwrap().as_slice(),
+            &[1, 2]
+        );
+
+        let alloc = test_alloc();
+        let hid = HIDClass::new(&alloc, desc, 10);
+        let dev = test_device(&alloc);
+
+        let mut report = KeyboardWithMediaReport::default();
+        report.modifier = 0x02;
+        report.keycodes[0] = KeyboardUsage::KeyboardAa as u8;
+        report.media_usage_id = MediaKey::PlayPause.into();
+
+        assert_eq!(hid.push_input(&report.keyboard()), Ok(9));
+        assert_eq!(
+            dev.bus().host_read(1),
+            Some(std::vec![0x01, 0x02, 0x00, 0x04, 0, 0, 0, 0, 0])
+        );
+        assert_eq!(hid.push_input(&report.media()), Ok(3));
+        assert_eq!(dev.bus().host_read(1), Some(std::vec![0x02, 0xCD, 0x00]));
+    }
+
+    #[test]
+    fn test_high_res_scroll_mouse() {
+        use crate::descriptor::{describe, AsFeatureReport};
+
+        // The layout documented in "Enhanced Wheel Support in Windows": each Resolution
+        // Multiplier shares a logical collection with the usage it applies to.
+        let expected = "\
+Usage Page (Generic Desktop)
+Usage (0x02)
+Collection (Application)
+  Usage (0x02)
+  Collection (Logical)
+    Usage (0x01)
+    Collection (Physical)
+      Usage Page (Button)
+      Usage Minimum (0x01)
+      Usage Maximum (0x08)
+      Logical Minimum (0)
+      Logical Maximum (1)
+      Report Size (1)
+      Report Count (8)
+      Input (Data,Var,Abs)
+      Usage Page (Generic Desktop)
+      Usage (0x30)
+      Logical Minimum (-127)
+      Logical Maximum (127)
+      Report Size (8)
+      Report Count (1)
+      Input (Data,Var,Rel)
+      Usage (0x31)
+      Input (Data,Var,Rel)
+      Collection (Logical)
+        Usage (0x48)
+        Logical Minimum (0)
+        Logical Maximum (1)
+        Physical Minimum (1)
+        Physical Maximum (4)
+        Report Size (1)
+        Feature (Data,Var,Abs)
+        Report Count (7)
+        Feature (Const,Var,Abs)
+        Usage (0x38)
+        Logical Minimum (-127)
+        Logical Maximum (127)
+        Physical Minimum (0)
+        Physical Maximum (0)
+        Report Size (8)
+        Report Count (1)
+        Input (Data,Var,Rel)
+      End Collection
+      Collection (Logical)
+        Usage (0x48)
+        Logical Minimum (0)
+        Logical Maximum (1)
+        Physical Minimum (1)
+        Physical Maximum (4)
+        Report Size (1)
+        Feature (Data,Var,Abs)
+        Report Count (7)
+        Feature (Const,Var,Abs)
+        Usage Page (Consumer)
+        Usage (0x238)
+        Logical Minimum (-127)
+        Logical Maximum (127)
+        Physical Minimum (0)
+        Physical Maximum (0)
+        Report Size (8)
+        Report Count (1)
+        Input (Data,Var,Rel)
+      End Collection
+    End Collection
+  End Collection
+End Collection
+";
+        assert_eq!(describe(HighResScrollMouseReport::desc()), expected);
+
+        let report = HighResScrollMouseReport {
+            buttons: 0x01,
+            x: -1,
+            y: 2,
+            wheel_multiplier: 0,
+            wheel: -4,
+            pan_multiplier: 0,
+            pan: 4,
+        };
+        let mut buf = [0u8; 8];
+        assert_eq!(report.serialize_into(&mut buf), Ok(5));
+        assert_eq!(buf[..5], [0x01, 0xFF, 0x02, 0xFC, 0x04]);
+        assert_eq!(report.wheel_multipliers(), (1, 1));
+
+        // The host enables high-resolution scrolling of the wheel only.
+        let feature = HighResScrollMouseReport::try_from_feature(&[0x01, 0x00]).unwrap();
+        assert_eq!(feature.wheel_multipliers(), (4, 1));
+        let feature = HighResScrollMouseReport::try_from_feature(&[0x01, 0x01]).unwrap();
+        assert_eq!(feature.wheel_multipliers(), (4, 4));
+        assert_eq!(feature.serialize_feature_into(&mut buf), Ok(2));
+        assert_eq!(buf[..2], [0x01, 0x01]);
+    }
+
+    #[gen_hid_descriptor(
+        (collection = APPLICATION, usage_page = GENERIC_DESKTOP, usage = KEYBOARD, report_id = 0x01) = {
+            (usage_page = KEYBOARD, usage_min = 0xE0, usage_max = 0xE7) = {
+                #[packed_bits 8] #[item_settings data,variable,absolute] modifier=input;
+            };
+            (usage_page = KEYBOARD, usage_min = 0x00, usage_max = 0xDD) = {
+                #[item_settings data,array,absolute] keycodes=input;
+            };
+        },
+        (collection = APPLICATION, usage_page = GENERIC_DESKTOP, usage = SYSTEM_CONTROL, report_id = 0x02) = {
+            (usage_min = 0x81, usage_max = 0xB7, logical_min = 1) = {
+                #[item_settings data,array,absolute,not_null] system_usage_id=input;
+            };
+        }
+    )]
+    #[allow(dead_code)]
+    struct CustomKeyboardWithSystemControl {
+        modifier: u8,
+        keycodes: [u8; 6],
+        system_usage_id: u8,
+    }
+
+    #[test]
+    fn test_multiple_top_level_collections() {
+        use crate::descriptor::describe;
+        use crate::hid_class::HIDClass;
+        use crate::test_bus::{test_alloc, test_device};
+
+        let desc = describe(CustomKeyboardWithSystemControl::desc());
+        let top_level: std::vec::Vec<&str> = desc.lines().filter(|l| !l.starts_with(' ')).collect();
+        assert_eq!(
+            top_level,
+            [
+                "Usage Page (Generic Desktop)",
+                "Usage (0x06)",
+                "Report ID (1)",
+                "Collection (Application)",
+                "End Collection",
+                "Usage Page (Generic Desktop)",
+                "Usage (0x80)",
+                "Report ID (2)",
+                "Collection (Application)",
+                "End Collection",
+            ]
+        );
+
+        let alloc = test_alloc();
+        let hid = HIDClass::new_ep_in(&alloc, CustomKeyboardWithSystemControl::desc(), 10);
+        let dev = test_device(&alloc);
+
+        let report = CustomKeyboardWithSystemControl {
+            modifier: 0x01,
+            keycodes: [0x04, 0, 0, 0, 0, 0],
+            system_usage_id: crate::descriptor::SystemControlKey::Sleep as u8,
+        };
+        assert_eq!(
+            hid.push_input(&CustomKeyboardWithSystemControlId1(&report)),
+            Ok(8)
+        );
+        assert_eq!(
+            dev.bus().host_read(1),
+            Some(std::vec![0x01, 0x01, 0x04, 0, 0, 0, 0, 0])
+        );
+        assert_eq!(
+            hid.push_input(&CustomKeyboardWithSystemControlId2(&report)),
+            Ok(2)
+        );
+        assert_eq!(dev.bus().host_read(1), Some(std::vec![0x02, 0x82]));
+    }
+
+    #[gen_hid_descriptor(
+        (collection = APPLICATION, usage_page = KEYBOARD) = {
+            (usage_min = 0x00, usage_max = 0xDD) = {
+                #[item_settings data,array,absolute,null] nullable=input;
+            };
+            (usage_min = 0x00, usage_max = 0xDD) = {
+                #[item_settings data,array,absolute,not_null] non_nullable=input;
+            };
+            (usage_min = 0x00, usage_max = 0xDD) = {
+                #[item_settings data,array,absolute] unspecified=input;
+            };
+            (usage_min = 0xE0, usage_max = 0xE7) = {
+                #[packed_bits 8] #[item_settings data,variable,absolute,null] modifiers=input;
+            };
+        }
+    )]
+    #[allow(dead_code)]
+    struct CustomArrayNullState {
+        nullable: [u8; 6],
+        non_nullable: [u8; 6],
+        unspecified: [u8; 6],
+        modifiers: u8,
+    }
+
+    #[test]
+    fn test_array_null_state() {
+        use crate::descriptor::describe;
+        use crate::descriptor::parser::items;
+        use usbd_hid_descriptors::{ItemType, MainItemKind};
+
+        let desc = describe(CustomArrayNullState::desc());
+        let inputs: std::vec::Vec<&str> = desc
+            .lines()
+            .map(str::trim)
+            .filter(|l| l.starts_with("Input"))
+            .collect();
+        assert_eq!(
+            inputs,
+            [
+                "Input (Data,Array,Abs,Null State)",
+                "Input (Data,Array,Abs)",
+                "Input (Data,Array,Abs)",
+                "Input (Data,Var,Abs,Null State)",
+            ]
+        );
+
+        // Null State is bit 6 of the main item data, for arrays and variables alike.
+        let main_items: std::vec::Vec<u32> = items(CustomArrayNullState::desc())
+            .map(|item| item.unwrap())
+            .filter(|item| {
+                item.typ == ItemType::Main.into() && item.tag == MainItemKind::Input.into()
+            })
+            .map(|item| item.value())
+            .collect();
+        assert_eq!(main_items, [0x40, 0x00, 0x00, 0x42]);
+    }
+
+    #[test]
+    fn test_push_input_queued() {
+        use crate::hid_class::HIDClass;
+        use crate::test_bus::{test_alloc, test_device};
+        use usb_device::UsbError;
+
+        let alloc = test_alloc();
+        let mut hid: HIDClass<_, 4, 0, 64> =
+            HIDClass::new_ep_in(&alloc, MouseReport::desc(), 10).with_storage();
+        let mut dev = test_device(&alloc);
+
+        let report = |x| MouseReport {
+            buttons: 0,
+            x,
+            y: 0,
+            wheel: 0,
+            pan: 0,
+        };
+        // The first report is written straight away, the rest wait for the endpoint.
+        for x in 1..=3 {
+            assert_eq!(hid.push_input_queued(&report(x)), Ok(5));
+        }
+        assert_eq!(hid.queued_input_len(), 2);
+
+        for x in 1..=3 {
+            assert_eq!(dev.bus().host_read(1), Some(std::vec![0, x as u8, 0, 0, 0]));
+            dev.poll(&mut [&mut hid]);
+        }
+        assert_eq!(dev.bus().host_read(1), None);
+        assert_eq!(hid.queued_input_len(), 0);
+
+        // Reports are rejected once the queue is full.
+        for x in 1..=5 {
+            assert_eq!(hid.push_input_queued(&report(x)), Ok(5));
+        }
+        assert_eq!(hid.push_input_queued(&report(6)), Err(UsbError::WouldBlock));
+    }
+
+    #[gen_hid_descriptor(
+        bit_packing(report),
+        (collection = APPLICATION, usage_page = VENDOR_DEFINED_START, usage = 0x01) = {
+            (usage_page = BUTTON, usage_min = 1, usage_max = 3) = {
+                #[packed_bits 3] #[item_settings data,variable,absolute] low_buttons=input;
+            };
+            (usage_page = BUTTON, usage_min = 4, usage_max = 11) = {
+                #[packed_bits 8] #[item_settings data,variable,absolute] high_buttons=input;
+            };
+            #[item_settings data,variable,absolute] value=input;
+            (usage_page = LEDS, usage_min = 1, usage_max = 5) = {
+                #[packed_bits 5] #[item_settings data,variable,absolute] leds=output;
+            };
+            (usage_page = LEDS, usage_min = 6, usage_max = 7) = {
+                #[packed_bits 2] #[item_settings data,variable,absolute] more_leds=output;
+            };
+        }
+    )]
+    #[allow(dead_code)]
+    struct CustomSharedPackedBits {
+        low_buttons: u8,
+        high_buttons: u8,
+        value: u8,
+        leds: u8,
+        more_leds: u8,
+    }
+
+    #[test]
+    fn test_bit_packing_report() {
+        use crate::descriptor::describe;
+
+        let desc = describe(CustomSharedPackedBits::desc());
+        let main_items: std::vec::Vec<&str> = desc
+            .lines()
+            .map(str::trim)
+            .filter(|l| {
+                l.starts_with("Input")
+                    || l.starts_with("Output")
+                    || l.starts_with("Report Size")
+                    || l.starts_with("Report Count")
+            })
+            .collect();
+        assert_eq!(
+            main_items,
+            [
+                "Report Size (1)",
+                "Report Count (3)",
+                "Input (Data,Var,Abs)",
+                "Report Count (8)",
+                "Input (Data,Var,Abs)",
+                // The 11 bits of buttons share a single padding item.
+                "Report Count (5)",
+                "Input (Const,Var,Abs)",
+                "Report Size (8)",
+                "Report Count (1)",
+                "Input (Data,Var,Abs)",
+                "Report Size (1)",
+                "Report Count (5)",
+                "Output (Data,Var,Abs)",
+                "Report Count (2)",
+                "Output (Data,Var,Abs)",
+                "Report Count (1)",
+                "Output (Const,Var,Abs)",
+            ]
+        );
+
+        let report = CustomSharedPackedBits {
+            low_buttons: 0b101,
+            high_buttons: 0b1000_0001,
+            value: 0x42,
+            leds: 0,
+            more_leds: 0,
+        };
+        assert_eq!(CustomSharedPackedBits::MAX_INPUT_LEN, 3);
+        assert_eq!(report.to_array(), [0b0000_1101, 0b0000_0100, 0x42]);
+
+        let leds = CustomSharedPackedBits::try_from(&[0b0111_0011u8][..]).unwrap();
+        assert_eq!({ leds.leds }, 0b1_0011);
+        assert_eq!({ leds.more_leds }, 0b11);
+        assert!(CustomSharedPackedBits::try_from(&[0u8, 0][..]).is_err());
+    }
+
+    #[test]
+    fn test_expected_set_report_len() {
+        // The keyboard's LED output report is a single byte.
+        assert_eq!(
+            KeyboardReport::expected_set_report_len(0, ReportType::Output),
+            Some(1)
+        );
+        assert_eq!(
+            KeyboardReport::expected_set_report_len(0, ReportType::Input),
+            Some(8)
+        );
+        assert_eq!(
+            KeyboardReport::expected_set_report_len(0, ReportType::Feature),
+            None
+        );
+        assert_eq!(
+            KeyboardReport::expected_set_report_len(1, ReportType::Output),
+            None
+        );
+
+        // Reports with an ID are prefixed with it.
+        assert_eq!(
+            CustomKeyboardWithSystemControl::expected_set_report_len(1, ReportType::Input),
+            Some(8)
+        );
+        assert_eq!(
+            CustomKeyboardWithSystemControl::expected_set_report_len(2, ReportType::Input),
+            Some(2)
+        );
+        assert_eq!(
+            CustomPadTo::expected_set_report_len(0, ReportType::Input),
+            Some(8)
+        );
+    }
+
+    #[test]
+    fn test_lamp_array_descriptor() {
+        use crate::descriptor::LampArrayReport;
+
+        let desc = LampArrayReport::desc();
+        assert_eq!(
+            desc[..8],
+            [
+                0x05, 0x59, // Usage Page (Lighting And Illumination)
+                0x09, 0x01, // Usage (LampArray)
+                0xA1, 0x01, // Collection (Application)
+                0x09, 0x02, //   Usage (LampArrayAttributesReport)
+            ]
+        );
+        assert_eq!(desc[desc.len() - 2..], [0xC0, 0xC0]);
+        assert_eq!(LampArrayReport::REPORT_IDS, [1, 2, 3, 4, 5, 6]);
+
+        // The 32-bit attributes are limited to the largest positive logical maximum.
+        let max = [0x27, 0xFF, 0xFF, 0xFF, 0x7F];
+        assert!(desc.windows(max.len()).any(|w| w == max));
+
+        let lens = [(1, 23), (2, 3), (3, 29), (4, 51), (5, 10), (6, 2)];
+        for (report_id, len) in lens {
+            assert_eq!(
+                LampArrayReport::expected_set_report_len(report_id, ReportType::Feature),
+                Some(len),
+            );
+        }
+        assert_eq!(
+            LampArrayReport::expected_set_report_len(1, ReportType::Input),
+            None
+        );
+    }
+
+    #[test]
+    fn test_rotary_encoder_report() {
+        use crate::descriptor::parser::items;
+        use crate::descriptor::{describe, RotaryEncoderReport};
+        use usbd_hid_descriptors::{GlobalItemKind, ItemType};
+
+        let desc = describe(RotaryEncoderReport::desc());
+        let dial: std::vec::Vec<&str> = desc
+            .lines()
+            .map(str::trim)
+            .skip_while(|l| *l != "Usage (0x37)")
+            .collect();
+        // Physical Minimum and Unit Exponent are left at their initial value of 0.
+        assert_eq!(
+            dial,
+            [
+                "Usage (0x37)",
+                "Logical Maximum (359)",
+                "Physical Maximum (359)",
+                "Unit (0x14)",
+                "Report Size (16)",
+                "Report Count (1)",
+                "Input (Data,Var,Abs)",
+                "End Collection",
+            ]
+        );
+        // English Rotation system (nibble 0 = 4), degrees to the power of 1 (nibble 1 = 1).
+        let unit = items(&RotaryEncoderReport::DESC)
+            .map(|item| item.unwrap())
+            .find(|item| {
+                item.typ == ItemType::Global.into() && item.tag == GlobalItemKind::Unit.into()
+            })
+            .unwrap();
+        assert_eq!(unit.data, [0x14]);
+
+        let mut buf = [0u8; 3];
+        let report = RotaryEncoderReport::new(90, true);
+        assert_eq!(report.serialize_into(&mut buf), Ok(3));
+        assert_eq!(buf, [0x01, 0x5A, 0x00]);
+        let report = RotaryEncoderReport::new(359 + 2, false);
+        assert_eq!(report.serialize_into(&mut buf), Ok(3));
+        assert_eq!(buf, [0x00, 0x01, 0x00]);
+    }
+
+    #[test]
+    fn test_digitizer_scan_time() {
+        use crate::descriptor::{describe, DigitizerReport};
+
+        // Scan Time shares the 16-bit report size of the Y coordinate before it, and is
+        // in units of 100 microseconds: seconds with an exponent of -4.
+        let desc = describe(DigitizerReport::desc());
+        let scan_time: std::vec::Vec<&str> = desc
+            .lines()
+            .map(str::trim)
+            .skip_while(|l| *l != "Usage (0x31)")
+            .take(8)
+            .collect();
+        assert_eq!(
+            scan_time,
+            [
+                "Usage (0x31)",
+                "Input (Data,Var,Abs)",
+                "End Collection",
+                "Usage Page (Digitizer)",
+                "Usage (0x56)",
+                "Unit (0x1001)",
+                "Unit Exponent (12)",
+                "Input (Data,Var,Abs)",
+            ]
+        );
+        assert_eq!(DigitizerReport::MAX_INPUT_LEN, 9);
+
+        let mut report = DigitizerReport {
+            tip_switch: 1,
+            contact_id: 0,
+            x: 0x1234,
+            y: 0x0100,
+            scan_time: 0,
+            contact_count: 1,
+        };
+        let mut last = None;
+        for micros in [0u64, 8_300, 16_600, 6_553_500] {
+            report.scan_time = DigitizerReport::scan_time(micros);
+            let bytes = report.to_array();
+            assert_eq!(bytes[..6], [0x01, 0x00, 0x34, 0x12, 0x00, 0x01]);
+            assert_eq!(bytes[6..8], report.scan_time.to_le_bytes());
+            assert_eq!(bytes[8], 1);
+            if let Some(last) = last {
+                assert!(report.scan_time > last);
+            }
+            last = Some(report.scan_time);
+        }
+        assert_eq!(last, Some(65535));
+        // The scan time wraps around every 6.5536 seconds.
+        assert_eq!(DigitizerReport::scan_time(6_553_600), 0);
+    }
+
+    #[test]
+    fn test_get_report_request() {
+        use crate::hid_class::HIDClass;
+        use crate::test_bus::*;
+        use usb_device::UsbError;
+
+        let alloc = test_alloc();
+        let mut hid: HIDClass<_, 0, 1, 0> =
+            HIDClass::new(&alloc, MouseReport::desc(), 10).with_storage();
+        let mut dev = test_device(&alloc);
+        assert!(hid.take_get_report_request().is_none());
+
+        // The response can't be deferred: without a report to answer with, the request is
+        // STALLed, but still surfaced.
+        assert_eq!(
+            get_report(&mut dev, &mut hid, ReportType::Feature, 0, 2),
+            Err(UsbError::InvalidState)
+        );
+        let request = hid.take_get_report_request().unwrap();
+        assert_eq!(request.report_type, ReportType::Feature);
+        assert_eq!(request.report_id, 0);
+        assert_eq!(request.len, 2);
+        assert!(hid.take_get_report_request().is_none());
+
+        // A report set ahead of time answers the request immediately.
+        hid.set_get_report_response(ReportType::Feature, 0, &[0x12, 0x34])
+            .unwrap();
+        assert_eq!(
+            get_report(&mut dev, &mut hid, ReportType::Feature, 0, 2),
+            Ok(std::vec![0x12, 0x34])
+        );
+        assert!(hid.take_get_report_request().is_some());
+        assert_eq!(
+            get_report(&mut dev, &mut hid, ReportType::Input, 0, 5),
+            Err(UsbError::InvalidState)
+        );
+        assert_eq!(
+            hid.take_get_report_request().map(|r| r.report_type),
+            Some(ReportType::Input)
+        );
+
+        assert_eq!(
+            hid.set_get_report_response(ReportType::Feature, 0, &[0; 1024]),
+            Err(UsbError::BufferOverflow)
+        );
+    }
+
+    #[test]
+    fn test_get_report_responses() {
+        use crate::descriptor::PrecisionTouchpadReport as Ptp;
+        use crate::hid_class::HIDClass;
+        use crate::test_bus::*;
+        use usb_device::UsbError;
+
+        let alloc = test_alloc();
+        let mut hid: HIDClass<_, 0, 4, 0> = HIDClass::new(&alloc, &Ptp::DESC, 10).with_storage();
+        let mut dev = test_device(&alloc);
+
+        // Each report type and ID is answered with its own report.
+        let mut input = [0u8; 30];
+        Ptp::default().serialize_into(&mut input).unwrap();
+        hid.set_get_report_response(ReportType::Input, 1, &input)
+            .unwrap();
+        hid.set_get_report_response(ReportType::Feature, 2, &Ptp::DEVICE_CAPABILITIES)
+            .unwrap();
+        hid.set_get_report_response(ReportType::Feature, 4, &[0x04, 0x00])
+            .unwrap();
+        assert_eq!(
+            get_report(&mut dev, &mut hid, ReportType::Feature, 2, 2),
+            Ok(Ptp::DEVICE_CAPABILITIES.to_vec())
+        );
+        assert_eq!(
+            get_report(&mut dev, &mut hid, ReportType::Input, 1, 30),
+            Ok(input.to_vec())
+        );
+        assert_eq!(
+            get_report(&mut dev, &mut hid, ReportType::Feature, 5, 2),
+            Err(UsbError::InvalidState)
+        );
+
+        // A report of the same type and ID replaces the one held.
+        hid.set_get_report_response(ReportType::Feature, 4, &[0x04, Ptp::INPUT_MODE_TOUCHPAD])
+            .unwrap();
+        assert_eq!(
+            get_report(&mut dev, &mut hid, ReportType::Feature, 4, 2),
+            Ok(std::vec![0x04, 0x03])
+        );
+
+        hid.set_get_report_response(ReportType::Feature, 5, &[0x05, 0x03])
+            .unwrap();
+        assert_eq!(
+            hid.set_get_report_response(ReportType::Feature, 3, &[0x03]),
+            Err(UsbError::BufferOverflow)
+        );
+        hid.clear_get_report_responses();
+        assert_eq!(
+            get_report(&mut dev, &mut hid, ReportType::Feature, 2, 2),
+            Err(UsbError::InvalidState)
+        );
+    }
+
+    #[gen_hid_descriptor(
+        (collection = APPLICATION, usage_page = VENDOR_DEFINED_START, usage = 0x01) = {
+            #[logical_min -1] #[logical_max 0] f1=input;
+            #[logical_min -128] #[logical_max 0] f2=input;
+            #[logical_min -129] #[logical_max 0] f3=input;
+            #[logical_min -200] #[logical_max 0] f4=input;
+            #[logical_min -32768] #[logical_max 0] f5=input;
+            #[logical_min -32769] #[logical_max 0] f6=input;
+        }
+    )]
+    #[allow(dead_code)]
+    struct CustomSignedBoundaries {
+        f1: i32,
+        f2: i32,
+        f3: i32,
+        f4: i32,
+        f5: i32,
+        f6: i32,
+    }
+
+    #[test]
+    fn test_signed_item_boundaries() {
+        let desc = CustomSignedBoundaries::desc();
+        let minimums: &[&[u8]] = &[
+            &[0x15, 0xFF],
+            &[0x15, 0x80, 0x81],
+            &[0x16, 0x7F, 0xFF, 0x81],
+            &[0x16, 0x38, 0xFF, 0x81],
+            &[0x16, 0x00, 0x80, 0x81],
+            &[0x17, 0xFF, 0x7F, 0xFF, 0xFF, 0x81],
+        ];
+        for min in minimums {
+            assert!(
+                desc.windows(min.len()).any(|w| w == *min),
+                "missing {:02x?}",
+                min
+            );
+        }
+
+        let described = crate::descriptor::describe(desc);
+        for value in [-1, -128, -129, -200, -32768, -32769] {
+            let line = std::format!("Logical Minimum ({})", value);
+            assert!(described.contains(&line), "missing {}", line);
+        }
+    }
+
+    #[gen_hid_descriptor(
+        (collection = APPLICATION, usage_page = LEDS, usage = 0x01) = {
+            (usage = NUM_LOCK, usage = CAPS_LOCK, usage = SCROLL_LOCK, usage = MUTE, usage = RING) = {
+                #[packed_bits 5] leds=output;
+            };
+        }
+    )]
+    #[allow(dead_code)]
+    struct CustomIndicatorPanel {
+        leds: u8,
+    }
+
+    #[test]
+    fn test_named_led_usages() {
+        let expected: &[u8] = &[
+            0x05, 0x08, // Usage Page (LEDs)
+            0x09, 0x01, // Usage (Num Lock)
+            0xa1, 0x01, // Collection (Application)
+            0x09, 0x01, //   Usage (Num Lock)
+            0x09, 0x02, //   Usage (Caps Lock)
+            0x09, 0x03, //   Usage (Scroll Lock)
+            0x09, 0x09, //   Usage (Mute)
+            0x09, 0x18, //   Usage (Ring)
+            0x15, 0x00, //   Logical Minimum (0)
+            0x25, 0x01, //   Logical Maximum (1)
+            0x75, 0x01, //   Report Size (1)
+            0x95, 0x05, //   Report Count (5)
+            0x91, 0x02, //   Output (Data, Variable, Absolute)
+            0x95, 0x03, //   Report Count (3)
+            0x91, 0x03, //   Output (Constant, Variable, Absolute)
+            0xc0, // End Collection
+        ];
+        assert_eq!(CustomIndicatorPanel::desc(), expected);
+
+        // Bit 3 is the Mute LED.
+        let report = CustomIndicatorPanel::try_from(&[0x08u8][..]).unwrap();
+        assert!(report.leds_bit(3));
+        assert!(!report.leds_bit(4));
+    }
+
+    #[gen_hid_descriptor(
+        (collection = APPLICATION, usage_page = VENDOR_DEFINED_START, usage = 0x01) = {
+            (report_id = 0x01,) = {
+                #[usage 0x02] leds=output;
+            };
+            (report_id = 0x02,) = {
+                #[usage 0x03] rumble=output;
+            };
+        }
+    )]
+    #[allow(dead_code)]
+    struct CustomMultiOutput {
+        leds: u8,
+        rumble: u16,
+    }
+
+    #[test]
+    fn test_read_output_dispatch() {
+        use crate::hid_class::HIDClass;
+        use crate::test_bus::{test_alloc, test_device};
+        use usb_device::UsbError;
+
+        let alloc = test_alloc();
+        let hid = HIDClass::new_ep_out(&alloc, CustomMultiOutput::desc(), 10);
+        let dev = test_device(&alloc);
+
+        let mut leds = None;
+        let mut rumble = None;
+        let mut dispatch = |report_id: u8, data: &[u8]| match report_id {
+            1 => leds = Some(data[0]),
+            2 => rumble = Some(u16::from_le_bytes([data[0], data[1]])),
+            _ => unreachable!(),
+        };
+
+        dev.bus().host_write(1, &[0x02, 0x34, 0x12], false);
+        assert_eq!(hid.read_output_dispatch(&mut dispatch), Ok(()));
+        dev.bus().host_write(1, &[0x01, 0x05], false);
+        assert_eq!(hid.read_output_dispatch(&mut dispatch), Ok(()));
+        assert_eq!(
+            hid.read_output_dispatch(&mut dispatch),
+            Err(UsbError::WouldBlock)
+        );
+        assert_eq!(leds, Some(0x05));
+        assert_eq!(rumble, Some(0x1234));
+
+        // Undeclared report IDs are not dispatched.
+        dev.bus().host_write(1, &[0x03, 0x00], false);
+        assert_eq!(
+            hid.read_output_dispatch(|_, _| unreachable!()),
+            Err::<(), _>(UsbError::ParseError)
+        );
+
+        // Without report IDs, the whole packet is dispatched as report ID 0.
+        let alloc = test_alloc();
+        let hid = HIDClass::new_ep_out(&alloc, KeyboardReport::desc(), 10);
+        let dev = test_device(&alloc);
+        dev.bus().host_write(1, &[0x02], false);
+        assert_eq!(
+            hid.read_output_dispatch(|id, data| (id, data[0])),
+            Ok((0, 0x02))
+        );
+    }
+
+    #[gen_hid_descriptor(
+        (collection = APPLICATION, usage_page = VENDOR_DEFINED_START, usage = 0x01) = {
+            #[packed_bits 3] buttons=input;
+            x=input;
+            status=input;
+            counter=input;
+            #[usage 0x02] mode=output;
+            #[usage 0x03] gain=output;
+        }
+    )]
+    #[allow(dead_code)]
+    struct CustomPackedLayout {
+        buttons: u8,
+        x: i16,
+        status: u8,
+        counter: u32,
+        mode: u8,
+        gain: u16,
+    }
+
+    #[gen_hid_descriptor(
+        aligned,
+        (collection = APPLICATION, usage_page = VENDOR_DEFINED_START, usage = 0x01) = {
+            #[packed_bits 3] buttons=input;
+            x=input;
+            status=input;
+            counter=input;
+            #[usage 0x02] mode=output;
+            #[usage 0x03] gain=output;
+        }
+    )]
+    #[allow(dead_code)]
+    struct CustomAlignedLayout {
+        buttons: u8,
+        x: i16,
+        status: u8,
+        counter: u32,
+        mode: u8,
+        gain: u16,
+    }
+
+    #[test]
+    fn test_aligned_layout() {
+        assert_eq!(CustomPackedLayout::desc(), CustomAlignedLayout::desc());
+        assert_eq!(core::mem::size_of::<CustomPackedLayout>(), 11);
+        assert!(core::mem::size_of::<CustomAlignedLayout>() > 11);
+        assert_eq!(core::mem::align_of::<CustomAlignedLayout>(), 4);
+
+        let packed = CustomPackedLayout {
+            buttons: 0b101,
+            x: -2,
+            status: 0x7E,
+            counter: 0x12345678,
+            mode: 0,
+            gain: 0,
+        };
+        let aligned = CustomAlignedLayout {
+            buttons: 0b101,
+            x: -2,
+            status: 0x7E,
+            counter: 0x12345678,
+            mode: 0,
+            gain: 0,
+        };
+        assert_eq!(
+            CustomAlignedLayout::MAX_INPUT_LEN,
+            CustomPackedLayout::MAX_INPUT_LEN
+        );
+        assert_eq!(aligned.to_array(), packed.to_array());
+        assert_eq!(
+            aligned.to_array(),
+            [0x05, 0xFE, 0xFF, 0x7E, 0x78, 0x56, 0x34, 0x12]
+        );
+
+        // Fields of an aligned struct can be borrowed.
+        let counter: &u32 = &aligned.counter;
+        assert_eq!(*counter, 0x12345678);
+
+        let output = [0x02, 0x34, 0x12];
+        let packed = CustomPackedLayout::try_from(&output[..]).unwrap();
+        let aligned = CustomAlignedLayout::try_from(&output[..]).unwrap();
+        assert_eq!((aligned.mode, aligned.gain), (0x02, 0x1234));
+        assert_eq!(({ packed.mode }, { packed.gain }), (0x02, 0x1234));
+    }
+
+    #[gen_hid_descriptor(
+        (collection = APPLICATION, usage_page = VENDOR_DEFINED_START, usage = 0x01) = {
+            #[usage 0x02] status=input;
+            #[usage 0x03] sensitivity=feature;
+            #[usage 0x04] #[packed_bits 2] flags=feature;
+            #[usage 0x05] offset=feature;
+        }
+    )]
+    #[allow(dead_code)]
+    struct CustomConfigReport {
+        status: u8,
+        sensitivity: u16,
+        flags: u8,
+        offset: i8,
+    }
+
+    #[test]
+    fn test_feature_round_trip() {
+        use crate::descriptor::AsFeatureReport;
+        use crate::hid_class::HIDClass;
+        use crate::test_bus::*;
+        use usb_device::UsbError;
+
+        assert_eq!(CustomConfigReport::MAX_INPUT_LEN, 1);
+        assert_eq!(CustomConfigReport::MAX_FEATURE_LEN, 4);
+
+        let alloc = test_alloc();
+        let mut hid: HIDClass<_, 0, 1, 0> =
+            HIDClass::new(&alloc, CustomConfigReport::desc(), 10).with_storage();
+        let mut dev = test_device(&alloc);
+        assert_eq!(
+            hid.pull_feature::<CustomConfigReport>(),
+            Err(UsbError::WouldBlock)
+        );
+
+        // The host writes the configuration.
+        set_report(
+            &mut dev,
+            &mut hid,
+            ReportType::Feature,
+            0,
+            &[0x34, 0x12, 0x02, 0xFD],
+        )
+        .unwrap();
+        let config: CustomConfigReport = hid.pull_feature().unwrap();
+        assert_eq!(
+            config,
+            CustomConfigReport {
+                status: 0,
+                sensitivity: 0x1234,
+                flags: 0b10,
+                offset: -3,
+            }
+        );
+        assert_eq!(
+            hid.pull_feature::<CustomConfigReport>(),
+            Err(UsbError::WouldBlock)
+        );
+
+        // And reads it back.
+        assert_eq!(hid.push_feature(&config), Ok(4));
+        assert_eq!(
+            get_report(&mut dev, &mut hid, ReportType::Feature, 0, 4),
+            Ok(std::vec![0x34, 0x12, 0x02, 0xFD])
+        );
+
+        // Feature reports of the wrong length are rejected.
+        set_report(&mut dev, &mut hid, ReportType::Feature, 0, &[0x34, 0x12]).unwrap();
+        assert_eq!(
+            hid.pull_feature::<CustomConfigReport>(),
+            Err(UsbError::ParseError)
+        );
+        let config = CustomConfigReport::try_from_feature(&[0x00, 0x00, 0x01, 0x00]).unwrap();
+        assert!(config.flags_bit(0));
+    }
+
+    #[gen_hid_descriptor(
+        (collection = APPLICATION, usage_page = GENERIC_DESKTOP, usage = MOUSE) = {
+            #[usage_page CONSUMER] #[usage AC_PAN] pan=input;
+        }
+    )]
+    #[quirks(wide_usage_page)]
+    #[allow(dead_code)]
+    struct CustomWideUsagePage {
+        pan: i8,
+    }
+
+    #[test]
+    fn test_wide_usage_page() {
+        let expected: &[u8] = &[
+            0x06, 0x01, 0x00, // Usage Page (Generic Desktop)
+            0x09, 0x02, // Usage (Mouse)
+            0xa1, 0x01, // Collection (Application)
+            0x06, 0x0c, 0x00, //   Usage Page (Consumer)
+            0x0a, 0x38, 0x02, //   Usage (AC Pan)
+            0x15, 0x81, //   Logical Minimum (-127)
+            0x25, 0x7f, //   Logical Maximum (127)
+            0x75, 0x08, //   Report Size (8)
+            0x95, 0x01, //   Report Count (1)
+            0x81, 0x02, //   Input (Data, Variable, Absolute)
+            0x06, 0x01, 0x00, //   Usage Page (Generic Desktop)
+            0xc0, // End Collection
+        ];
+        assert_eq!(CustomWideUsagePage::desc(), expected);
+    }
+
+    #[gen_hid_descriptor(
+        (collection = APPLICATION, usage_page = DIGITIZER, usage = TOUCH_SCREEN) = {
+            (collection = LOGICAL, usage = FINGER, repeat = 2) = {
+                #[usage TIP_SWITCH] #[packed_bits 1] tip_switch=input;
+                #[usage CONTACT_IDENTIFIER] contact_id=input;
+                (usage_page = GENERIC_DESKTOP,) = {
+                    #[usage X] x=input;
+                    #[usage Y] y=input;
+                };
+            };
+            #[usage CONTACT_COUNT] contact_count=input;
+        }
+    )]
+    #[allow(dead_code)]
+    struct CustomTouchContacts {
+        tip_switch: [u8; 2],
+        contact_id: [u8; 2],
+        x: [u16; 2],
+        y: [u16; 2],
+        contact_count: u8,
+    }
+
+    #[test]
+    fn test_repeated_group() {
+        let expected: &[u8] = &[
+            0x05, 0x0d, // Usage Page (Digitizer)
+            0x09, 0x04, // Usage (Touch Screen)
+            0xa1, 0x01, // Collection (Application)
+            0x09, 0x22, //   Usage (Finger)
+            0xa1, 0x02, //   Collection (Logical)
+            0x09, 0x42, //     Usage (Tip Switch)
+            0x15, 0x00, //     Logical Minimum (0)
+            0x25, 0x01, //     Logical Maximum (1)
+            0x75, 0x01, //     Report Size (1)
+            0x95, 0x01, //     Report Count (1)
+            0x81, 0x02, //     Input (Data, Variable, Absolute)
+            0x95, 0x07, //     Report Count (7)
+            0x81, 0x03, //     Input (Constant, Variable, Absolute)
+            0x09, 0x51, //     Usage (Contact Identifier)
+            0x26, 0xff, 0x00, //     Logical Maximum (255)
+            0x75, 0x08, //     Report Size (8)
+            0x95, 0x01, //     Report Count (1)
+            0x81, 0x02, //     Input (Data, Variable, Absolute)
+            0x05, 0x01, //     Usage Page (Generic Desktop)
+            0x09, 0x30, //     Usage (X)
+            0x27, 0xff, 0xff, 0x00, 0x00, //     Logical Maximum (65535)
+            0x75, 0x10, //     Report Size (16)
+            0x81, 0x02, //     Input (Data, Variable, Absolute)
+            0x09, 0x31, //     Usage (Y)
+            0x81, 0x02, //     Input (Data, Variable, Absolute)
+            0xc0, //   End Collection
+            0x05, 0x0d, //   Usage Page (Digitizer)
+            0x09, 0x22, //   Usage (Finger)
+            0xa1, 0x02, //   Collection (Logical)
+            0x09, 0x42, //     Usage (Tip Switch)
+            0x25, 0x01, //     Logical Maximum (1)
+            0x75, 0x01, //     Report Size (1)
+            0x81, 0x02, //     Input (Data, Variable, Absolute)
+            0x95, 0x07, //     Report Count (7)
+            0x81, 0x03, //     Input (Constant, Variable, Absolute)
+            0x09, 0x51, //     Usage (Contact Identifier)
+            0x26, 0xff, 0x00, //     Logical Maximum (255)
+            0x75, 0x08, //     Report Size (8)
+            0x95, 0x01, //     Report Count (1)
+            0x81, 0x02, //     Input (Data, Variable, Absolute)
+            0x05, 0x01, //     Usage Page (Generic Desktop)
+            0x09, 0x30, //     Usage (X)
+            0x27, 0xff, 0xff, 0x00, 0x00, //     Logical Maximum (65535)
+            0x75, 0x10, //     Report Size (16)
+            0x81, 0x02, //     Input (Data, Variable, Absolute)
+            0x09, 0x31, //     Usage (Y)
+            0x81, 0x02, //     Input (Data, Variable, Absolute)
+            0xc0, //   End Collection
+            0x05, 0x0d, //   Usage Page (Digitizer)
+            0x09, 0x54, //   Usage (Contact Count)
+            0x26, 0xff, 0x00, //   Logical Maximum (255)
+            0x75, 0x08, //   Report Size (8)
+            0x81, 0x02, //   Input (Data, Variable, Absolute)
+            0xc0, // End Collection
+        ];
+        assert_eq!(CustomTouchContacts::desc(), expected);
+
+        // Each contact holds the next element of the array fields.
+        let report = CustomTouchContacts {
+            tip_switch: [1, 0],
+            contact_id: [3, 4],
+            x: [0x0102, 0x0304],
+            y: [0x0506, 0x0708],
+            contact_count: 1,
+        };
+        assert_eq!(
+            report.to_array(),
+            [0x01, 0x03, 0x02, 0x01, 0x06, 0x05, 0x00, 0x04, 0x04, 0x03, 0x08, 0x07, 0x01]
+        );
+    }
+
+    #[test]
+    fn test_serialize_with_id() {
+        let report = MouseReport {
+            buttons: 0x05,
+            x: -3,
+            y: 7,
+            wheel: -1,
+            pan: 1,
+        };
+        let mut expected = [0u8; 64];
+        let size = ssmarshal::serialize(&mut expected, &report).unwrap();
+
+        let mut buf = [0u8; 64];
+        assert_eq!(report.serialize_with_id(0x03, &mut buf), Ok(size + 1));
+        assert_eq!(buf[0], 0x03);
+        assert_eq!(buf[1..size + 1], expected[..size]);
+
+        assert_eq!(
+            report.serialize_with_id(0x03, &mut buf[..size]),
+            Err(UsbError::BufferOverflow)
+        );
+        assert_eq!(
+            report.serialize_with_id(0x03, &mut []),
+            Err(UsbError::BufferOverflow)
+        );
+    }
+
+    #[gen_hid_descriptor(
+        (collection = APPLICATION, usage_page = GENERIC_DESKTOP, usage = JOYSTICK) = {
+            (usage_page = GENERIC_DESKTOP, physical_min = -90, physical_max = 90,) = {
+                #[usage X] x=input;
+                #[usage Y] y=input;
+            };
+            #[usage WHEEL] throttle=input;
+        }
+    )]
+    #[allow(dead_code)]
+    struct CustomPhysicalJoystick {
+        x: i8,
+        y: i8,
+        throttle: u8,
+    }
+
+    #[test]
+    fn test_physical_range() {
+        let expected: &[u8] = &[
+            0x05, 0x01, // Usage Page (Generic Desktop)
+            0x09, 0x04, // Usage (Joystick)
+            0xa1, 0x01, // Collection (Application)
+            0x05, 0x01, //   Usage Page (Generic Desktop)
+            0x09, 0x30, //   Usage (X)
+            0x15, 0x81, //   Logical Minimum (-127)
+            0x25, 0x7f, //   Logical Maximum (127)
+            0x35, 0xa6, //   Physical Minimum (-90)
+            0x45, 0x5a, //   Physical Maximum (90)
+            0x75, 0x08, //   Report Size (8)
+            0x95, 0x01, //   Report Count (1)
+            0x81, 0x02, //   Input (Data, Variable, Absolute)
+            0x09, 0x31, //   Usage (Y)
+            0x81, 0x02, //   Input (Data, Variable, Absolute)
+            0x09, 0x38, //   Usage (Wheel)
+            0x15, 0x00, //   Logical Minimum (0)
+            0x26, 0xff, 0x00, //   Logical Maximum (255)
+            0x35, 0x00, //   Physical Minimum (0)
+            0x45, 0x00, //   Physical Maximum (0)
+            0x81, 0x02, //   Input (Data, Variable, Absolute)
+            0xc0, // End Collection
+        ];
+        assert_eq!(CustomPhysicalJoystick::desc(), expected);
+    }
+
+    #[test]
+    fn test_precision_touchpad_descriptor() {
+        use crate::descriptor::{describe, report_ids, PrecisionTouchpadReport, TouchpadContact};
+
+        let desc = &PrecisionTouchpadReport::DESC;
+        assert_eq!(PrecisionTouchpadReport::DESC_LEN, 409);
+        assert_eq!(
+            report_ids::<8>(desc).unwrap().as_slice(),
+            [0x01, 0x02, 0x04, 0x05]
+        );
+
+        // Windows' sample layout: a Touch Pad application collection with report ID 1.
+        assert_eq!(desc[..8], [0x05, 0x0D, 0x09, 0x05, 0xA1, 0x01, 0x85, 0x01]);
+        #[rustfmt::skip]
+        let finger = [
+            0x09, 0x22, 0xA1, 0x02, 0x15, 0x00, 0x25, 0x01, 0x09, 0x47, 0x09, 0x42,
+            0x95, 0x02, 0x75, 0x01, 0x81, 0x02, 0x95, 0x01, 0x75, 0x06, 0x25, 0x3F,
+            0x09, 0x51, 0x81, 0x02, 0x05, 0x01, 0x26, 0xFF, 0x0F, 0x75, 0x10, 0x55,
+            0x0E, 0x65, 0x13, 0x09, 0x30, 0x35, 0x00, 0x46, 0x90, 0x01, 0x81, 0x02,
+            0x46, 0x13, 0x01, 0x09, 0x31, 0x81, 0x02, 0xC0,
+        ];
+        assert_eq!(desc[8..64], finger);
+        for contact in 1..PrecisionTouchpadReport::MAX_CONTACTS {
+            let start = 64 + (contact - 1) * 58;
+            assert_eq!(desc[start..start + 2], [0x05, 0x0D]);
+            assert_eq!(desc[start + 2..start + 58], finger);
+        }
+        #[rustfmt::skip]
+        let tail = [
+            // Scan time, contact count & button.
+            0x55, 0x0C, 0x66, 0x01, 0x10, 0x47, 0xFF, 0xFF, 0x00, 0x00, 0x27, 0xFF,
+            0xFF, 0x00, 0x00, 0x75, 0x10, 0x95, 0x01, 0x05, 0x0D, 0x09, 0x56, 0x81,
+            0x02, 0x09, 0x54, 0x25, 0x7F, 0x75, 0x08, 0x81, 0x02, 0x05, 0x09, 0x09,
+            0x01, 0x25, 0x01, 0x75, 0x01, 0x81, 0x02, 0x95, 0x07, 0x81, 0x03,
+            // Device capabilities feature report.
+            0x05, 0x0D, 0x85, 0x02, 0x09, 0x55, 0x09, 0x59, 0x75, 0x04, 0x95, 0x02,
+            0x25, 0x0F, 0xB1, 0x02, 0xC0,
+            // Configuration collection: input mode & function switch feature reports.
+            0x05, 0x0D, 0x09, 0x0E, 0xA1, 0x01, 0x85, 0x04, 0x09, 0x22, 0xA1, 0x02,
+            0x09, 0x52, 0x15, 0x00, 0x25, 0x0A, 0x75, 0x08, 0x95, 0x01, 0xB1, 0x02,
+            0xC0, 0x09, 0x22, 0xA1, 0x00, 0x85, 0x05, 0x09, 0x57, 0x09, 0x58, 0x75,
+            0x01, 0x95, 0x02, 0x25, 0x01, 0xB1, 0x02, 0x95, 0x06, 0xB1, 0x03, 0xC0,
+            0xC0,
+        ];
+        assert_eq!(desc[296..], tail);
+
+        // The certification status blob can't be answered, so it isn't declared.
+        let lines = describe(desc);
+        assert!(!lines.contains("Report Count (256)"));
+        assert!(!lines.contains("Usage Page (Vendor Defined 0xFF00)"));
+
+        let mut report = PrecisionTouchpadReport {
+            scan_time: 0x1234,
+            contact_count: 1,
+            buttons: 1,
+            ..Default::default()
+        };
+        report.contacts[0] = TouchpadContact::new(3, 0x0FFF, 0x0100, true, true);
+        assert_eq!({ report.contacts[0].flags }, 0x0F);
+
+        let mut buf = [0u8; 64];
+        assert_eq!(report.serialize_into(&mut buf), Ok(30));
+        assert_eq!(buf[..6], [0x01, 0x0F, 0xFF, 0x0F, 0x00, 0x01]);
+        assert_eq!(buf[6..26], [0u8; 20]);
+        assert_eq!(buf[26..30], [0x34, 0x12, 0x01, 0x01]);
+    }
+
+    #[test]
+    fn test_precision_touchpad_report_lengths() {
+        use crate::descriptor::parser::items;
+        use crate::descriptor::PrecisionTouchpadReport as Ptp;
+        use crate::hid_class::HIDClass;
+        use crate::test_bus::test_alloc;
+        use usbd_hid_descriptors::{GlobalItemKind, ItemType, MainItemKind};
+
+        // Parses the hand-written descriptor back into the length, in bits, of each report.
+        let mut lengths: std::vec::Vec<(u8, u8, u32)> = std::vec::Vec::new();
+        let (mut report_id, mut report_size, mut report_count) = (0, 0, 0);
+        for item in items(&Ptp::DESC) {
+            let item = item.unwrap();
+            if item.typ == ItemType::Global.into() {
+                match item.tag {
+                    t if t == GlobalItemKind::ReportID.into() => report_id = item.value() as u8,
+                    t if t == GlobalItemKind::ReportSize.into() => report_size = item.value(),
+                    t if t == GlobalItemKind::ReportCount.into() => report_count = item.value(),
+                    _ => {}
+                }
+            } else if item.typ == ItemType::Main.into()
+                && [MainItemKind::Input.into(), MainItemKind::Feature.into()].contains(&item.tag)
+            {
+                match lengths
+                    .iter_mut()
+                    .find(|(tag, id, _)| *tag == item.tag && *id == report_id)
+                {
+                    Some((_, _, bits)) => *bits += report_size * report_count,
+                    None => lengths.push((item.tag, report_id, report_size * report_count)),
+                }
+            }
+        }
+
+        let input = MainItemKind::Input.into();
+        let feature = MainItemKind::Feature.into();
+        assert_eq!(
+            lengths,
+            [
+                (
+                    input,
+                    Ptp::REPORT_ID,
+                    8 * core::mem::size_of::<Ptp>() as u32
+                ),
+                (feature, Ptp::DEVICE_CAPABILITIES_REPORT_ID, 8),
+                (feature, Ptp::INPUT_MODE_REPORT_ID, 8),
+                (feature, Ptp::FUNCTION_SWITCH_REPORT_ID, 8),
+            ]
+        );
+        assert_eq!(Ptp::DEVICE_CAPABILITIES.len(), 2);
+
+        // Every report, with its report ID, fits in the control buffer.
+        let alloc = test_alloc();
+        let hid = HIDClass::new(&alloc, &Ptp::DESC, 10);
+        for (_, _, bits) in lengths {
+            let len_with_id = 1 + bits as usize / 8;
+            assert!(len_with_id <= hid.control_buffer_len());
+        }
+    }
+
+    #[test]
+    fn test_no_redundant_report_count() {
+        use crate::descriptor::parser::items;
+        use crate::descriptor::LampArrayReport;
+        use usbd_hid_descriptors::{GlobalItemKind, ItemType};
+
+        // Returns the Report Count values of the descriptor, in order, and the offsets
+        // of those that repeat the count already in effect.
+        fn report_counts(desc: &[u8]) -> (std::vec::Vec<u32>, std::vec::Vec<usize>) {
+            let (mut counts, mut redundant) = (std::vec::Vec::new(), std::vec::Vec::new());
+            for item in items(desc) {
+                let item = item.unwrap();
+                if item.typ == ItemType::Global.into()
+                    && item.tag == GlobalItemKind::ReportCount.into()
+                {
+                    if counts.last() == Some(&item.value()) {
+                        redundant.push(item.offset);
+                    }
+                    counts.push(item.value());
+                }
+            }
+            (counts, redundant)
+        }
+
+        // Modifiers, reserved byte, LEDs, LED padding and keycodes each change the count.
+        let (counts, redundant) = report_counts(KeyboardReport::desc());
+        assert_eq!(counts, [8, 1, 5, 3, 6]);
+        assert!(redundant.is_empty());
+
+        for desc in [
+            MouseReport::desc(),
+            KeyboardWithMediaReport::desc(),
+            LampArrayReport::desc(),
+            CustomTouchContacts::desc(),
+            CustomMultiReport::desc(),
+        ] {
+            assert_eq!(report_counts(desc).1, []);
+        }
+    }
+
+    // This should generate the following descriptor:
+    // 0x06, 0x00, 0xFF,  // Usage Page (Vendor Defined 0xFF00)
+    // 0x09, 0x01,        // Usage (0x01)
+    // 0xA1, 0x01,        // Collection (Application)
+    // 0x09, 0x02,        //   Usage (0x02)
+    // 0x15, 0x00,        //   Logical Minimum (0)
+    // 0x27, 0xFF, 0xFF, 0x00, 0x00,  //   Logical Maximum (65535)
+    // 0x65, 0x11,        //   Unit (SI Linear: Centimeter)
+    // 0x55, 0x0E,        //   Unit Exponent (-2)
+    // 0x75, 0x10,        //   Report Size (16)
+    // 0x95, 0x01,        //   Report Count (1)
+    // 0x81, 0x02,        //   Input (Data,Var,Abs)
+    // 0x09, 0x03,        //   Usage (0x03)
+    // 0x65, 0x14,        //   Unit (English Rotation: Degrees)
+    // 0x55, 0x00,        //   Unit Exponent (0)
+    // 0x81, 0x02,        //   Input (Data,Var,Abs)
+    // 0x09, 0x04,        //   Usage (0x04)
+    // 0x66, 0x11, 0xF0,  //   Unit (SI Linear: Centimeter per Second)
+    // 0x55, 0x0E,        //   Unit Exponent (-2)
+    // 0x81, 0x02,        //   Input (Data,Var,Abs)
+    // 0x09, 0x05,        //   Usage (0x05)
+    // 0x26, 0xFF, 0x00,  //   Logical Maximum (255)
+    // 0x65, 0x00,        //   Unit (None)
+    // 0x55, 0x00,        //   Unit Exponent (0)
+    // 0x75, 0x08,        //   Report Size (8)
+    // 0x81, 0x02,        //   Input (Data,Var,Abs)
+    // 0xC0,              // End Collection
+    #[gen_hid_descriptor(
+        (collection = APPLICATION, usage_page = VENDOR_DEFINED_START, usage = 0x01) = {
+            (usage = 0x02, unit = CENTIMETERS, unit_exponent = -2) = {
+                distance=input;
+            };
+            (usage = 0x03, unit = DEGREES) = {
+                heading=input;
+            };
+            // The exponent can also be given as its 4-bit encoding.
+            (usage = 0x04, unit = 0xF011, unit_exponent = 0x0E) = {
+                speed=input;
+            };
+            (usage = 0x05,) = {
+                samples=input;
+            };
+        }
+    )]
+    #[allow(dead_code)]
+    struct CustomSensorUnits {
+        distance: u16,
+        heading: u16,
+        speed: u16,
+        samples: u8,
+    }
+
+    #[test]
+    fn test_group_units() {
+        let expected: &[u8] = &[
+            0x06, 0x00, 0xFF, 0x09, 0x01, 0xA1, 0x01, 0x09, 0x02, 0x15, 0x00, 0x27, 0xFF, 0xFF,
+            0x00, 0x00, 0x65, 0x11, 0x55, 0x0E, 0x75, 0x10, 0x95, 0x01, 0x81, 0x02, 0x09, 0x03,
+            0x65, 0x14, 0x55, 0x00, 0x81, 0x02, 0x09, 0x04, 0x66, 0x11, 0xF0, 0x55, 0x0E, 0x81,
+            0x02, 0x09, 0x05, 0x26, 0xFF, 0x00, 0x65, 0x00, 0x55, 0x00, 0x75, 0x08, 0x81, 0x02,
+            0xC0,
+        ];
+        assert_eq!(CustomSensorUnits::desc(), expected);
+    }
+
+    // This should generate the following descriptor:
+    // 0x05, 0x01,        // Usage Page (Generic Desktop)
+    // 0x09, 0x80,        // Usage (System Control)
+    // 0xA1, 0x01,        // Collection (Application)
+    // 0x19, 0x81,        //   Usage Minimum (0x81)
+    // 0x29, 0xB7,        //   Usage Maximum (0xB7)
+    // 0x16, 0x81, 0x00,  //   Logical Minimum (129)
+    // 0x26, 0xB7, 0x00,  //   Logical Maximum (183)
+    // 0x75, 0x08,        //   Report Size (8)
+    // 0x95, 0x01,        //   Report Count (1)
+    // 0x81, 0x00,        //   Input (Data,Array,Abs)
+    // 0x06, 0x00, 0xFF,  //   Usage Page (Vendor Defined 0xFF00)
+    // 0x09, 0x01,        //   Usage (0x01)
+    // 0x15, 0x00,        //   Logical Minimum (0)
+    // 0x26, 0xFF, 0x00,  //   Logical Maximum (255)
+    // 0x81, 0x02,        //   Input (Data,Var,Abs)
+    // 0xC0,              // End Collection
+    #[gen_hid_descriptor(
+        (collection = APPLICATION, usage_page = GENERIC_DESKTOP, usage = SYSTEM_CONTROL) = {
+            (usage_min = 0x81, usage_max = 0xB7, logical_min = 0x81, logical_max = 0xB7) = {
+                #[item_settings data,array,absolute,not_null] usage_id=input;
+            };
+            (usage_page = VENDOR_DEFINED_START, usage = 0x01) = {
+                level=input;
+            };
+        }
+    )]
+    #[allow(dead_code)]
+    struct CustomClampedSystemControl {
+        usage_id: u8,
+        level: u8,
+    }
+
+    #[test]
+    fn test_group_logical_max() {
+        let expected: &[u8] = &[
+            0x05, 0x01, 0x09, 0x80, 0xA1, 0x01, 0x19, 0x81, 0x29, 0xB7, 0x16, 0x81, 0x00, 0x26,
+            0xB7, 0x00, 0x75, 0x08, 0x95, 0x01, 0x81, 0x00, 0x06, 0x00, 0xFF, 0x09, 0x01, 0x15,
+            0x00, 0x26, 0xFF, 0x00, 0x81, 0x02, 0xC0,
+        ];
+        assert_eq!(CustomClampedSystemControl::desc(), expected);
+    }
+
+    // This should generate the following descriptor:
+    // 0x06, 0x00, 0xFF,              // Usage Page (Vendor Defined 0xFF00)
+    // 0x0B, 0x01, 0x00, 0x42, 0xFF,  // Usage (Vendor 0xFF42: 0x01)
+    // 0xA1, 0x01,                    // Collection (Application)
+    // 0x09, 0x02,                    //   Usage (0x02)
+    // 0x15, 0x00,                    //   Logical Minimum (0)
+    // 0x26, 0xFF, 0x00,              //   Logical Maximum (255)
+    // 0x75, 0x08,                    //   Report Size (8)
+    // 0x95, 0x01,                    //   Report Count (1)
+    // 0x81, 0x02,                    //   Input (Data,Var,Abs)
+    // 0xC0,                          // End Collection
+    #[gen_hid_descriptor(
+        (collection = APPLICATION, usage_page = VENDOR_DEFINED_START, usage = 0xFF420001) = {
+            (usage = 0x02,) = {
+                data=input;
+            };
+        }
+    )]
+    #[allow(dead_code)]
+    struct CustomExtendedApplicationUsage {
+        data: u8,
+    }
+
+    #[test]
+    fn test_extended_application_usage() {
+        let expected: &[u8] = &[
+            0x06, 0x00, 0xFF, 0x0B, 0x01, 0x00, 0x42, 0xFF, 0xA1, 0x01, 0x09, 0x02, 0x15, 0x00,
+            0x26, 0xFF, 0x00, 0x75, 0x08, 0x95, 0x01, 0x81, 0x02, 0xC0,
+        ];
+        assert_eq!(CustomExtendedApplicationUsage::desc(), expected);
+        // The extended usage selects its own page, regardless of the Usage Page global.
+        let desc = crate::descriptor::describe(CustomExtendedApplicationUsage::desc());
+        assert_eq!(desc.lines().nth(1), Some("Usage (0xFF420001)"));
+    }
+
+    #[gen_hid_descriptor(
+        (collection = APPLICATION, usage_page = VENDOR_DEFINED_START, usage = 0x01) = {
+            (usage = 0x02,) = {
+                reading=input;
+            };
+            (usage = 0x03,) = {
+                mode=output;
+            };
+            (usage = 0x04,) = {
+                #[item_settings data,variable,absolute,not_volatile] calibration=feature;
+            };
+            (usage = 0x05,) = {
+                #[item_settings constant,variable,absolute] serial=feature;
+            };
+        }
+    )]
+    #[allow(dead_code)]
+    struct CustomCalibration {
+        reading: i16,
+        mode: u8,
+        calibration: [i16; 3],
+        serial: u32,
+    }
+
+    #[test]
+    fn test_feature_calibration() {
+        use crate::descriptor::AsFeatureReport;
+        use crate::hid_class::HIDClass;
+        use crate::test_bus::*;
+
+        // Feature fields are emitted as Feature items, in their own report.
+        let desc = CustomCalibration::desc();
+        assert_eq!(
+            desc[34..48],
+            [0x16, 0x01, 0x80, 0x26, 0xFF, 0x7F, 0x75, 0x10, 0x95, 0x03, 0xB1, 0x02, 0x09, 0x05]
+        );
+        assert_eq!(desc[desc.len() - 3..], [0xB1, 0x03, 0xC0]);
+        assert_eq!(CustomCalibration::MAX_INPUT_LEN, 2);
+        assert_eq!(CustomCalibration::MAX_FEATURE_LEN, 10);
+        assert_eq!(
+            CustomCalibration::expected_set_report_len(0, ReportType::Feature),
+            Some(10)
+        );
+
+        let alloc = test_alloc();
+        let mut hid: HIDClass<_, 0, 1, 0> =
+            HIDClass::new(&alloc, CustomCalibration::desc(), 10).with_storage();
+        let mut dev = test_device(&alloc);
+
+        // SET_FEATURE: the host stores new calibration data.
+        let calibration = [0x10, 0x00, 0xF0, 0xFF, 0x00, 0x80, 0x78, 0x56, 0x34, 0x12];
+        set_report(&mut dev, &mut hid, ReportType::Feature, 0, &calibration).unwrap();
+        let mut device: CustomCalibration = hid.pull_feature().unwrap();
+        assert_eq!({ device.calibration }, [16, -16, i16::MIN]);
+        assert_eq!({ device.serial }, 0x12345678);
+        assert_eq!(({ device.reading }, device.mode), (0, 0));
+
+        // GET_FEATURE: only the feature fields are returned, not the input or output ones.
+        device.reading = 0x7FFF;
+        device.mode = 0x01;
+        device.calibration[1] = 0;
+        let mut buf = [0u8; 16];
+        assert_eq!(device.serialize_feature_into(&mut buf), Ok(10));
+        assert_eq!(hid.push_feature(&device), Ok(10));
+        let expected = [0x10, 0x00, 0x00, 0x00, 0x00, 0x80, 0x78, 0x56, 0x34, 0x12];
+        assert_eq!(buf[..10], expected);
+        assert_eq!(
+            get_report(&mut dev, &mut hid, ReportType::Feature, 0, 10),
+            Ok(expected.to_vec())
+        );
+    }
+
+    #[test]
+    fn test_xbox_gamepad_report() {
+        use crate::descriptor::{describe, XboxGamepadReport};
+
+        let desc = XboxGamepadReport::desc();
+        assert_eq!(desc[..6], [0x05, 0x01, 0x09, 0x05, 0xA1, 0x01]);
+        // The D-pad is a hat switch with a null state, in degrees.
+        #[rustfmt::skip]
+        let hat = [
+            0x09, 0x39, 0x15, 0x01, 0x25, 0x08, 0x46, 0x3B, 0x01, 0x65, 0x14, 0x75, 0x08,
+            0x81, 0x42,
+        ];
+        assert!(desc.windows(hat.len()).any(|w| w == hat));
+        // The unit and physical range only apply to the hat switch.
+        let buttons = describe(desc);
+        let buttons: std::vec::Vec<&str> = buttons
+            .lines()
+            .map(str::trim)
+            .skip_while(|l| *l != "Usage Page (Button)")
+            .take(7)
+            .collect();
+        assert_eq!(
+            buttons,
+            [
+                "Usage Page (Button)",
+                "Usage Minimum (0x01)",
+                "Usage Maximum (0x0A)",
+                "Logical Minimum (0)",
+                "Logical Maximum (1)",
+                "Physical Maximum (0)",
+                "Unit (0x0)",
+            ]
+        );
+        assert_eq!(XboxGamepadReport::MAX_INPUT_LEN, 16);
+
+        let mut report = XboxGamepadReport::default();
+        report.left_stick[0] = 0xFFFF;
+        report.right_trigger = 1023;
+        report.dpad = 3;
+        report.buttons = XboxGamepadReport::BUTTON_A | XboxGamepadReport::BUTTON_RIGHT_STICK;
+        report.view = 1;
+        assert_eq!(
+            report.to_array(),
+            [
+                0xFF, 0xFF, 0x00, 0x80, 0x00, 0x80, 0x00, 0x80, 0x00, 0x00, 0xFF, 0x03, 0x03, 0x01,
+                0x02, 0x01
+            ]
+        );
+    }
+
+    // This should generate the following descriptor:
+    // 0x06, 0x00, 0xFF,  // Usage Page (Vendor Defined 0xFF00)
+    // 0x09, 0x01,        // Usage (0x01)
+    // 0xA1, 0x01,        // Collection (Application)
+    // 0x19, 0x10,        //   Usage Minimum (0x10)
+    // 0x29, 0x14,        //   Usage Maximum (0x14)
+    // 0x49, 0x01,        //   Designator Minimum (1)
+    // 0x59, 0x05,        //   Designator Maximum (5)
+    // 0x15, 0x00,        //   Logical Minimum (0)
+    // 0x26, 0xFF, 0x00,  //   Logical Maximum (255)
+    // 0x75, 0x08,        //   Report Size (8)
+    // 0x95, 0x05,        //   Report Count (5)
+    // 0x81, 0x02,        //   Input (Data,Var,Abs)
+    // 0x09, 0x20,        //   Usage (0x20)
+    // 0x39, 0x06,        //   Designator Index (6)
+    // 0x95, 0x01,        //   Report Count (1)
+    // 0x81, 0x02,        //   Input (Data,Var,Abs)
+    // 0xC0,              // End Collection
+    #[gen_hid_descriptor(
+        (collection = APPLICATION, usage_page = VENDOR_DEFINED_START, usage = 0x01) = {
+            #[usage_min 0x10] #[usage_max 0x14] #[designator_min 1] #[designator_max 5] finger_bend=input;
+            #[usage 0x20] #[designator 6] wrist=input;
+        }
+    )]
+    #[allow(dead_code)]
+    struct CustomGlove {
+        finger_bend: [u8; 5],
+        wrist: u8,
+    }
+
+    #[test]
+    fn test_designator_range() {
+        let expected: &[u8] = &[
+            0x06, 0x00, 0xFF, 0x09, 0x01, 0xA1, 0x01, 0x19, 0x10, 0x29, 0x14, 0x49, 0x01, 0x59,
+            0x05, 0x15, 0x00, 0x26, 0xFF, 0x00, 0x75, 0x08, 0x95, 0x05, 0x81, 0x02, 0x09, 0x20,
+            0x39, 0x06, 0x95, 0x01, 0x81, 0x02, 0xC0,
+        ];
+        assert_eq!(CustomGlove::desc(), expected);
+
+        let expected: &[u8] = &[
+            0x06, 0x00, 0xFF, 0x09, 0x01, 0xA1, 0x01, 0x19, 0x10, 0x29, 0x14, 0x49, 0x01, 0x59,
+            0x05, 0x15, 0x00, 0x26, 0xFF, 0x00, 0x75, 0x08, 0x95, 0x02, 0x81, 0x00, 0xC0,
+        ];
+        assert_eq!(CustomGloveArray::desc(), expected);
+    }
+
+    // The pressed-finger array selects its designator from the range, so the range need not
+    // match the two elements of the field.
+    #[gen_hid_descriptor(
+        (collection = APPLICATION, usage_page = VENDOR_DEFINED_START, usage = 0x01) = {
+            #[usage_min 0x10] #[usage_max 0x14] #[designator_min 1] #[designator_max 5] #[item_settings data,array,absolute] pressed=input;
+        }
+    )]
+    #[allow(dead_code)]
+    struct CustomGloveArray {
+        pressed: [u8; 2],
+    }
+
+    // This should generate the following descriptor:
+    // 0x05, 0x0C,                    // Usage Page (Consumer)
+    // 0x09, 0x01,                    // Usage (Consumer Control)
+    // 0x85, 0x03,                    // Report ID (3)
+    // 0xA1, 0x01,                    // Collection (Application)
+    // 0x05, 0x0C,                    //   Usage Page (Consumer)
+    // 0x19, 0x00,                    //   Usage Minimum (0x00)
+    // 0x2A, 0x14, 0x05,              //   Usage Maximum (0x514)
+    // 0x15, 0x00,                    //   Logical Minimum (0)
+    // 0x27, 0xFF, 0xFF, 0x00, 0x00,  //   Logical Maximum (65535)
+    // 0x75, 0x10,                    //   Report Size (16)
+    // 0x95, 0x01,                    //   Report Count (1)
+    // 0x81, 0x00,                    //   Input (Data,Array,Abs)
+    // 0x05, 0x0C,                    //   Usage Page (Consumer)
+    // 0x09, 0xE2,                    //   Usage (Mute)
+    // 0x09, 0xE9,                    //   Usage (Volume Increment)
+    // 0x09, 0xEA,                    //   Usage (Volume Decrement)
+    // 0x25, 0x01,                    //   Logical Maximum (1)
+    // 0x75, 0x01,                    //   Report Size (1)
+    // 0x95, 0x03,                    //   Report Count (3)
+    // 0x81, 0x02,                    //   Input (Data,Var,Abs)
+    // 0x95, 0x05,                    //   Report Count (5)
+    // 0x81, 0x03,                    //   Input (Const,Var,Abs)
+    // 0xC0,                          // End Collection
+    #[gen_hid_descriptor(
+        (collection = APPLICATION, usage_page = CONSUMER, usage = CONSUMER_CONTROL, report_id = 0x03) = {
+            (usage_page = CONSUMER, usage_min = 0x00, usage_max = 0x514) = {
+                #[item_settings data,array,absolute,not_null] usage_id=input;
+            };
+            (usage_page = CONSUMER, usage = 0xE2, usage = VOLUME_INCREMENT, usage = VOLUME_DECREMENT) = {
+                #[packed_bits 3] #[item_settings data,variable,absolute] controls=input;
+            };
+        }
+    )]
+    #[allow(dead_code)]
+    struct CustomConsumerArrayAndBitmap {
+        usage_id: u16,
+        controls: u8,
+    }
+
+    #[test]
+    fn test_consumer_array_and_bitmap() {
+        use crate::descriptor::{describe, report_ids};
+
+        let expected: &[u8] = &[
+            0x05, 0x0C, 0x09, 0x01, 0x85, 0x03, 0xA1, 0x01, 0x05, 0x0C, 0x19, 0x00, 0x2A, 0x14,
+            0x05, 0x15, 0x00, 0x27, 0xFF, 0xFF, 0x00, 0x00, 0x75, 0x10, 0x95, 0x01, 0x81, 0x00,
+            0x05, 0x0C, 0x09, 0xE2, 0x09, 0xE9, 0x09, 0xEA, 0x25, 0x01, 0x75, 0x01, 0x95, 0x03,
+            0x81, 0x02, 0x95, 0x05, 0x81, 0x03, 0xC0,
+        ];
+        let desc = CustomConsumerArrayAndBitmap::desc();
+        assert_eq!(desc, expected);
+        assert_eq!(report_ids::<4>(desc).unwrap().as_slice(), &[0x03]);
+        assert!(describe(desc).contains("Input (Data,Array,Abs)"));
+        assert!(describe(desc).contains("Input (Data,Var,Abs)"));
+    }
+
+    #[test]
+    fn test_consumer_array_and_bitmap_combined_press() {
+        // Play/Pause from the array, held together with Volume Increment from the bitmap.
+        let report = CustomConsumerArrayAndBitmap {
+            usage_id: 0xCD,
+            controls: 0b010,
+        };
+        let mut buf = [0u8; 8];
+        assert_eq!(report.serialize_with_id(0x03, &mut buf), Ok(4));
+        assert_eq!(buf[..4], [0x03, 0xCD, 0x00, 0x02]);
+
+        let released = CustomConsumerArrayAndBitmap {
+            usage_id: 0,
+            controls: 0,
+        };
+        assert_eq!(released.serialize_with_id(0x03, &mut buf), Ok(4));
+        assert_eq!(buf[..4], [0x03, 0x00, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn test_protocol_requests() {
+        use crate::hid_class::{
+            HIDClass, HidClassSettings, HidProtocol, HidProtocolMode, HidSubClass,
+        };
+        use crate::test_bus::*;
+        use usb_device::class_prelude::UsbClass;
+        use usb_device::UsbError;
+
+        let alloc = test_alloc();
+        let mut hid = HIDClass::new_with_settings(
+            &alloc,
+            KeyboardReport::desc(),
+            10,
+            HidClassSettings::default()
+                .with_subclass(HidSubClass::Boot)
+                .with_protocol(HidProtocol::Keyboard),
+        );
+        let mut dev = test_device(&alloc);
+
+        assert_eq!(hid.get_protocol_mode(), Ok(HidProtocolMode::Report));
+        assert_eq!(
+            control_in(
+                &mut dev,
+                &mut hid,
+                REQ_TYPE_CLASS_INTERFACE_IN,
+                HID_REQ_GET_PROTOCOL,
+                0,
+                0,
+                1
+            ),
+            Ok(std::vec![HidProtocolMode::Report as u8])
+        );
+
+        // A BIOS selects the boot protocol.
+        control_out(
+            &mut dev,
+            &mut hid,
+            REQ_TYPE_CLASS_INTERFACE_OUT,
+            HID_REQ_SET_PROTOCOL,
+            HidProtocolMode::Boot as u16,
+            0,
+            &[],
+        )
+        .unwrap();
+        assert_eq!(hid.get_protocol_mode(), Ok(HidProtocolMode::Boot));
+        assert_eq!(
+            control_in(
+                &mut dev,
+                &mut hid,
+                REQ_TYPE_CLASS_INTERFACE_IN,
+                HID_REQ_GET_PROTOCOL,
+                0,
+                0,
+                1
+            ),
+            Ok(std::vec![HidProtocolMode::Boot as u8])
+        );
+
+        // Report protocol is restored after a bus reset.
+        hid.reset();
+        assert_eq!(hid.get_protocol_mode(), Ok(HidProtocolMode::Report));
+
+        // Devices without a boot protocol reject the requests.
+        let alloc = test_alloc();
+        let mut hid = HIDClass::new(&alloc, MouseReport::desc(), 10);
+        let mut dev = test_device(&alloc);
+        assert_eq!(hid.get_protocol_mode(), Err(UsbError::Unsupported));
+        assert_eq!(
+            control_in(
+                &mut dev,
+                &mut hid,
+                REQ_TYPE_CLASS_INTERFACE_IN,
+                HID_REQ_GET_PROTOCOL,
+                0,
+                0,
+                1
+            ),
+            Err(UsbError::InvalidState)
+        );
+    }
+
+    #[test]
+    fn test_top_collection() {
+        use crate::descriptor::{KeyboardWithMediaReport, PrecisionTouchpadReport};
+
+        assert_eq!(MouseReport::TOP_COLLECTION, Some(0x01));
+        assert_eq!(HighResScrollMouseReport::TOP_COLLECTION, Some(0x01));
+        assert_eq!(CustomPhysicalJoystick::TOP_COLLECTION, Some(0x01));
+        // Several top-level collections, told apart by report IDs.
+        assert_eq!(KeyboardWithMediaReport::TOP_COLLECTION, None);
+        assert_eq!(PrecisionTouchpadReport::TOP_COLLECTION, None);
+    }
+
+    #[test]
+    fn test_default_storage() {
+        use crate::hid_class::HIDClass;
+        use crate::test_bus::{test_alloc, test_device, TestBus};
+        use usb_device::UsbError;
+
+        // The optional report stores take no memory unless enabled: 4 staged input reports,
+        // 4 GET_REPORT responses of at least 128 bytes and 64 bytes of last input report.
+        let default_size = core::mem::size_of::<HIDClass<TestBus>>();
+        let enabled_size = core::mem::size_of::<HIDClass<TestBus, 4, 4, 64>>();
+        assert!(enabled_size - default_size >= 4 * 64 + 4 * 128 + 64);
+
+        let alloc = test_alloc();
+        let mut hid = HIDClass::new_ep_in(&alloc, MouseReport::desc(), 10);
+        let dev = test_device(&alloc);
+
+        let report = MouseReport {
+            buttons: 0,
+            x: 0,
+            y: 0,
+            wheel: 0,
+            pan: 0,
+        };
+        assert_eq!(hid.push_input_queued(&report), Ok(5));
+        assert_eq!(hid.push_input_queued(&report), Err(UsbError::WouldBlock));
+        dev.bus().host_read(1).unwrap();
+
+        // Methods relying on a disabled store fail rather than silently doing nothing.
+        assert_eq!(
+            hid.push_input_if_changed(&report),
+            Err(UsbError::Unsupported)
+        );
+        assert_eq!(
+            hid.set_get_report_response(ReportType::Feature, 0, &[0x01]),
+            Err(UsbError::Unsupported)
+        );
+        assert_eq!(
+            hid.push_feature(&CustomConfigReport {
+                status: 0,
+                sensitivity: 0,
+                flags: 0,
+                offset: 0,
+            }),
+            Err(UsbError::Unsupported)
+        );
+        assert_eq!(dev.bus().host_read(1), None);
+    }
+
+    #[test]
+    fn test_last_input_oversized() {
+        use crate::hid_class::HIDClass;
+        use crate::test_bus::{test_alloc, test_device};
+
+        let alloc = test_alloc();
+        let mut hid: HIDClass<_, 0, 0, 4> =
+            HIDClass::new_ep_in(&alloc, MouseReport::desc(), 10).with_storage();
+        let dev = test_device(&alloc);
+
+        assert_eq!(hid.push_raw_input(&[0x01, 0x02]), Ok(2));
+        assert_eq!(hid.last_input().as_deref(), Some(&[0x01, 0x02][..]));
+        dev.bus().host_read(1).unwrap();
+
+        // A report which doesn't fit clears the last one, rather than leaving it stale.
+        let report = MouseReport {
+            buttons: 0,
+            x: 0,
+            y: 0,
+            wheel: 0,
+            pan: 0,
+        };
+        assert_eq!(hid.push_input(&report), Ok(5));
+        assert_eq!(hid.last_input(), None);
+        dev.bus().host_read(1).unwrap();
+        assert_eq!(hid.push_input_if_changed(&report), Ok(5));
+    }
 }
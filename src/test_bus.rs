@@ -0,0 +1,380 @@
+//! A simulated UsbBus, used to drive HIDClass through control & interrupt transfers in tests.
+use std::sync::Mutex;
+use std::vec::Vec;
+
+use usb_device::bus::PollResult;
+use usb_device::class_prelude::*;
+use usb_device::device::{UsbDevice, UsbDeviceBuilder, UsbVidPid};
+use usb_device::endpoint::EndpointType;
+use usb_device::{Result, UsbDirection, UsbError};
+
+use crate::hid_class::ReportType;
+
+const NUM_ENDPOINTS: usize = 16;
+
+/// bmRequestType values used by the host in SETUP packets.
+pub const REQ_TYPE_CLASS_INTERFACE_IN: u8 = 0xa1;
+pub const REQ_TYPE_CLASS_INTERFACE_OUT: u8 = 0x21;
+pub const REQ_TYPE_STANDARD_DEVICE_IN: u8 = 0x80;
+pub const REQ_TYPE_STANDARD_INTERFACE_IN: u8 = 0x81;
+
+/// Standard GET_DESCRIPTOR request, see (9.4.3): USB 2.0 specification
+pub const REQ_GET_DESCRIPTOR: u8 = 0x06;
+pub const DESC_TYPE_CONFIGURATION: u8 = 0x02;
+pub const DESC_TYPE_INTERFACE: u8 = 0x04;
+pub const DESC_TYPE_ENDPOINT: u8 = 0x05;
+
+/// HID class requests, see (7.2): <https://www.usb.org/sites/default/files/hid1_11.pdf>
+pub const HID_REQ_GET_REPORT: u8 = 0x01;
+pub const HID_REQ_GET_IDLE: u8 = 0x02;
+pub const HID_REQ_GET_PROTOCOL: u8 = 0x03;
+pub const HID_REQ_SET_REPORT: u8 = 0x09;
+pub const HID_REQ_SET_IDLE: u8 = 0x0a;
+pub const HID_REQ_SET_PROTOCOL: u8 = 0x0b;
+
+#[derive(Default, Clone)]
+pub struct EndpointState {
+    pub allocated: bool,
+    pub ep_type: Option<EndpointType>,
+    pub max_packet_size: u16,
+    pub interval: u8,
+    pub stalled: bool,
+    /// Packet waiting to be read by the device (OUT) or host (IN).
+    pub packet: Option<Vec<u8>>,
+    pub setup: bool,
+    pub in_complete: bool,
+}
+
+#[derive(Default)]
+struct State {
+    out_eps: [EndpointState; NUM_ENDPOINTS],
+    in_eps: [EndpointState; NUM_ENDPOINTS],
+}
+
+impl State {
+    fn ep(&mut self, addr: EndpointAddress) -> &mut EndpointState {
+        match addr.direction() {
+            UsbDirection::Out => &mut self.out_eps[addr.index()],
+            UsbDirection::In => &mut self.in_eps[addr.index()],
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct TestBus {
+    state: Mutex<State>,
+}
+
+impl TestBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a copy of the state of the given endpoint.
+    pub fn endpoint(&self, addr: EndpointAddress) -> EndpointState {
+        self.state.lock().unwrap().ep(addr).clone()
+    }
+
+    /// Queues a packet from the host on the given OUT endpoint.
+    pub fn host_write(&self, index: usize, data: &[u8], setup: bool) {
+        let mut state = self.state.lock().unwrap();
+        if setup {
+            // A SETUP packet always clears a stalled control endpoint.
+            state.out_eps[index].stalled = false;
+            state.in_eps[index].stalled = false;
+        }
+        let ep = &mut state.out_eps[index];
+        ep.packet = Some(Vec::from(data));
+        ep.setup = setup;
+    }
+
+    /// Takes the packet pending on the given IN endpoint, as if the host read it.
+    pub fn host_read(&self, index: usize) -> Option<Vec<u8>> {
+        let mut state = self.state.lock().unwrap();
+        let ep = &mut state.in_eps[index];
+        let packet = ep.packet.take();
+        if packet.is_some() {
+            ep.in_complete = true;
+        }
+        packet
+    }
+
+    fn stalled(&self, index: usize) -> bool {
+        let state = self.state.lock().unwrap();
+        state.out_eps[index].stalled || state.in_eps[index].stalled
+    }
+}
+
+impl UsbBus for TestBus {
+    fn alloc_ep(
+        &mut self,
+        ep_dir: UsbDirection,
+        ep_addr: Option<EndpointAddress>,
+        ep_type: EndpointType,
+        max_packet_size: u16,
+        interval: u8,
+    ) -> Result<EndpointAddress> {
+        let mut state = self.state.lock().unwrap();
+        let index = match ep_addr {
+            Some(addr) => addr.index(),
+            None => (1..NUM_ENDPOINTS)
+                .find(|i| !state.ep(EndpointAddress::from_parts(*i, ep_dir)).allocated)
+                .ok_or(UsbError::EndpointOverflow)?,
+        };
+        let addr = EndpointAddress::from_parts(index, ep_dir);
+        let ep = state.ep(addr);
+        if ep.allocated {
+            return Err(UsbError::InvalidEndpoint);
+        }
+        ep.allocated = true;
+        ep.ep_type = Some(ep_type);
+        ep.max_packet_size = max_packet_size;
+        ep.interval = interval;
+        Ok(addr)
+    }
+
+    fn enable(&mut self) {}
+
+    fn reset(&self) {}
+
+    fn set_device_address(&self, _addr: u8) {}
+
+    fn write(&self, ep_addr: EndpointAddress, buf: &[u8]) -> Result<usize> {
+        let mut state = self.state.lock().unwrap();
+        let ep = state.ep(ep_addr);
+        if !ep.allocated {
+            return Err(UsbError::InvalidEndpoint);
+        }
+        if ep.packet.is_some() {
+            return Err(UsbError::WouldBlock);
+        }
+        if buf.len() > ep.max_packet_size as usize {
+            return Err(UsbError::BufferOverflow);
+        }
+        ep.packet = Some(Vec::from(buf));
+        Ok(buf.len())
+    }
+
+    fn read(&self, ep_addr: EndpointAddress, buf: &mut [u8]) -> Result<usize> {
+        let mut state = self.state.lock().unwrap();
+        let ep = state.ep(ep_addr);
+        if !ep.allocated {
+            return Err(UsbError::InvalidEndpoint);
+        }
+        let packet = ep.packet.as_ref().ok_or(UsbError::WouldBlock)?;
+        if packet.len() > buf.len() {
+            return Err(UsbError::BufferOverflow);
+        }
+        let len = packet.len();
+        buf[..len].copy_from_slice(packet);
+        ep.packet = None;
+        ep.setup = false;
+        Ok(len)
+    }
+
+    fn set_stalled(&self, ep_addr: EndpointAddress, stalled: bool) {
+        self.state.lock().unwrap().ep(ep_addr).stalled = stalled;
+    }
+
+    fn is_stalled(&self, ep_addr: EndpointAddress) -> bool {
+        self.state.lock().unwrap().ep(ep_addr).stalled
+    }
+
+    fn suspend(&self) {}
+
+    fn resume(&self) {}
+
+    fn poll(&self) -> PollResult {
+        let mut state = self.state.lock().unwrap();
+        let (mut ep_out, mut ep_in_complete, mut ep_setup) = (0u16, 0u16, 0u16);
+        for i in 0..NUM_ENDPOINTS {
+            let out = &state.out_eps[i];
+            if out.packet.is_some() {
+                if out.setup {
+                    ep_setup |= 1 << i;
+                } else {
+                    ep_out |= 1 << i;
+                }
+            }
+            let ep_in = &mut state.in_eps[i];
+            if ep_in.in_complete {
+                ep_in.in_complete = false;
+                ep_in_complete |= 1 << i;
+            }
+        }
+        if ep_out | ep_in_complete | ep_setup == 0 {
+            PollResult::None
+        } else {
+            PollResult::Data {
+                ep_out,
+                ep_in_complete,
+                ep_setup,
+            }
+        }
+    }
+}
+
+/// Returns an allocator over a fresh TestBus, for the classes under test to allocate
+/// their endpoints from.
+pub fn test_alloc() -> UsbBusAllocator<TestBus> {
+    UsbBusAllocator::new(TestBus::new())
+}
+
+/// Builds the device, once the classes under test have allocated their endpoints.
+pub fn test_device(alloc: &UsbBusAllocator<TestBus>) -> UsbDevice<'_, TestBus> {
+    UsbDeviceBuilder::new(alloc, UsbVidPid(0x1209, 0x0001)).build()
+}
+
+fn poll<C: UsbClass<TestBus>>(dev: &mut UsbDevice<TestBus>, class: &mut C) {
+    dev.poll(&mut [class]);
+}
+
+fn setup_packet(request_type: u8, request: u8, value: u16, index: u16, length: u16) -> [u8; 8] {
+    let mut setup = [request_type, request, 0, 0, 0, 0, 0, 0];
+    setup[2..4].copy_from_slice(&value.to_le_bytes());
+    setup[4..6].copy_from_slice(&index.to_le_bytes());
+    setup[6..8].copy_from_slice(&length.to_le_bytes());
+    setup
+}
+
+/// Performs a control transfer with a host-to-device data stage.
+/// Returns Err(InvalidState) if the device stalled the request.
+pub fn control_out<C: UsbClass<TestBus>>(
+    dev: &mut UsbDevice<TestBus>,
+    class: &mut C,
+    request_type: u8,
+    request: u8,
+    value: u16,
+    index: u16,
+    data: &[u8],
+) -> Result<()> {
+    let setup = setup_packet(request_type, request, value, index, data.len() as u16);
+    dev.bus().host_write(0, &setup, true);
+    poll(dev, class);
+
+    let max_packet_size = dev
+        .bus()
+        .endpoint(EndpointAddress::from(0x00))
+        .max_packet_size;
+    for chunk in data.chunks(max_packet_size as usize) {
+        dev.bus().host_write(0, chunk, false);
+        poll(dev, class);
+    }
+    if dev.bus().stalled(0) {
+        return Err(UsbError::InvalidState);
+    }
+
+    // Status stage.
+    match dev.bus().host_read(0) {
+        Some(status) if status.is_empty() => {
+            poll(dev, class);
+            Ok(())
+        }
+        _ => Err(UsbError::InvalidState),
+    }
+}
+
+/// Performs a control transfer with a device-to-host data stage, returning the data.
+/// Returns Err(InvalidState) if the device stalled the request.
+pub fn control_in<C: UsbClass<TestBus>>(
+    dev: &mut UsbDevice<TestBus>,
+    class: &mut C,
+    request_type: u8,
+    request: u8,
+    value: u16,
+    index: u16,
+    length: u16,
+) -> Result<Vec<u8>> {
+    let setup = setup_packet(request_type, request, value, index, length);
+    dev.bus().host_write(0, &setup, true);
+    poll(dev, class);
+
+    let max_packet_size = dev
+        .bus()
+        .endpoint(EndpointAddress::from(0x80))
+        .max_packet_size;
+    let mut data = Vec::new();
+    loop {
+        if dev.bus().stalled(0) {
+            return Err(UsbError::InvalidState);
+        }
+        let packet = dev.bus().host_read(0).ok_or(UsbError::WouldBlock)?;
+        data.extend_from_slice(&packet);
+        poll(dev, class);
+        if packet.len() < max_packet_size as usize || data.len() >= length as usize {
+            break;
+        }
+    }
+
+    // Status stage.
+    dev.bus().host_write(0, &[], false);
+    poll(dev, class);
+    Ok(data)
+}
+
+/// Issues a HID SET_REPORT request to interface 0.
+pub fn set_report<C: UsbClass<TestBus>>(
+    dev: &mut UsbDevice<TestBus>,
+    class: &mut C,
+    report_type: ReportType,
+    report_id: u8,
+    data: &[u8],
+) -> Result<()> {
+    let value = (report_type as u16) << 8 | report_id as u16;
+    control_out(
+        dev,
+        class,
+        REQ_TYPE_CLASS_INTERFACE_OUT,
+        HID_REQ_SET_REPORT,
+        value,
+        0,
+        data,
+    )
+}
+
+/// Issues a HID GET_REPORT request to interface 0.
+pub fn get_report<C: UsbClass<TestBus>>(
+    dev: &mut UsbDevice<TestBus>,
+    class: &mut C,
+    report_type: ReportType,
+    report_id: u8,
+    length: u16,
+) -> Result<Vec<u8>> {
+    let value = (report_type as u16) << 8 | report_id as u16;
+    control_in(
+        dev,
+        class,
+        REQ_TYPE_CLASS_INTERFACE_IN,
+        HID_REQ_GET_REPORT,
+        value,
+        0,
+        length,
+    )
+}
+
+/// Reads the full configuration descriptor of the device.
+pub fn get_configuration_descriptor<C: UsbClass<TestBus>>(
+    dev: &mut UsbDevice<TestBus>,
+    class: &mut C,
+) -> Result<Vec<u8>> {
+    control_in(
+        dev,
+        class,
+        REQ_TYPE_STANDARD_DEVICE_IN,
+        REQ_GET_DESCRIPTOR,
+        (DESC_TYPE_CONFIGURATION as u16) << 8,
+        0,
+        255,
+    )
+}
+
+/// Splits a configuration descriptor into its descriptors, returning (type, descriptor) pairs.
+pub fn split_descriptors(mut desc: &[u8]) -> Vec<(u8, &[u8])> {
+    let mut out = Vec::new();
+    while desc.len() >= 2 {
+        let len = desc[0] as usize;
+        out.push((desc[1], &desc[..len]));
+        desc = &desc[len..];
+    }
+    out
+}